@@ -0,0 +1,210 @@
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::tuple::Tuple;
+use super::{Scalar, EPSILON};
+
+// Axis aligned box; an empty box has min above max so that adding any point or box fixes it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        BoundingBox::new(
+            Tuple::point(Scalar::INFINITY, Scalar::INFINITY, Scalar::INFINITY),
+            Tuple::point(Scalar::NEG_INFINITY, Scalar::NEG_INFINITY, Scalar::NEG_INFINITY))
+    }
+}
+
+// Entry and exit t of the ray through the slab min <= axis <= max
+pub fn check_axis(origin: Scalar, direction: Scalar, min: Scalar, max: Scalar) -> (Scalar, Scalar) {
+    let tmin_numerator = min - origin;
+    let tmax_numerator = max - origin;
+    let (tmin, tmax) = if direction.abs() >= EPSILON {
+        (tmin_numerator / direction, tmax_numerator / direction)
+    } else {
+        (tmin_numerator * Scalar::INFINITY, tmax_numerator * Scalar::INFINITY)
+    };
+    if tmin > tmax { (tmax, tmin) } else { (tmin, tmax) }
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple, max: Tuple) -> Self {
+        Self { min, max }
+    }
+
+    pub fn infinite() -> Self {
+        BoundingBox::new(
+            Tuple::point(Scalar::NEG_INFINITY, Scalar::NEG_INFINITY, Scalar::NEG_INFINITY),
+            Tuple::point(Scalar::INFINITY, Scalar::INFINITY, Scalar::INFINITY))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max].iter().all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+    }
+
+    pub fn add_point(&mut self, point: Tuple) {
+        self.min = Tuple::point(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z));
+        self.max = Tuple::point(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z));
+    }
+
+    pub fn add_box(&mut self, other: &BoundingBox) {
+        if !other.is_empty() {
+            self.add_point(other.min);
+            self.add_point(other.max);
+        }
+    }
+
+    pub fn contains_point(&self, point: Tuple) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) &&
+            (self.min.y..=self.max.y).contains(&point.y) &&
+            (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    // Bounds of the eight transformed corners. Transforming an unbounded box would mix infinities
+    // with zero matrix entries, so it stays unbounded.
+    pub fn transform(&self, matrix: Matrix) -> BoundingBox {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return BoundingBox::infinite();
+        }
+        let mut transformed = BoundingBox::default();
+        for &x in [self.min.x, self.max.x].iter() {
+            for &y in [self.min.y, self.max.y].iter() {
+                for &z in [self.min.z, self.max.z].iter() {
+                    transformed.add_point(matrix * Tuple::point(x, y, z));
+                }
+            }
+        }
+        transformed
+    }
+
+    pub fn intersects(&self, ray: Ray) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        tmin <= tmax
+    }
+
+    pub fn centroid(&self) -> Tuple {
+        Tuple::point((self.min.x + self.max.x) / 2., (self.min.y + self.max.y) / 2., (self.min.z + self.max.z) / 2.)
+    }
+
+    // 0, 1 or 2 for x, y or z; ties go to the earlier axis
+    pub fn widest_axis(&self) -> usize {
+        let (dx, dy, dz) = (self.max.x - self.min.x, self.max.y - self.min.y, self.max.z - self.min.z);
+        let widest = dx.max(dy).max(dz);
+        if widest == dx { 0 } else if widest == dy { 1 } else { 2 }
+    }
+
+    // Halves the box across its widest axis
+    pub fn split(&self) -> (BoundingBox, BoundingBox) {
+        let (mut left_max, mut right_min) = (self.max, self.min);
+        match self.widest_axis() {
+            0 => {
+                left_max.x = (self.min.x + self.max.x) / 2.;
+                right_min.x = left_max.x;
+            }
+            1 => {
+                left_max.y = (self.min.y + self.max.y) / 2.;
+                right_min.y = left_max.y;
+            }
+            _ => {
+                left_max.z = (self.min.z + self.max.z) / 2.;
+                right_min.z = left_max.z;
+            }
+        }
+        (BoundingBox::new(self.min, left_max), BoundingBox::new(right_min, self.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::{FRAC_PI_4, SQRT_2};
+
+    #[test]
+    fn empty_box_grows_to_fit_points() {
+        let mut b = BoundingBox::default();
+        assert!(b.is_empty());
+
+        b.add_point(Tuple::point(-5., 2., 0.));
+        b.add_point(Tuple::point(7., 0., -3.));
+
+        assert_eq!(b.min, Tuple::point(-5., 0., -3.));
+        assert_eq!(b.max, Tuple::point(7., 2., 0.));
+    }
+
+    #[test]
+    fn adding_one_box_to_another() {
+        let mut b = BoundingBox::new(Tuple::point(-5., -2., 0.), Tuple::point(7., 4., 4.));
+        b.add_box(&BoundingBox::new(Tuple::point(8., -7., -2.), Tuple::point(14., 2., 8.)));
+        b.add_box(&BoundingBox::default());
+
+        assert_eq!(b.min, Tuple::point(-5., -7., -2.));
+        assert_eq!(b.max, Tuple::point(14., 4., 8.));
+    }
+
+    #[test]
+    fn box_contains_points_and_boxes() {
+        let b = BoundingBox::new(Tuple::point(5., -2., 0.), Tuple::point(11., 4., 7.));
+
+        assert!(b.contains_point(Tuple::point(5., -2., 0.)));
+        assert!(b.contains_point(Tuple::point(8., 1., 3.)));
+        assert!(!b.contains_point(Tuple::point(3., 0., 3.)));
+        assert!(!b.contains_point(Tuple::point(8., 1., 8.)));
+        assert!(b.contains_box(&BoundingBox::new(Tuple::point(6., -1., 1.), Tuple::point(10., 3., 6.))));
+        assert!(!b.contains_box(&BoundingBox::new(Tuple::point(4., -3., -1.), Tuple::point(10., 3., 6.))));
+    }
+
+    #[test]
+    fn transforming_a_box() {
+        let b = BoundingBox::new(Tuple::point(-1., -1., -1.), Tuple::point(1., 1., 1.));
+        let transformed = b.transform(Matrix::rotation_x(FRAC_PI_4) * Matrix::rotation_y(FRAC_PI_4));
+
+        assert_eq!(transformed.min, Tuple::point(-SQRT_2, -1.70711, -1.70711));
+        assert_eq!(transformed.max, Tuple::point(SQRT_2, 1.70711, 1.70711));
+        assert_eq!(BoundingBox::infinite().transform(Matrix::translation(1., 0., 0.)), BoundingBox::infinite());
+    }
+
+    #[test]
+    fn intersecting_ray_with_box() {
+        let b = BoundingBox::new(Tuple::point(5., -2., 0.), Tuple::point(11., 4., 7.));
+        let cases = [
+            (Tuple::point(15., 1., 2.), Tuple::vector(-1., 0., 0.), true),
+            (Tuple::point(8., 2., 12.), Tuple::vector(0., 0., -1.), true),
+            (Tuple::point(8., 1., 3.5), Tuple::vector(0., 0., 1.), true),
+            (Tuple::point(9., -1., -8.), Tuple::vector(2., 4., 6.), false),
+            (Tuple::point(12., 5., 4.), Tuple::vector(-1., 0., 0.), false),
+        ];
+        for (origin, direction, expected) in cases.iter() {
+            assert_eq!(b.intersects(Ray::new(*origin, direction.normalize())), *expected);
+        }
+        assert!(!BoundingBox::default().intersects(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.))));
+    }
+
+    #[test]
+    fn splitting_along_widest_axis() {
+        let (left, right) = BoundingBox::new(Tuple::point(-1., -2., -3.), Tuple::point(9., 5.5, 3.)).split();
+
+        assert_eq!(left.max, Tuple::point(4., 5.5, 3.));
+        assert_eq!(right.min, Tuple::point(4., -2., -3.));
+    }
+}
@@ -2,71 +2,371 @@ use super::canvas::Canvas;
 use super::tuple::{Tuple, ORIGO};
 use super::ray::Ray;
 use super::matrix::Matrix;
-use super::world::World;
+use super::shape::inverse_transform_parameter;
+use super::world::{RenderSettings, World};
+use super::color::{Color, BLACK};
+use super::sampling::Rng;
+use std::sync::Mutex;
+use rayon::prelude::*;
+use super::Scalar;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    Perspective,
+    // Equidistant fisheye: the angle off the view axis grows linearly with distance from the
+    // image center, reaching fov / 2 at the edge of the shorter image side
+    Fisheye { fov: Scalar }
+}
 
 pub struct Camera {
     pub hsize: usize,
     pub vsize: usize,
-    pub field_of_view: f64,
-    pub pixel_size: f64,
-    pub transform: Matrix,
-    half_width: f64,
-    half_height: f64
+    pub field_of_view: Scalar,
+    pub pixel_size: Scalar,
+    transform: Matrix,
+    // Kept alongside transform so ray generation doesn't invert a matrix per pixel
+    inverse_transform: Matrix,
+    pub distortion: Scalar,
+    pub vignette: Scalar,
+    pub projection: Projection,
+    // Lens radius; 0 keeps the pinhole camera
+    pub aperture: Scalar,
+    pub focal_distance: Scalar,
+    half_width: Scalar,
+    half_height: Scalar
 }
 
 impl Camera {
-    pub fn new(hsize: usize, vsize: usize, field_of_view: f64, transform: Option<Matrix>) -> Self {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Scalar, transform: Option<Matrix>) -> Self {
         let half_view = (field_of_view / 2.).tan();
-        let aspect_ratio = hsize as f64 / vsize as f64;
+        let aspect_ratio = hsize as Scalar / vsize as Scalar;
         let (half_width, half_height) = if aspect_ratio >= 1. {
             (half_view, half_view / aspect_ratio)
         } else {
             (half_view * aspect_ratio, half_view)
         };
-        let pixel_size = half_width * 2. / hsize as f64;
+        let pixel_size = half_width * 2. / hsize as Scalar;
         Self {
             hsize,
             vsize,
             field_of_view,
             pixel_size,
             transform: transform.unwrap_or_default(),
+            inverse_transform: inverse_transform_parameter(transform),
+            distortion: 0.,
+            vignette: 0.,
+            projection: Projection::Perspective,
+            aperture: 0.,
+            focal_distance: 1.,
             half_width,
             half_height }
     }
 
+    // Like new, but refuses a transform that can't be inverted instead of building a camera
+    // that panics on its first ray
+    pub fn try_new(hsize: usize, vsize: usize, field_of_view: Scalar, transform: Option<Matrix>) -> Result<Self, String> {
+        match transform {
+            Some(t) if t.inverse().is_none() => Err("camera transform is not invertible".to_string()),
+            _ => Ok(Camera::new(hsize, vsize, field_of_view, transform))
+        }
+    }
+
+    pub fn transformation(&self) -> Matrix {
+        self.transform
+    }
+
+    pub fn inverse_transformation(&self) -> Matrix {
+        self.inverse_transform
+    }
+
+    pub fn set_transformation(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse_transform = inverse_transform_parameter(Some(transform));
+    }
+
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
-        let pixel = self.transform.inverse().unwrap() * Tuple::point(world_x, world_y, -1.);
-        let origin = self.transform.inverse().unwrap() * ORIGO;
+        self.ray_for_subpixel(px, py, 0, 0, 1)
+    }
+
+    // Ray through the center of cell (sub_x, sub_y) when the pixel is split into an n x n grid
+    pub fn ray_for_subpixel(&self, px: usize, py: usize, sub_x: usize, sub_y: usize, n: usize) -> Ray {
+        let n = n.max(1) as Scalar;
+        let xoffset = (px as Scalar + (sub_x as Scalar + 0.5) / n) * self.pixel_size;
+        let yoffset = (py as Scalar + (sub_y as Scalar + 0.5) / n) * self.pixel_size;
+        let (film_x, film_y) = self.distort(self.half_width - xoffset, self.half_height - yoffset);
+        let film_point = match self.projection {
+            Projection::Perspective => Tuple::point(film_x, film_y, -1.),
+            Projection::Fisheye { fov } => {
+                let (x, y) = (film_x / self.half_width.min(self.half_height), film_y / self.half_width.min(self.half_height));
+                let theta = (x * x + y * y).sqrt() * fov / 2.;
+                let phi = y.atan2(x);
+                Tuple::point(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos())
+            }
+        };
+        let pixel = self.inverse_transform * film_point;
+        let origin = self.inverse_transform * ORIGO;
         let direction = (pixel - origin).normalize();
 
         Ray::new(origin, direction)
     }
 
-    pub fn render(&self, world: World) -> Canvas {
+    pub fn lens_ray_for_pixel(&self, px: usize, py: usize, rng: &mut Rng) -> Ray {
+        self.through_lens(self.ray_for_pixel(px, py), rng)
+    }
+
+    // Jitters the origin of a pinhole ray across the lens and re-aims it at the point
+    // focal_distance along the ray, so only that distance stays sharp
+    fn through_lens(&self, ray: Ray, rng: &mut Rng) -> Ray {
+        if self.aperture <= 0. {
+            return ray;
+        }
+        let focal_point = ray.position(self.focal_distance);
+        let (x, y) = rng.unit_disk();
+        let origin = self.inverse_transform * Tuple::point(x * self.aperture, y * self.aperture, 0.);
+
+        Ray::new(origin, (focal_point - origin).normalize())
+    }
+
+    // Radial distortion around the image center, positive is barrel and negative pincushion
+    fn distort(&self, x: Scalar, y: Scalar) -> (Scalar, Scalar) {
+        if self.distortion == 0. {
+            return (x, y);
+        }
+        let r2 = (x * x + y * y) / (self.half_width * self.half_width + self.half_height * self.half_height);
+        let scale = 1. + self.distortion * r2;
+        (x * scale, y * scale)
+    }
+
+    // Cosine-power falloff of the angle between the pixel's film position and the view axis
+    fn vignette_factor(&self, px: usize, py: usize) -> Scalar {
+        if self.vignette == 0. {
+            return 1.;
+        }
+        let x = self.half_width - (px as Scalar + 0.5) * self.pixel_size;
+        let y = self.half_height - (py as Scalar + 0.5) * self.pixel_size;
+        let cos_theta = 1. / (1. + x * x + y * y).sqrt();
+        cos_theta.powf(self.vignette)
+    }
+
+    // Averages lens_samples passes over an n x n grid of rays in the pixel, n being
+    // settings.antialias_samples. With an aperture, each pixel draws its lens positions from its
+    // own stream of the world seed, so the order pixels are rendered in doesn't matter.
+    fn color_for_pixel(&self, world: &World, px: usize, py: usize, lens_samples: usize, settings: &RenderSettings) -> Color {
+        let n = settings.antialias_samples.max(1);
+        let mut rng = Rng::for_pixel(world.seed, px, py);
+        let mut sum = BLACK;
+        for _ in 0..lens_samples {
+            for sub_y in 0..n {
+                for sub_x in 0..n {
+                    let ray = self.through_lens(self.ray_for_subpixel(px, py, sub_x, sub_y, n), &mut rng);
+                    sum = sum + world.color_at_with(ray, settings);
+                }
+            }
+        }
+        sum / (lens_samples * n * n) as Scalar * self.vignette_factor(px, py)
+    }
+
+    pub fn render(&self, world: World, settings: &RenderSettings) -> Canvas {
+        self.render_with_progress(&world, settings, |_, _| {})
+    }
+
+    // Calls on_row(rows_done, total_rows) after each finished scanline
+    pub fn render_with_progress(&self, world: &World, settings: &RenderSettings, mut on_row: impl FnMut(usize, usize)) -> Canvas {
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                image.write_pixel(x, y, self.color_for_pixel(world, x, y, 1, settings));
+            }
+            on_row(y + 1, self.vsize);
+        }
+        image
+    }
+
+    // Renders the world, then draws the edges of each object's bounding box over it to check where
+    // things are. Unbounded objects are skipped, as are edges reaching behind the camera; the
+    // boxes are placed with the plain perspective projection, ignoring distortion and fisheye.
+    pub fn render_with_bounds_overlay(&self, world: &World, settings: &RenderSettings, color: Color) -> Canvas {
+        let mut image = self.render_with_progress(world, settings, |_, _| {});
+        for object in world.objects() {
+            let bounds = object.parent_space_bounds();
+            if bounds.is_empty() || !bounds.is_finite() {
+                continue;
+            }
+            // Corner i takes max on the axes whose bit is set in i: 1 for x, 2 for y, 4 for z
+            let corners: Vec<Option<(Scalar, Scalar)>> = (0..8)
+                .map(|i| Tuple::point(
+                    if i & 1 == 0 { bounds.min.x } else { bounds.max.x },
+                    if i & 2 == 0 { bounds.min.y } else { bounds.max.y },
+                    if i & 4 == 0 { bounds.min.z } else { bounds.max.z }))
+                .map(|corner| self.project(corner))
+                .collect();
+            for i in 0..8 {
+                for bit in [1, 2, 4].iter().filter(|&&bit| i & bit == 0) {
+                    if let (Some(from), Some(to)) = (corners[i], corners[i | bit]) {
+                        image.draw_line(from, to, color);
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    // Inverse of ray_for_pixel for the perspective projection, giving fractional pixel
+    // coordinates; None for points behind the camera
+    fn project(&self, world_point: Tuple) -> Option<(Scalar, Scalar)> {
+        let p = self.transform * world_point;
+        if p.z >= 0. {
+            return None;
+        }
+        let (film_x, film_y) = (p.x / -p.z, p.y / -p.z);
+        Some(((self.half_width - film_x) / self.pixel_size - 0.5, (self.half_height - film_y) / self.pixel_size - 0.5))
+    }
+
+    fn render_tile(&self, world: &World, settings: &RenderSettings, x0: usize, y0: usize, width: usize, height: usize) -> Canvas {
+        let mut tile = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                tile.write_pixel(x, y, self.color_for_pixel(world, x0 + x, y0 + y, 1, settings));
+            }
+        }
+        tile
+    }
+
+    // Renders pixels x0..x1, y0..y1 of the full image into a canvas of just that size; rays are
+    // built from full image coordinates, so tiles match the same pixels of a full render exactly
+    pub fn render_region(&self, world: &World, settings: &RenderSettings, x0: usize, y0: usize, x1: usize, y1: usize) -> Canvas {
+        let (x1, y1) = (x1.min(self.hsize), y1.min(self.vsize));
+        self.render_tile(world, settings, x0.min(x1), y0.min(y1), x1.saturating_sub(x0), y1.saturating_sub(y0))
+    }
+
+    // Averages an evenly spaced samples_per_axis x samples_per_axis grid of rays in each pixel,
+    // in place of settings.antialias_samples
+    pub fn render_antialiased(&self, world: &World, settings: &RenderSettings, samples_per_axis: usize) -> Canvas {
+        self.render_with_progress(world, &RenderSettings { antialias_samples: samples_per_axis, ..*settings }, |_, _| {})
+    }
+
+    // One ray per pixel first; pixels that differ by more than threshold from any of their four
+    // neighbors, so both sides of an edge, are then resampled on 2x2, 4x4, ... grids until two
+    // successive estimates agree or the next grid would exceed max_samples rays. The grid sizes
+    // take the place of settings.antialias_samples.
+    pub fn render_adaptive(&self, world: &World, settings: &RenderSettings, max_samples: usize, threshold: Scalar) -> Canvas {
+        self.render_adaptive_counted(world, settings, max_samples, threshold).0
+    }
+
+    // Also returns how many rays each pixel took, row by row
+    fn render_adaptive_counted(&self, world: &World, settings: &RenderSettings, max_samples: usize, threshold: Scalar) -> (Canvas, Vec<usize>) {
+        let settings = RenderSettings { antialias_samples: 1, ..*settings };
+        let base = self.render_with_progress(world, &settings, |_, _| {});
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut samples = vec![1; self.hsize * self.vsize];
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let mut estimate = base.pixel_at(x, y);
+                let neighbors = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+                let on_edge = neighbors.iter()
+                    .filter(|&&(nx, ny)| nx < self.hsize && ny < self.vsize)
+                    .any(|&(nx, ny)| contrast(estimate, base.pixel_at(nx, ny)) > threshold);
+                if on_edge {
+                    let mut n = 2;
+                    while n * n <= max_samples {
+                        let refined = self.color_for_pixel(world, x, y, 1, &RenderSettings { antialias_samples: n, ..settings });
+                        samples[y * self.hsize + x] += n * n;
+                        let converged = contrast(refined, estimate) <= threshold;
+                        estimate = refined;
+                        if converged {
+                            break;
+                        }
+                        n *= 2;
+                    }
+                }
+                image.write_pixel(x, y, estimate);
+            }
+        }
+        (image, samples)
+    }
+
+    // Averages samples passes of lens rays per pixel, each pixel drawing from its own stream of the
+    // world seed
+    pub fn render_dof(&self, world: &World, settings: &RenderSettings, samples: usize) -> Canvas {
+        let samples = samples.max(1);
         let mut image = Canvas::new(self.hsize, self.vsize);
         for y in 0..self.vsize {
             for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(ray);
+                image.write_pixel(x, y, self.color_for_pixel(world, x, y, samples, settings));
+            }
+        }
+        image
+    }
+
+    // Renders scanlines in parallel on the rayon thread pool
+    pub fn render_parallel(&self, world: &World, settings: &RenderSettings) -> Canvas {
+        self.render_parallel_with_progress(world, settings, |_, _| {})
+    }
+
+    // Rows finish in any order, but rows_done still counts up by one on every call
+    pub fn render_parallel_with_progress(&self, world: &World, settings: &RenderSettings, on_row: impl FnMut(usize, usize) + Send) -> Canvas {
+        let progress = Mutex::new((0, on_row));
+        let rows: Vec<Vec<Color>> = (0..self.vsize).into_par_iter()
+            .map(|y| {
+                let row = (0..self.hsize).map(|x| self.color_for_pixel(world, x, y, 1, settings)).collect();
+                let mut progress = progress.lock().unwrap();
+                progress.0 += 1;
+                let rows_done = progress.0;
+                (progress.1)(rows_done, self.vsize);
+                row
+            })
+            .collect();
+
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, color) in row.into_iter().enumerate() {
                 image.write_pixel(x, y, color);
             }
         }
         image
     }
+
+    // Renders tile_size x tile_size tiles in parallel on the rayon thread pool and hands each one to
+    // on_tile(x0, y0, tile) as soon as it is done, so only the tiles being worked on are held by the
+    // renderer. The sink can write them to disk or into a larger image.
+    pub fn render_tiles(&self, world: &World, settings: &RenderSettings, tile_size: usize, on_tile: impl FnMut(usize, usize, Canvas) + Send) {
+        let tile_size = tile_size.max(1);
+        let tiles: Vec<(usize, usize)> = (0..self.vsize).step_by(tile_size)
+            .flat_map(|y0| (0..self.hsize).step_by(tile_size).map(move |x0| (x0, y0)))
+            .collect();
+        let sink = Mutex::new(on_tile);
+        tiles.into_par_iter().for_each(|(x0, y0)| {
+            let width = tile_size.min(self.hsize - x0);
+            let height = tile_size.min(self.vsize - y0);
+            let tile = self.render_tile(world, settings, x0, y0, width, height);
+            (sink.lock().unwrap())(x0, y0, tile);
+        });
+    }
+
+    // Tiled render copied into one canvas, each tile as soon as it finishes
+    pub fn render_tiled(&self, world: &World, settings: &RenderSettings, tile_size: usize) -> Canvas {
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        self.render_tiles(world, settings, tile_size, |x0, y0, tile| image.blit(&tile, x0, y0));
+        image
+    }
+}
+
+// Largest per-channel difference between two colors
+fn contrast(a: Color, b: Color) -> Scalar {
+    (a.r - b.r).abs().max((a.g - b.g).abs()).max((a.b - b.b).abs())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
+    use crate::consts::{FRAC_PI_2, FRAC_PI_4, PI, SQRT_2};
     use crate::approx_eq;
     use crate::matrix::IDENTITY_MATRIX;
-    use crate::color::Color;
+    use crate::color::WHITE;
+    use crate::material::Material;
+    use crate::sphere::Sphere;
+    use crate::cube::Cube;
+    use crate::light::PointLight;
 
     #[test]
     fn construct_camera() {
@@ -75,7 +375,7 @@ mod tests {
         assert_eq!(c.hsize, 160);
         assert_eq!(c.vsize, 120);
         assert_eq!(c.field_of_view, FRAC_PI_2);
-        assert_eq!(c.transform, IDENTITY_MATRIX);
+        assert_eq!(c.transformation(), IDENTITY_MATRIX);
     }
 
     #[test]
@@ -118,6 +418,163 @@ mod tests {
         assert_eq!(r.direction, Tuple::vector(SQRT_2 / 2., 0., -SQRT_2 / 2.));
     }
 
+    #[test]
+    fn camera_with_singular_transform_is_rejected() {
+        let singular = Camera::try_new(4, 3, FRAC_PI_2, Some(Matrix::scaling(0., 0., 0.)));
+        let tr = Matrix::translation(0., 0., 5.);
+
+        assert_eq!(singular.err(), Some("camera transform is not invertible".to_string()));
+        assert_eq!(Camera::try_new(4, 3, FRAC_PI_2, Some(tr)).unwrap().transformation(), tr);
+    }
+
+    #[test]
+    fn camera_caches_inverse_transform() {
+        let t = Matrix::rotation_y(FRAC_PI_4) * Matrix::translation(0., -2., 5.);
+        let mut c = Camera::new(201, 101, FRAC_PI_2, Some(t));
+        assert_eq!(c.inverse_transformation(), t.inverse().unwrap());
+
+        let moved = Matrix::translation(1., 2., 3.);
+        c.set_transformation(moved);
+        assert_eq!(c.transformation(), moved);
+        assert_eq!(c.inverse_transformation(), moved.inverse().unwrap());
+        assert_eq!(c.ray_for_pixel(100, 50).origin, Tuple::point(-1., -2., -3.));
+    }
+
+    #[test]
+    fn retransformed_camera_renders_like_a_new_one() {
+        let w = World::default_world();
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let fresh = Camera::new(11, 11, FRAC_PI_2, Some(tr));
+        let mut moved = Camera::new(11, 11, FRAC_PI_2, Some(Matrix::translation(3., 0., 0.)));
+        moved.set_transformation(tr);
+
+        assert_eq!(moved.render_parallel(&w, &RenderSettings::default()).diff(&fresh.render_parallel(&w, &RenderSettings::default())).unwrap().1, 0.);
+    }
+
+    #[test]
+    fn progress_is_reported_once_per_row() {
+        let w = World::default_world();
+        let c = Camera::new(7, 5, FRAC_PI_2, None);
+        let mut calls = vec![];
+        c.render_with_progress(&w, &RenderSettings::default(), |done, total| calls.push((done, total)));
+
+        assert_eq!(calls, (1..=5).map(|done| (done, 5)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parallel_progress_counts_up_regardless_of_row_order() {
+        let w = World::default_world();
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let c = Camera::new(9, 13, FRAC_PI_2, Some(tr));
+        let mut calls = vec![];
+        let image = c.render_parallel_with_progress(&w, &RenderSettings::default(), |done, total| calls.push((done, total)));
+
+        assert_eq!(calls, (1..=13).map(|done| (done, 13)).collect::<Vec<_>>());
+        assert_eq!(image.diff(&c.render_with_progress(&w, &RenderSettings::default(), |_, _| {})).unwrap().1, 0.);
+    }
+
+    #[test]
+    fn region_tiles_reassemble_into_full_render() {
+        let w = World::default_world();
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let c = Camera::new(20, 20, FRAC_PI_2, Some(tr));
+        let full = c.render_parallel(&w, &RenderSettings::default());
+        let mut assembled = Canvas::new(20, 20);
+        for (x0, y0) in [(0, 0), (10, 0), (0, 10), (10, 10)].iter() {
+            let tile = c.render_region(&w, &RenderSettings::default(), *x0, *y0, x0 + 10, y0 + 10);
+            assert_eq!((tile.width, tile.height), (10, 10));
+            assembled.blit(&tile, *x0, *y0);
+        }
+
+        for y in 0..20 {
+            for x in 0..20 {
+                let (a, b) = (assembled.pixel_at(x, y), full.pixel_at(x, y));
+                assert!(a.r == b.r && a.g == b.g && a.b == b.b);
+            }
+        }
+    }
+
+    #[test]
+    fn region_is_clipped_to_image() {
+        let w = World::default_world();
+        let c = Camera::new(20, 10, FRAC_PI_2, None);
+        let tile = c.render_region(&w, &RenderSettings::default(), 15, 5, 30, 30);
+
+        assert_eq!((tile.width, tile.height), (5, 5));
+    }
+
+    // A mirrored box around a red ball, seen from inside the box
+    fn mirrored_box(reflective: Scalar) -> World {
+        let mirror = Material { reflective, ..Default::default() };
+        let walls = Cube::new_boxed(Some(mirror), Some(Matrix::scaling(5., 5., 5.)));
+        let ball = Sphere::new_boxed(Some(Material { color: Color::new(1., 0.2, 0.2), ..Default::default() }), None);
+        World::new(Some(PointLight::new(Tuple::point(-2., 3., -4.), WHITE)), vec![walls, ball])
+    }
+
+    #[test]
+    fn recursion_depth_controls_reflections() {
+        let tr = Matrix::view_transform(Tuple::point(0., 1., -4.), Tuple::point(0., 0., 5.), Tuple::vector(0., 1., 0.));
+        let c = Camera::new(9, 9, FRAC_PI_2, Some(tr));
+        let no_depth = RenderSettings { max_recursion_depth: 0, ..Default::default() };
+        let mirrored = c.render_with_progress(&mirrored_box(0.5), &no_depth, |_, _| {});
+        let plain = c.render_with_progress(&mirrored_box(0.), &RenderSettings::default(), |_, _| {});
+        let reflecting = c.render_with_progress(&mirrored_box(0.5), &RenderSettings::default(), |_, _| {});
+
+        assert_eq!(mirrored.diff(&plain).unwrap().1, 0.);
+        assert!(reflecting.diff(&plain).unwrap().1 > 0.01);
+    }
+
+    #[test]
+    fn adaptive_render_supersamples_both_sides_of_the_edge() {
+        // A glowing white wall whose edge runs through column 4, to the right of its center, so
+        // column 4 starts out black and column 3 is the bright side of the edge
+        let glow = Material { ambient: 1., diffuse: 0., specular: 0., ..Default::default() };
+        let wall = Cube::new_boxed(Some(glow), Some(Matrix::translation(49., 0., -10.) * Matrix::scaling(50., 50., 0.5)));
+        let w = World::new(Some(PointLight::new(Tuple::point(0., 0., 10.), WHITE)), vec![wall]);
+        let c = Camera::new(8, 4, FRAC_PI_2, None);
+        let (image, samples) = c.render_adaptive_counted(&w, &RenderSettings::default(), 16, 0.1);
+
+        for y in 0..4 {
+            for x in 0..8 {
+                let expected = match x {
+                    3 => 1 + 4,
+                    4 => 1 + 4 + 16,
+                    _ => 1
+                };
+                assert_eq!(samples[y * 8 + x], expected);
+            }
+            assert_eq!(image.pixel_at(3, y), WHITE);
+            assert_eq!(image.pixel_at(4, y), Color::new(0.5, 0.5, 0.5));
+            assert_eq!(image.pixel_at(5, y), BLACK);
+        }
+        assert_eq!(c.render_adaptive(&w, &RenderSettings::default(), 16, 0.1).diff(&image).unwrap().1, 0.);
+    }
+
+    #[test]
+    fn adaptive_render_of_flat_region_casts_one_ray_per_pixel() {
+        let w = World::default_world();
+        let c = Camera::new(6, 6, 0.1, Some(Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.))));
+        let (image, samples) = c.render_adaptive_counted(&w, &RenderSettings::default(), 16, 0.1);
+
+        assert!(samples.iter().all(|&n| n == 1));
+        assert_eq!(image.diff(&c.render_parallel(&w, &RenderSettings::default())).unwrap().1, 0.);
+    }
+
+    #[test]
+    fn every_renderer_honors_the_settings() {
+        let tr = Matrix::view_transform(Tuple::point(0., 1., -4.), Tuple::point(0., 0., 5.), Tuple::vector(0., 1., 0.));
+        let c = Camera::new(9, 9, FRAC_PI_2, Some(tr));
+        let w = mirrored_box(0.5);
+        let settings = RenderSettings { max_recursion_depth: 0, antialias_samples: 2, ..Default::default() };
+        let serial = c.render_with_progress(&w, &settings, |_, _| {});
+
+        assert!(serial.diff(&c.render_with_progress(&w, &RenderSettings::default(), |_, _| {})).unwrap().1 > 0.01);
+        assert_eq!(c.render_parallel(&w, &settings).diff(&serial).unwrap().1, 0.);
+        assert_eq!(c.render_tiled(&w, &settings, 4).diff(&serial).unwrap().1, 0.);
+        assert_eq!(c.render_region(&w, &settings, 0, 0, 9, 9).diff(&serial).unwrap().1, 0.);
+        assert_eq!(c.render_dof(&w, &settings, 1).diff(&serial).unwrap().1, 0.);
+    }
+
     #[test]
     fn render_world_with_camera() {
         let w = World::default_world();
@@ -127,7 +584,277 @@ mod tests {
         let tr = Matrix::view_transform(from, to, up);
         let c = Camera::new(11, 11, FRAC_PI_2, Some(tr));
 
-        let image = c.render(w);
+        let image = c.render(w, &RenderSettings::default());
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
-}
\ No newline at end of file
+
+    // First row, scanning down, whose ray passes below the line y = z * -slope
+    fn row_below_line(c: &Camera, px: usize, slope: Scalar) -> usize {
+        (0..c.vsize)
+            .find(|&py| {
+                let d = c.ray_for_pixel(px, py).direction;
+                d.y / -d.z < slope
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn default_camera_has_no_distortion() {
+        let c = Camera::new(160, 120, FRAC_PI_2, None);
+
+        assert_eq!(c.distortion, 0.);
+    }
+
+    #[test]
+    fn barrel_distortion_bows_straight_edge_outward() {
+        let mut c = Camera::new(101, 101, FRAC_PI_2, None);
+        let slope = 0.8;
+
+        let straight_center = row_below_line(&c, 50, slope);
+        let straight_edge = row_below_line(&c, 0, slope);
+        assert_eq!(straight_center, straight_edge);
+
+        c.distortion = 0.3;
+        let bowed_center = row_below_line(&c, 50, slope);
+        let bowed_edge = row_below_line(&c, 0, slope);
+        assert!(bowed_center < bowed_edge);
+    }
+
+    #[test]
+    fn pincushion_distortion_bows_straight_edge_inward() {
+        let mut c = Camera::new(101, 101, FRAC_PI_2, None);
+        c.distortion = -0.2;
+        let slope = 0.6;
+
+        assert!(row_below_line(&c, 50, slope) > row_below_line(&c, 0, slope));
+    }
+
+    #[test]
+    fn distortion_leaves_center_ray_unchanged() {
+        let mut c = Camera::new(201, 101, FRAC_PI_2, None);
+        c.distortion = 0.5;
+        let r = c.ray_for_pixel(100, 50);
+
+        assert_eq!(r.direction, Tuple::vector(0., 0., -1.));
+    }
+
+    #[test]
+    fn vignetting_darkens_corners_but_not_center() {
+        let m = Material::new(WHITE, 1., 0., 0., 200., 0., None);
+        let backdrop = Sphere::new_boxed(Some(m), Some(Matrix::scaling(10., 10., 10.)));
+        let light = Some(PointLight::new(ORIGO, WHITE));
+        let mut c = Camera::new(11, 11, FRAC_PI_2, None);
+        c.vignette = 4.;
+
+        let image = c.render(World::new(light, vec![backdrop]), &RenderSettings::default());
+        let center = image.pixel_at(5, 5);
+        let corner = image.pixel_at(0, 0);
+
+        assert_eq!(center, WHITE);
+        assert!(corner.r < center.r);
+        assert!(image.pixel_at(10, 10).r < center.r);
+    }
+
+    #[test]
+    fn zero_vignette_leaves_pixels_unchanged() {
+        let c = Camera::new(11, 11, FRAC_PI_2, None);
+
+        assert_eq!(c.vignette_factor(0, 0), 1.);
+        assert_eq!(c.vignette_factor(5, 5), 1.);
+    }
+
+    #[test]
+    fn subpixel_ray_with_one_sample_matches_pixel_ray() {
+        let c = Camera::new(201, 101, FRAC_PI_2, None);
+
+        assert_eq!(c.ray_for_subpixel(0, 0, 0, 0, 1).direction, c.ray_for_pixel(0, 0).direction);
+        assert_eq!(c.ray_for_subpixel(100, 50, 1, 1, 3).direction, c.ray_for_pixel(100, 50).direction);
+    }
+
+    #[test]
+    fn supersampled_edge_pixel_blends_its_neighbours() {
+        let m = Material::new(WHITE, 1., 0., 0., 200., 0., None);
+        let disc = Sphere::new_boxed(Some(m), Some(Matrix::translation(0., 0., -3.)));
+        let w = World::new(Some(PointLight::new(ORIGO, WHITE)), vec![disc]);
+        let c = Camera::new(11, 11, FRAC_PI_2, None);
+        let single = c.render_antialiased(&w, &RenderSettings::default(), 1);
+        let aliased = c.render_antialiased(&w, &RenderSettings::default(), 2);
+
+        assert_eq!(single.pixel_at(2, 5), BLACK);
+        assert_eq!(single.pixel_at(4, 5), WHITE);
+        assert_eq!(aliased.pixel_at(3, 5), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(aliased.pixel_at(5, 5), WHITE);
+    }
+
+    #[test]
+    fn zero_aperture_lens_ray_is_the_pinhole_ray() {
+        let t = Matrix::rotation_y(FRAC_PI_4) * Matrix::translation(0., -2., 5.);
+        let c = Camera::new(201, 101, FRAC_PI_2, Some(t));
+        let pinhole = c.ray_for_pixel(10, 20);
+        let lens = c.lens_ray_for_pixel(10, 20, &mut Rng::new(1));
+
+        assert_eq!(lens.origin, pinhole.origin);
+        assert_eq!(lens.direction, pinhole.direction);
+    }
+
+    #[test]
+    fn lens_rays_converge_at_the_focal_distance() {
+        let mut c = Camera::new(201, 101, FRAC_PI_2, None);
+        c.aperture = 0.5;
+        c.focal_distance = 4.;
+        let focal_point = c.ray_for_pixel(30, 40).position(4.);
+        let mut rng = Rng::new(9);
+
+        for _ in 0..8 {
+            let r = c.lens_ray_for_pixel(30, 40, &mut rng);
+            assert_ne!(r.origin, ORIGO);
+            assert_eq!((focal_point - r.origin).normalize(), r.direction);
+        }
+    }
+
+    #[test]
+    fn dof_render_is_reproducible_from_the_world_seed() {
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let mut c = Camera::new(11, 11, FRAC_PI_2, Some(tr));
+        c.aperture = 0.3;
+        c.focal_distance = 2.;
+        let mut w = World::default_world();
+        w.seed = 5;
+        let first = c.render_dof(&w, &RenderSettings::default(), 4);
+        let second = c.render_dof(&w, &RenderSettings::default(), 4);
+        w.seed = 6;
+        let reseeded = c.render_dof(&w, &RenderSettings::default(), 4);
+
+        assert_eq!(first.diff(&second).unwrap().1, 0.);
+        assert!(first.diff(&reseeded).unwrap().1 > 0.);
+    }
+
+    #[test]
+    fn every_renderer_samples_the_lens_when_there_is_an_aperture() {
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let mut c = Camera::new(11, 11, FRAC_PI_2, Some(tr));
+        let w = World::default_world();
+        let pinhole = c.render_parallel(&w, &RenderSettings::default());
+        c.aperture = 0.3;
+        c.focal_distance = 2.;
+        let dof = c.render_dof(&w, &RenderSettings::default(), 1);
+
+        assert!(dof.diff(&pinhole).unwrap().1 > 0.);
+        assert_eq!(c.render_with_progress(&w, &RenderSettings::default(), |_, _| {}).diff(&dof).unwrap().1, 0.);
+        assert_eq!(c.render_parallel(&w, &RenderSettings::default()).diff(&dof).unwrap().1, 0.);
+        assert_eq!(c.render_tiled(&w, &RenderSettings::default(), 4).diff(&dof).unwrap().1, 0.);
+        assert_eq!(c.render(World::default_world(), &RenderSettings::default()).diff(&dof).unwrap().1, 0.);
+    }
+
+    #[test]
+    fn dof_and_soft_shadow_render_is_reproducible_from_the_world_seed() {
+        use crate::light::AreaLight;
+        use crate::plane::Plane;
+
+        let tr = Matrix::view_transform(Tuple::point(0., 2., -5.), Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.));
+        let mut c = Camera::new(16, 12, FRAC_PI_2, Some(tr));
+        c.aperture = 0.2;
+        c.focal_distance = 5.;
+        let light = AreaLight::new(Tuple::point(-1., 4., -1.), Tuple::vector(2., 0., 0.), 3, Tuple::vector(0., 0., 2.), 3, WHITE).jittered();
+        let mut w = World::with_lights(vec![Box::new(light)], vec![
+            Plane::new_boxed(None, Some(Matrix::translation(0., -1., 0.))),
+            Sphere::default_boxed()]);
+        let render = |c: &Camera, w: &World| c.render_dof(w, &RenderSettings::default(), 3);
+
+        w.seed = 11;
+        let first = render(&c, &w);
+        assert_eq!(first.diff(&render(&c, &w)).unwrap().1, 0.);
+        w.seed = 12;
+        assert!(first.diff(&render(&c, &w)).unwrap().1 > 0.);
+
+        // Without the lens only the jittered shadow rays depend on the seed
+        c.aperture = 0.;
+        let pinhole = render(&c, &w);
+        assert_eq!(pinhole.diff(&render(&c, &w)).unwrap().1, 0.);
+        w.seed = 11;
+        assert!(pinhole.diff(&render(&c, &w)).unwrap().1 > 0.);
+    }
+
+    #[test]
+    fn parallel_render_matches_serial_render() {
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let c = Camera::new(23, 17, FRAC_PI_2, Some(tr));
+        let serial = c.render(World::default_world(), &RenderSettings::default());
+        let parallel = c.render_parallel(&World::default_world(), &RenderSettings::default());
+
+        let (_, error) = serial.diff(&parallel).unwrap();
+        assert_eq!(error, 0.);
+        assert_eq!(parallel.pixel_at(11, 8), serial.pixel_at(11, 8));
+    }
+
+    #[test]
+    fn tiled_render_matches_monolithic_render() {
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let c = Camera::new(23, 17, FRAC_PI_2, Some(tr));
+        let monolithic = c.render(World::default_world(), &RenderSettings::default());
+        let tiled = c.render_tiled(&World::default_world(), &RenderSettings::default(), 5);
+
+        let (_, error) = monolithic.diff(&tiled).unwrap();
+        assert_eq!(error, 0.);
+        assert_eq!(tiled.pixel_at(11, 8), monolithic.pixel_at(11, 8));
+    }
+
+    #[test]
+    fn bounds_overlay_outlines_sphere_extent() {
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let c = Camera::new(101, 101, FRAC_PI_2, Some(tr));
+        let w = World::new(Some(PointLight::new(Tuple::point(-10., 10., -10.), WHITE)), vec![Sphere::default_boxed()]);
+        let overlay = Color::new(1., 0., 1.);
+        let image = c.render_with_bounds_overlay(&w, &RenderSettings::default(), overlay);
+        let outlined: Vec<(usize, usize)> = (0..101).flat_map(|y| (0..101).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.pixel_at(x, y) == overlay)
+            .collect();
+
+        // The near face of the unit box is 4 units away and spans a quarter of the half-width
+        let extent = |coords: Vec<usize>| (*coords.iter().min().unwrap(), *coords.iter().max().unwrap());
+        assert_eq!(extent(outlined.iter().map(|p| p.0).collect()), (37, 63));
+        assert_eq!(extent(outlined.iter().map(|p| p.1).collect()), (37, 63));
+        assert_ne!(image.pixel_at(50, 50), overlay);
+        assert_eq!(image.pixel_at(50, 37), overlay);
+        assert_eq!(image.pixel_at(50, 50), c.render(w, &RenderSettings::default()).pixel_at(50, 50));
+    }
+
+    #[test]
+    fn tiles_are_handed_over_covering_the_image_once() {
+        let tr = Matrix::view_transform(Tuple::point(0., 0., -5.), ORIGO, Tuple::vector(0., 1., 0.));
+        let c = Camera::new(23, 17, FRAC_PI_2, Some(tr));
+        let monolithic = c.render(World::default_world(), &RenderSettings::default());
+        let mut covered = Canvas::new(23, 17);
+        let mut tiles = 0;
+        c.render_tiles(&World::default_world(), &RenderSettings::default(), 5, |x0, y0, tile| {
+            tiles += 1;
+            assert!(tile.width <= 5 && tile.height <= 5);
+            assert_eq!(tile.pixel_at(0, 0), monolithic.pixel_at(x0, y0));
+            for y in y0..y0 + tile.height {
+                for x in x0..x0 + tile.width {
+                    assert_eq!(covered.pixel_at(x, y), BLACK);
+                    covered.write_pixel(x, y, WHITE);
+                }
+            }
+        });
+
+        assert_eq!(tiles, 20);
+        assert!((0..17).all(|y| (0..23).all(|x| covered.pixel_at(x, y) == WHITE)));
+    }
+
+    #[test]
+    fn fisheye_covers_a_full_hemisphere() {
+        let mut c = Camera::new(101, 101, FRAC_PI_2, None);
+        c.projection = Projection::Fisheye { fov: PI };
+
+        let center = c.ray_for_pixel(50, 50);
+        assert_eq!(center.direction, Tuple::vector(0., 0., -1.));
+
+        let edge = c.ray_for_pixel(100, 50);
+        assert!(edge.direction.x < -0.999);
+        assert!(edge.direction.z.abs() < 0.02);
+
+        let perspective = Camera::new(101, 101, FRAC_PI_2, None).ray_for_pixel(100, 50);
+        assert!(perspective.direction.z < -0.7);
+    }
+}
@@ -1,21 +1,39 @@
+#[cfg(feature = "png")]
 use std::io::Result;
+#[cfg(feature = "png")]
 use png::HasParameters;
+#[cfg(feature = "png")]
 use std::fs::File;
+#[cfg(feature = "png")]
+use std::path::Path;
 
 use super::color::*;
+use super::Scalar;
 
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
+    pub dither: bool,
+    // Applied when quantizing for output; 1 keeps the values linear, 2.2 suits most displays
+    pub gamma: Scalar,
     canvas: Vec<Vec<Color>>
 }
 
+// 4x4 Bayer threshold matrix used for ordered dithering at quantization time
+const BAYER_4X4: [[Scalar; 4]; 4] = [
+    [ 0.,  8.,  2., 10.],
+    [12.,  4., 14.,  6.],
+    [ 3., 11.,  1.,  9.],
+    [15.,  7., 13.,  5.]];
+
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Canvas {
         let black_row = vec![BLACK; width];
         Canvas {
             width,
             height,
+            dither: false,
+            gamma: 1.,
             canvas: vec![black_row; height]
         }
     }
@@ -28,7 +46,94 @@ impl Canvas {
         self.canvas[y][x] = c;
     }
 
-    fn clamp_to_byte(color_component: f64) -> u8 {
+    pub fn diff(&self, other: &Canvas) -> std::result::Result<(Canvas, Scalar), String> {
+        if self.width != other.width || self.height != other.height {
+            return Err(format!("Canvas dimensions differ: {}x{} vs {}x{}",
+                               self.width, self.height, other.width, other.height));
+        }
+        let mut difference = Canvas::new(self.width, self.height);
+        let mut total_error = 0.;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let a = self.pixel_at(x, y);
+                let b = other.pixel_at(x, y);
+                let d = Color::new((a.r - b.r).abs(), (a.g - b.g).abs(), (a.b - b.b).abs());
+                total_error += (d.r + d.g + d.b) / 3.;
+                difference.write_pixel(x, y, d);
+            }
+        }
+        let pixel_count = (self.width * self.height).max(1) as Scalar;
+        Ok((difference, total_error / pixel_count))
+    }
+
+    // Straight line between two points in pixel coordinates, clipped to the canvas first so far
+    // away end points cost nothing
+    pub fn draw_line(&mut self, from: (Scalar, Scalar), to: (Scalar, Scalar), c: Color) {
+        if ![from.0, from.1, to.0, to.1].iter().all(|v| v.is_finite()) || self.width == 0 || self.height == 0 {
+            return;
+        }
+        // Liang-Barsky: narrow t0..t1 to the part of the segment inside each edge of the canvas
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let (mut t0, mut t1): (Scalar, Scalar) = (0., 1.);
+        let edges = [(-dx, from.0), (dx, (self.width - 1) as Scalar - from.0),
+                     (-dy, from.1), (dy, (self.height - 1) as Scalar - from.1)];
+        for &(p, q) in edges.iter() {
+            if p == 0. {
+                if q < 0. {
+                    return;
+                }
+            } else if p < 0. {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        if t0 > t1 {
+            return;
+        }
+        let (x0, y0) = (from.0 + t0 * dx, from.1 + t0 * dy);
+        let (x1, y1) = (from.0 + t1 * dx, from.1 + t1 * dy);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.);
+        for i in 0..=steps as usize {
+            let t = i as Scalar / steps;
+            let (x, y) = ((x0 + t * (x1 - x0)).round(), (y0 + t * (y1 - y0)).round());
+            self.write_pixel(x as usize, y as usize, c);
+        }
+    }
+
+    // Copies src with its top left corner at (dest_x, dest_y), dropping whatever falls outside
+    pub fn blit(&mut self, src: &Canvas, dest_x: usize, dest_y: usize) {
+        let width = src.width.min(self.width.saturating_sub(dest_x));
+        let height = src.height.min(self.height.saturating_sub(dest_y));
+        for y in 0..height {
+            for x in 0..width {
+                self.write_pixel(dest_x + x, dest_y + y, src.pixel_at(x, y));
+            }
+        }
+    }
+
+    pub fn downscale_average(&self, factor: usize) -> std::result::Result<Canvas, String> {
+        if factor == 0 || !self.width.is_multiple_of(factor) || !self.height.is_multiple_of(factor) {
+            return Err(format!("Canvas {}x{} cannot be downscaled by a factor of {}",
+                               self.width, self.height, factor));
+        }
+        let mut image = Canvas::new(self.width / factor, self.height / factor);
+        let samples = (factor * factor) as Scalar;
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let mut sum = BLACK;
+                for sy in 0..factor {
+                    for sx in 0..factor {
+                        sum = sum + self.pixel_at(x * factor + sx, y * factor + sy);
+                    }
+                }
+                image.write_pixel(x, y, sum * (1. / samples));
+            }
+        }
+        Ok(image)
+    }
+
+    fn clamp_to_byte(color_component: Scalar) -> u8 {
         if color_component < 0.0 {
             0u8
         } else if color_component >= 1.0 {
@@ -38,23 +143,110 @@ impl Canvas {
         }
     }
 
+    fn dither_offset(&self, x: usize, y: usize) -> Scalar {
+        if !self.dither {
+            return 0.;
+        }
+        // Sub-LSB offset in the range (-0.5, 0.5) of one 8-bit step
+        ((BAYER_4X4[y % 4][x % 4] + 0.5) / 16. - 0.5) / 256.
+    }
+
     fn to_rgb_bytes(&self) -> Vec<u8> {
         const BYTES_PER_PIXEL: usize = 3;
         let mut bytes = vec![0u8; self.width * self.height * BYTES_PER_PIXEL];
         let mut index = 0;
-        for row in &self.canvas {
-            for color in row {
-                bytes[index] = Canvas::clamp_to_byte(color.r);
-                bytes[index + 1] = Canvas::clamp_to_byte(color.g);
-                bytes[index + 2] = Canvas::clamp_to_byte(color.b);
+        for (y, row) in self.canvas.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                let color = if self.gamma == 1. { *color } else { color.clamp().gamma(self.gamma) };
+                let offset = self.dither_offset(x, y);
+                bytes[index] = Canvas::clamp_to_byte(color.r + offset);
+                bytes[index + 1] = Canvas::clamp_to_byte(color.g + offset);
+                bytes[index + 2] = Canvas::clamp_to_byte(color.b + offset);
                 index += 3;
             }
         }
         bytes
     }
 
+    // Plain P3 PPM, using the same quantization as the PNG output
+    pub fn to_ppm(&self) -> String {
+        const MAX_LINE_LENGTH: usize = 70;
+        let mut ppm = format!("P3\n{} {}\n255\n", self.width, self.height);
+        if self.width == 0 {
+            return ppm;
+        }
+        for row in self.to_rgb_bytes().chunks(self.width * 3) {
+            let mut line = String::new();
+            for value in row {
+                let value = value.to_string();
+                if !line.is_empty() && line.len() + 1 + value.len() > MAX_LINE_LENGTH {
+                    ppm.push_str(&line);
+                    ppm.push('\n');
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&value);
+            }
+            ppm.push_str(&line);
+            ppm.push('\n');
+        }
+        ppm
+    }
+
+    pub fn from_ppm(contents: &str) -> std::result::Result<Canvas, String> {
+        let mut tokens = contents.lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split_whitespace());
+        match tokens.next() {
+            Some("P3") => {}
+            other => return Err(format!("Expected a P3 header, found {:?}", other))
+        }
+        let number = |token: Option<&str>, what: &str| -> std::result::Result<usize, String> {
+            let token = token.ok_or(format!("Missing {}", what))?;
+            token.parse().map_err(|_| format!("Invalid {} {:?}", what, token))
+        };
+        let width = number(tokens.next(), "width")?;
+        let height = number(tokens.next(), "height")?;
+        let max_value = number(tokens.next(), "maximum color value")?;
+        if max_value == 0 {
+            return Err("The maximum color value must be positive".to_string());
+        }
+        // Checked before allocating, so a header claiming a huge image can't exhaust memory
+        let samples = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3))
+            .ok_or(format!("The image size {}x{} is too large", width, height))?;
+        let available = tokens.clone().count();
+        if available < samples {
+            return Err(format!("Expected {} color values for a {}x{} image, found {}", samples, width, height, available));
+        }
+        let mut next_sample = |what: &str| -> std::result::Result<Scalar, String> {
+            match number(tokens.next(), what)? {
+                value if value > max_value => Err(format!("The {} {} is above the maximum of {}", what, value, max_value)),
+                value => Ok(value as Scalar / max_value as Scalar)
+            }
+        };
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let r = next_sample("red value")?;
+                let g = next_sample("green value")?;
+                let b = next_sample("blue value")?;
+                canvas.write_pixel(x, y, Color::new(r, g, b));
+            }
+        }
+        Ok(canvas)
+    }
+
+    #[cfg(feature = "png")]
     pub fn save(&self, file_name: &str) -> Result<()> {
-        let file = File::create(file_name)?;
+        self.save_png(Path::new(file_name))
+    }
+
+    // Shares to_rgb_bytes with the PPM exporter, so both files hold identical pixel values
+    #[cfg(feature = "png")]
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)?;
         let w = &mut std::io::BufWriter::new(file);
 
         let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
@@ -66,6 +258,22 @@ impl Canvas {
     }
 }
 
+// Pixels hold unclamped render output; this compresses it into 0..1 with c / (c + 1)
+// per channel instead of clipping highlights at quantization time
+pub fn tone_map_reinhard(canvas: &Canvas) -> Canvas {
+    let reinhard = |c: Scalar| { let c = c.max(0.); c / (c + 1.) };
+    let mut mapped = Canvas::new(canvas.width, canvas.height);
+    mapped.dither = canvas.dither;
+    mapped.gamma = canvas.gamma;
+    for y in 0..canvas.height {
+        for x in 0..canvas.width {
+            let c = canvas.pixel_at(x, y);
+            mapped.write_pixel(x, y, Color::new(reinhard(c.r), reinhard(c.g), reinhard(c.b)));
+        }
+    }
+    mapped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,8 +321,269 @@ mod tests {
         let rgb_bytes = c.to_rgb_bytes();
 
         assert_eq!(255u8, rgb_bytes[0]);                                        // clamp the 1.5 r value to 255
-        assert_eq!(128u8, rgb_bytes[(2 + 1 * WIDTH) * BYTES_PER_PIXEL + 1]);     // the .5 g value should be converted to 128
-        assert_eq!(0u8, rgb_bytes[(4 + 2 * WIDTH) * BYTES_PER_PIXEL + 0]);     // clamp the -.5 r value to 0
+        assert_eq!(128u8, rgb_bytes[(2 + WIDTH) * BYTES_PER_PIXEL + 1]);         // the .5 g value should be converted to 128
+        assert_eq!(0u8, rgb_bytes[(4 + 2 * WIDTH) * BYTES_PER_PIXEL]);         // clamp the -.5 r value to 0
         assert_eq!(255u8, rgb_bytes[(4 + 2 * WIDTH) * BYTES_PER_PIXEL + 2]);     // the 1.0 b value should be 255
     }
-}
\ No newline at end of file
+
+    fn unique_red_bytes_in_first_row(c: &Canvas) -> usize {
+        let bytes = c.to_rgb_bytes();
+        let mut reds: Vec<u8> = (0..c.width).map(|x| bytes[x * 3]).collect();
+        reds.sort_unstable();
+        reds.dedup();
+        reds.len()
+    }
+
+    #[test]
+    fn dithering_smooth_gradient_gives_more_unique_values() {
+        const WIDTH: usize = 64;
+        let mut c = Canvas::new(WIDTH, 1);
+        for x in 0..WIDTH {
+            let v = 0.5 + 0.01 * x as Scalar / WIDTH as Scalar;
+            c.write_pixel(x, 0, Color::new(v, v, v));
+        }
+        let without_dither = unique_red_bytes_in_first_row(&c);
+        c.dither = true;
+        let with_dither = unique_red_bytes_in_first_row(&c);
+
+        assert!(with_dither > without_dither);
+    }
+
+    #[test]
+    fn dithering_is_disabled_by_default() {
+        let c = Canvas::new(10, 20);
+
+        assert!(!c.dither);
+        assert_eq!(c.dither_offset(3, 2), 0.);
+    }
+
+    #[test]
+    fn diff_canvas_against_itself() {
+        let mut c = Canvas::new(4, 4);
+        c.write_pixel(1, 2, Color::new(0.2, 0.4, 0.6));
+        let (difference, error) = c.diff(&c).unwrap();
+
+        assert_eq!(error, 0.);
+        assert_eq!(difference.pixel_at(1, 2), BLACK);
+    }
+
+    #[test]
+    fn diff_highlights_changed_pixel() {
+        let a = Canvas::new(4, 4);
+        let mut b = Canvas::new(4, 4);
+        b.write_pixel(3, 1, Color::new(0.3, 0.6, 0.9));
+        let (difference, error) = a.diff(&b).unwrap();
+
+        assert!(crate::approx_eq(error, 0.6 / 16.));
+        assert_eq!(difference.pixel_at(3, 1), Color::new(0.3, 0.6, 0.9));
+        assert_eq!(difference.pixel_at(0, 0), BLACK);
+    }
+
+    #[test]
+    fn diff_with_mismatched_dimensions_fails() {
+        let a = Canvas::new(4, 4);
+        let b = Canvas::new(4, 5);
+
+        assert!(a.diff(&b).is_err());
+    }
+
+    #[test]
+    fn blit_copies_region_and_clips_at_edges() {
+        let mut c = Canvas::new(4, 3);
+        let mut src = Canvas::new(3, 2);
+        src.write_pixel(0, 0, RED);
+        src.write_pixel(1, 1, GREEN);
+        src.write_pixel(2, 1, BLUE);
+        c.blit(&src, 2, 2);
+
+        assert_eq!(c.pixel_at(2, 2), RED);
+        assert_eq!(c.pixel_at(3, 2), BLACK);
+        assert_eq!(c.pixel_at(1, 1), BLACK);
+
+        c.blit(&src, 10, 0);
+        c.blit(&src, 0, 1);
+        assert_eq!(c.pixel_at(1, 2), GREEN);
+        assert_eq!(c.pixel_at(2, 2), BLUE);
+    }
+
+    fn lit_pixels(c: &Canvas) -> Vec<(usize, usize)> {
+        (0..c.height).flat_map(|y| (0..c.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| c.pixel_at(x, y) != BLACK)
+            .collect()
+    }
+
+    #[test]
+    fn drawing_lines() {
+        let mut c = Canvas::new(5, 4);
+        c.draw_line((0., 0.), (3., 3.), RED);
+
+        assert_eq!(lit_pixels(&c), vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+
+        let mut c = Canvas::new(5, 4);
+        c.draw_line((-100., 2.), (1e9, 2.), GREEN);
+        c.draw_line((-1., -1.), (-1., 10.), BLUE);
+
+        assert_eq!(lit_pixels(&c), (0..5).map(|x| (x, 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn downscale_averages_high_contrast_edge() {
+        // A vertical edge at x = 3 of a 2x render falls in the middle of output pixel 1
+        let mut c = Canvas::new(8, 4);
+        for y in 0..4 {
+            for x in 0..3 {
+                c.write_pixel(x, y, WHITE);
+            }
+        }
+        let small = c.downscale_average(2).unwrap();
+
+        assert_eq!(small.width, 4);
+        assert_eq!(small.height, 2);
+        assert_eq!(small.pixel_at(0, 0), WHITE);
+        assert_eq!(small.pixel_at(1, 1), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(small.pixel_at(2, 0), BLACK);
+    }
+
+    #[test]
+    fn downscale_requires_divisible_dimensions() {
+        let c = Canvas::new(5, 4);
+
+        assert!(c.downscale_average(2).is_err());
+        assert!(c.downscale_average(0).is_err());
+        assert_eq!(c.downscale_average(1).unwrap().width, 5);
+    }
+
+    #[test]
+    fn ppm_header() {
+        let ppm = Canvas::new(5, 3).to_ppm();
+
+        assert!(ppm.starts_with("P3\n5 3\n255\n"));
+    }
+
+    #[test]
+    fn ppm_pixel_data_is_clamped() {
+        let mut c = Canvas::new(5, 3);
+        c.write_pixel(0, 0, Color::new(1.5, 0., 0.));
+        c.write_pixel(2, 1, Color::new(0., 0.5, 0.));
+        c.write_pixel(4, 2, Color::new(-0.5, 0., 1.));
+        let ppm = c.to_ppm();
+        let lines: Vec<&str> = ppm.lines().collect();
+
+        assert_eq!(lines[3], "255 0 0 0 0 0 0 0 0 0 0 0 0 0 0");
+        assert_eq!(lines[4], "0 0 0 0 0 0 0 128 0 0 0 0 0 0 0");
+        assert_eq!(lines[5], "0 0 0 0 0 0 0 0 0 0 0 0 0 0 255");
+    }
+
+    #[test]
+    fn ppm_lines_are_wrapped_at_70_characters() {
+        let mut c = Canvas::new(10, 2);
+        for y in 0..2 {
+            for x in 0..10 {
+                c.write_pixel(x, y, Color::new(1., 0.8, 0.6));
+            }
+        }
+        let ppm = c.to_ppm();
+        let lines: Vec<&str> = ppm.lines().collect();
+
+        assert_eq!(lines[3], "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204");
+        assert_eq!(lines[4], "153 255 204 153 255 204 153 255 204 153 255 204 153");
+        assert!(lines.iter().all(|line| line.len() <= 70));
+        assert!(ppm.ends_with('\n'));
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, WHITE);
+        c.write_pixel(1, 0, RED);
+        c.write_pixel(2, 1, Color::new(0.2, 0.4, 0.6));
+        let parsed = Canvas::from_ppm(&c.to_ppm()).unwrap();
+
+        assert_eq!((parsed.width, parsed.height), (3, 2));
+        assert_eq!(parsed.pixel_at(0, 0), WHITE);
+        assert_eq!(parsed.pixel_at(1, 0), RED);
+        assert_eq!(parsed.pixel_at(0, 1), BLACK);
+        assert_eq!(parsed.to_ppm(), c.to_ppm());
+    }
+
+    #[test]
+    fn ppm_import_skips_comments_and_respects_scale() {
+        let ppm = "P3\n# made by hand\n2 1\n100\n# first pixel\n100 50 0\n0 25 100\n";
+        let c = Canvas::from_ppm(ppm).unwrap();
+
+        assert_eq!(c.pixel_at(0, 0), Color::new(1., 0.5, 0.));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0., 0.25, 1.));
+    }
+
+    #[test]
+    fn ppm_import_rejects_malformed_files() {
+        assert!(Canvas::from_ppm("P6\n1 1\n255\n0 0 0\n").is_err());
+        assert!(Canvas::from_ppm("P3\n2 1\n255\n0 0 0\n").is_err());
+        assert!(Canvas::from_ppm("P3\n1 1\n0\n0 0 0\n").is_err());
+        assert!(Canvas::from_ppm("P3\n1 x\n255\n").is_err());
+        assert_eq!(Canvas::from_ppm("P3 4000000000 4000000000 255").err(),
+                   Some("The image size 4000000000x4000000000 is too large".to_string()));
+        assert_eq!(Canvas::from_ppm("P3 400000 400000 255 0 0 0").err(),
+                   Some("Expected 480000000000 color values for a 400000x400000 image, found 3".to_string()));
+        assert_eq!(Canvas::from_ppm("P3\n1 1\n100\n0 101 0\n").err(),
+                   Some("The green value 101 is above the maximum of 100".to_string()));
+    }
+
+    #[test]
+    fn ppm_of_empty_canvas_is_just_the_header() {
+        assert_eq!(Canvas::new(0, 0).to_ppm(), "P3\n0 0\n255\n");
+        assert_eq!(Canvas::new(0, 3).to_ppm(), "P3\n0 3\n255\n");
+        assert_eq!(Canvas::from_ppm(&Canvas::new(0, 3).to_ppm()).unwrap().height, 3);
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn png_round_trip_matches_ppm_values() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.5, 0., 0.));
+        c.write_pixel(1, 0, Color::new(0.2, 0.4, 0.6));
+        c.write_pixel(2, 1, WHITE);
+        let path = std::env::temp_dir().join(format!("rustracer-canvas-{}.png", std::process::id()));
+        c.save_png(&path).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut bytes = vec![0; info.buffer_size()];
+        reader.next_frame(&mut bytes).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(&bytes[0..3], &[255, 0, 0]);
+        assert_eq!(&bytes[3..6], &[51, 102, 153]);
+        assert_eq!(&bytes[15..18], &[255, 255, 255]);
+        assert_eq!(bytes, c.to_rgb_bytes());
+    }
+
+    #[test]
+    fn ppm_output_with_gamma() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.5, 1.5, -0.5));
+        c.write_pixel(1, 0, Color::new(0.5, 0.5, 0.5));
+        let linear = c.to_ppm();
+        c.gamma = 2.2;
+        let corrected = c.to_ppm();
+
+        assert_eq!(linear.lines().nth(3).unwrap(), "128 255 0 128 128 128");
+        assert_eq!(corrected.lines().nth(3).unwrap(), "186 255 0 186 186 186");
+    }
+
+    #[test]
+    fn reinhard_tone_mapping_compresses_highlights() {
+        let mut c = Canvas::new(2, 1);
+        c.gamma = 2.2;
+        c.write_pixel(0, 0, Color::new(4., 1., -0.5));
+        c.write_pixel(1, 0, Color::new(0.1, 0.25, 0.5));
+        let mapped = tone_map_reinhard(&c);
+
+        assert_eq!(mapped.pixel_at(0, 0), Color::new(0.8, 0.5, 0.));
+        let dark = mapped.pixel_at(1, 0);
+        assert!(dark.r < 0.1 && dark.r > 0.09);
+        assert_eq!(dark, Color::new(0.1 / 1.1, 0.2, 1. / 3.));
+        assert_eq!(mapped.gamma, 2.2);
+    }
+}
@@ -0,0 +1,260 @@
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use std::any::Any;
+use super::Scalar;
+
+// A clip plane is a (normal, offset) pair; points p with normal . p > offset are clipped away,
+// so the normal points out of the part of the shape that is kept.
+pub type ClipPlane = (Tuple, Scalar);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipCap {
+    id: ShapeId,
+    normal: Tuple,
+    material: Material,
+    transforms: Transforms,
+}
+
+impl Shape for ClipCap {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    // Caps are only ever hit through their owning Clipped shape
+    fn inner_intersect_ref(&self, _object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        vec![]
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        self.normal
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Clipped {
+    id: ShapeId,
+    shape: BoxShape,
+    clip_planes: Vec<ClipPlane>,
+    caps: Vec<ClipCap>,
+    transforms: Transforms,
+}
+
+impl PartialEq for Clipped {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape.box_eq(other.shape.as_any()) &&
+            self.clip_planes == other.clip_planes &&
+            self.transformation() == other.transformation()
+    }
+}
+
+impl Shape for Clipped {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let shape_xs = self.shape.intersect_ref(object_ray);
+        let mut xs: Vec<IntersectionRef> = shape_xs.iter()
+            .filter(|i| !self.is_clipped(object_ray.position(i.t)))
+            .copied()
+            .collect();
+
+        for ((normal, offset), cap) in self.clip_planes.iter().zip(self.caps.iter()) {
+            let denominator = normal.dot(&object_ray.direction);
+            if super::approx_eq(0., denominator) {
+                continue;
+            }
+            let t = (offset - normal.dot(&object_ray.origin)) / denominator;
+            if self.is_clipped(object_ray.position(t)) {
+                continue;
+            }
+            // The plane crossing lies inside the solid when an odd number of its surface hits precede it
+            let crossings = shape_xs.iter().filter(|i| i.t < t).count();
+            if crossings % 2 == 1 {
+                xs.push(IntersectionRef::new(t, cap));
+            }
+        }
+        xs
+    }
+
+    fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
+        self.shape.normal_at(object_point)
+    }
+
+    fn material(&self) -> &Material {
+        self.shape.material()
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        self.shape.material_mut()
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    // Clipping only removes parts of the wrapped shape, so its bounds still enclose what is left
+    fn bounds(&self) -> BoundingBox {
+        self.shape.parent_space_bounds()
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+        self.shape.renew_ids();
+        for cap in self.caps.iter_mut() {
+            cap.renew_ids();
+        }
+    }
+
+    // Hits are reported on the wrapped shape and the caps, so they need the chain as well
+    fn set_parent_transformation(&mut self, parent: Matrix) {
+        self.transforms.set_parent(parent);
+        self.update_children();
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transforms.set_transform(transform);
+        self.update_children();
+    }
+
+    // The caps are cut from the wrapped shape and take its material along
+    fn set_material(&mut self, material: Material) {
+        for cap in self.caps.iter_mut() {
+            cap.set_material(material.clone());
+        }
+        self.shape.set_material(material);
+    }
+}
+
+impl Clipped {
+    pub fn new(shape: BoxShape, clip_planes: Vec<ClipPlane>) -> Self {
+        let caps = clip_planes.iter()
+            .map(|(normal, _)| ClipCap {
+                id: ShapeId::default(),
+                normal: normal.normalize(),
+                material: shape.material().clone(),
+                transforms: Transforms::default(),
+            })
+            .collect();
+        Self { id: ShapeId::default(), shape, clip_planes, caps, transforms: Transforms::default() }
+    }
+
+    pub fn new_boxed(shape: BoxShape, clip_planes: Vec<ClipPlane>) -> BoxShape {
+        Box::new(Clipped::new(shape, clip_planes))
+    }
+
+    fn update_children(&mut self) {
+        let chain = self.transforms.chain();
+        self.shape.set_parent_transformation(chain);
+        for cap in self.caps.iter_mut() {
+            cap.set_parent_transformation(chain);
+        }
+    }
+
+    fn is_clipped(&self, point: Tuple) -> bool {
+        self.clip_planes.iter().any(|(normal, offset)| normal.dot(&point) > *offset + super::EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use crate::intersection::hit_ref;
+
+    fn half_sphere() -> Clipped {
+        Clipped::new(Sphere::default_boxed(), vec![(Tuple::vector(0., 0., -1.), 0.)])
+    }
+
+    #[test]
+    fn ray_into_clipped_half_hits_cap() {
+        let c = half_sphere();
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = c.intersect_ref(r);
+        let hit = hit_ref(&xs).unwrap();
+
+        assert_eq!(hit.t, 5.);
+        assert_eq!(hit.object.normal_at(r.position(hit.t)), Tuple::vector(0., 0., -1.));
+        assert_eq!(hit.object.material(), c.material());
+    }
+
+    #[test]
+    fn ray_into_kept_half_hits_sphere() {
+        let c = half_sphere();
+        let r = Ray::new(Tuple::point(0., 0., 5.), Tuple::vector(0., 0., -1.));
+        let xs = c.intersect_ref(r);
+        let hit = hit_ref(&xs).unwrap();
+
+        assert_eq!(hit.t, 4.);
+        assert_eq!(hit.object.normal_at(r.position(hit.t)), Tuple::vector(0., 0., 1.));
+    }
+
+    #[test]
+    fn ray_missing_shape_does_not_hit_cap() {
+        let c = half_sphere();
+        let r = Ray::new(Tuple::point(2., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        assert!(c.intersect_ref(r).is_empty());
+    }
+
+    #[test]
+    fn ray_only_through_clipped_region_misses() {
+        let c = half_sphere();
+        let r = Ray::new(Tuple::point(-5., 0., -0.5), Tuple::vector(1., 0., 0.));
+
+        assert!(c.intersect_ref(r).is_empty());
+    }
+}
@@ -1,10 +1,11 @@
 use core::ops;
+use super::Scalar;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Color {
-    pub r: f64,
-    pub g: f64,
-    pub b: f64
+    pub r: Scalar,
+    pub g: Scalar,
+    pub b: Scalar
 }
 
 pub const BLACK: Color = Color { r: 0., g: 0., b: 0.0};
@@ -43,9 +44,9 @@ impl ops::Sub<Color> for Color {
     }
 }
 
-impl ops::Mul<f64> for Color {
+impl ops::Mul<Scalar> for Color {
     type Output = Color;
-    fn mul(self, rhs: f64) -> Color {
+    fn mul(self, rhs: Scalar) -> Color {
         Color {
             r: self.r * rhs,
             g: self.g * rhs,
@@ -54,6 +55,17 @@ impl ops::Mul<f64> for Color {
     }
 }
 
+impl ops::Div<Scalar> for Color {
+    type Output = Color;
+    fn div(self, rhs: Scalar) -> Color {
+        Color {
+            r: self.r / rhs,
+            g: self.g / rhs,
+            b: self.b / rhs
+        }
+    }
+}
+
 impl ops::Mul<Color> for Color {
     type Output = Color;
     fn mul(self, rhs: Color) -> Color {
@@ -66,9 +78,34 @@ impl ops::Mul<Color> for Color {
 }
 
 impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Color {
+    pub fn new(r: Scalar, g: Scalar, b: Scalar) -> Color {
         Color {r, g, b}
     }
+
+    // Element-wise product, same as `*` between two colors
+    pub fn hadamard(&self, other: &Color) -> Color {
+        *self * *other
+    }
+
+    // Linear blend, t = 0 gives self and t = 1 gives other
+    pub fn lerp(&self, other: &Color, t: Scalar) -> Color {
+        *self + (*other - *self) * t
+    }
+
+    // Rec. 709 relative luminance, for grayscale conversion
+    pub fn luminance(&self) -> Scalar {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn clamp(&self) -> Color {
+        Color::new(self.r.clamp(0., 1.), self.g.clamp(0., 1.), self.b.clamp(0., 1.))
+    }
+
+    // Gamma encoding; negative channels have no real root, so clamp first
+    pub fn gamma(&self, gamma: Scalar) -> Color {
+        let exponent = 1. / gamma;
+        Color::new(self.r.powf(exponent), self.g.powf(exponent), self.b.powf(exponent))
+    }
 }
 
 #[cfg(test)]
@@ -116,6 +153,13 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn dividing_color_by_scalar() {
+        let c = Color::new(0.4, 0.6, 0.8);
+
+        assert_eq!(c / 4., Color::new(0.1, 0.15, 0.2));
+    }
+
     #[test]
     fn multiplying_colors() {
         let c1 = Color::new(1., 0.2, 0.4);
@@ -126,4 +170,46 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn hadamard_product_matches_multiplication() {
+        let c1 = Color::new(1., 0.2, 0.4);
+        let c2 = Color::new(0.9, 1., 0.1);
+
+        assert_eq!(c1.hadamard(&c2), c1 * c2);
+        assert_eq!(c1.hadamard(&c2), Color::new(0.9, 0.2, 0.04));
+    }
+
+    #[test]
+    fn interpolating_between_colors() {
+        let a = Color::new(0.2, 0.4, 1.);
+        let b = Color::new(1., 0., 0.5);
+
+        assert_eq!(a.lerp(&b, 0.), a);
+        assert_eq!(a.lerp(&b, 1.), b);
+        assert_eq!(a.lerp(&b, 0.5), Color::new(0.6, 0.2, 0.75));
+    }
+
+    #[test]
+    fn luminance_weights_channels_perceptually() {
+        assert_eq!(WHITE.luminance(), 1.);
+        assert_eq!(BLACK.luminance(), 0.);
+        assert!(GREEN.luminance() > RED.luminance() && RED.luminance() > BLUE.luminance());
+        assert!(crate::approx_eq(Color::new(0.5, 0.5, 0.5).luminance(), 0.5));
+    }
+
+    #[test]
+    fn clamping_color_channels() {
+        let c = Color::new(1.5, -0.5, 0.25).clamp();
+
+        assert_eq!(c, Color::new(1., 0., 0.25));
+    }
+
+    #[test]
+    fn gamma_correcting_color() {
+        let c = Color::new(0.5, 0., 1.);
+
+        assert_eq!(c.gamma(2.2), Color::new(0.72974, 0., 1.));
+        assert_eq!(c.gamma(1.), c);
+    }
+}
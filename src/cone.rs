@@ -0,0 +1,230 @@
+use super::bounds::BoundingBox;
+use super::cylinder::push_cap_hit_unless_on_rim;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use super::{Scalar, EPSILON};
+use std::any::Any;
+
+// Double-napped cone x² + z² = y² with its tip at the origin, optionally truncated to minimum < y < maximum
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cone {
+    id: ShapeId,
+    transforms: Transforms,
+    material: Material,
+    minimum: Scalar,
+    maximum: Scalar,
+    closed: bool,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            id: ShapeId::default(),
+            transforms: Transforms::default(),
+            material: Material::default(),
+            minimum: Scalar::NEG_INFINITY,
+            maximum: Scalar::INFINITY,
+            closed: false,
+        }
+    }
+}
+
+// Whether the ray at t lies within the cap at height y, whose radius is |y|
+fn check_cap(ray: Ray, t: Scalar, y: Scalar) -> bool {
+    let x = ray.origin.x + t * ray.direction.x;
+    let z = ray.origin.z + t * ray.direction.z;
+    x * x + z * z <= y * y + EPSILON
+}
+
+impl Shape for Cone {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let (origin, direction) = (object_ray.origin, object_ray.direction);
+        let mut xs = vec![];
+        let a = direction.x * direction.x - direction.y * direction.y + direction.z * direction.z;
+        let b = 2. * origin.x * direction.x - 2. * origin.y * direction.y + 2. * origin.z * direction.z;
+        let c = origin.x * origin.x - origin.y * origin.y + origin.z * origin.z;
+        let mut side_hits = vec![];
+        if super::approx_eq(0., a) {
+            // Parallel to one of the halves, so the ray crosses the other one exactly once
+            if !super::approx_eq(0., b) {
+                side_hits.push(-c / (2. * b));
+            }
+        } else {
+            // Rays grazing the surface can come out marginally negative through rounding
+            let discriminant = b * b - 4. * a * c;
+            if discriminant < -EPSILON {
+                return xs;
+            }
+            let root = discriminant.max(0.).sqrt();
+            let t0 = (-b - root) / (2. * a);
+            let t1 = (-b + root) / (2. * a);
+            side_hits.push(t0.min(t1));
+            side_hits.push(t0.max(t1));
+        }
+        for t in side_hits {
+            let y = origin.y + t * direction.y;
+            if self.minimum < y && y < self.maximum {
+                xs.push(IntersectionRef::new(t, self));
+            }
+        }
+        if self.closed && !super::approx_eq(0., direction.y) {
+            let side_hits = xs.len();
+            for bound in [self.minimum, self.maximum].iter() {
+                let t = (bound - origin.y) / direction.y;
+                if check_cap(object_ray, t, *bound) {
+                    push_cap_hit_unless_on_rim(&mut xs, side_hits, t, self);
+                }
+            }
+        }
+        xs
+    }
+
+    fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
+        let (x, y, z) = (object_point.x, object_point.y, object_point.z);
+        let distance = x * x + z * z;
+        if distance < y * y && y >= self.maximum - EPSILON {
+            Tuple::vector(0., 1., 0.)
+        } else if distance < y * y && y <= self.minimum + EPSILON {
+            Tuple::vector(0., -1., 0.)
+        } else {
+            let side_y = distance.sqrt();
+            Tuple::vector(x, if y > 0. { -side_y } else { side_y }, z)
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let radius = self.minimum.abs().max(self.maximum.abs());
+        BoundingBox::new(Tuple::point(-radius, self.minimum, -radius), Tuple::point(radius, self.maximum, radius))
+    }
+}
+
+impl Cone {
+    pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
+        Self {
+            transforms: Transforms::new(transform),
+            material: material.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_boxed(material: Option<Material>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Cone::new(material, transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Cone::default())
+    }
+
+    pub fn truncated(self, minimum: Scalar, maximum: Scalar, closed: bool) -> Self {
+        Self { minimum, maximum, closed, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersecting_cone_with_ray() {
+        let shape = Cone::default();
+        let cases = [
+            (Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.), 5., 5.),
+            (Tuple::point(0., 0., -5.), Tuple::vector(1., 1., 1.), 8.66025, 8.66025),
+            (Tuple::point(1., 1., -5.), Tuple::vector(-0.5, -1., 1.), 4.55006, 49.44994)];
+
+        for (origin, direction, t0, t1) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            let xs = shape.inner_intersect(r);
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < 0.001);
+            assert!((xs[1].t - t1).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn intersecting_cone_with_ray_parallel_to_one_half() {
+        let shape = Cone::default();
+        let r = Ray::new(Tuple::point(0., 0., -1.), Tuple::vector(0., 1., 1.).normalize());
+        let xs = shape.inner_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 0.35355).abs() < 0.0001);
+    }
+
+    #[test]
+    fn intersecting_cone_end_caps() {
+        let shape = Cone::default().truncated(-0.5, 0.5, true);
+        let cases = [
+            (Tuple::point(0., 0., -5.), Tuple::vector(0., 1., 0.), 0),
+            (Tuple::point(0., 0., -0.25), Tuple::vector(0., 1., 1.), 2),
+            (Tuple::point(0., 0., -0.25), Tuple::vector(0., 1., 0.), 4)];
+
+        for (origin, direction, count) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            assert_eq!(shape.inner_intersect(r).len(), *count);
+        }
+    }
+
+    #[test]
+    fn normal_on_cone() {
+        let shape = Cone::default();
+        let cases = [
+            (Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 0.)),
+            (Tuple::point(1., 1., 1.), Tuple::vector(1., -Scalar::sqrt(2.), 1.)),
+            (Tuple::point(-1., -1., 0.), Tuple::vector(-1., 1., 0.))];
+
+        for (point, normal) in cases.iter() {
+            assert_eq!(shape.inner_normal_at(*point), *normal);
+        }
+    }
+
+    #[test]
+    fn bounds_of_cones() {
+        let unbounded = Cone::default().bounds();
+        let truncated = Cone::default().truncated(-5., 3., false).bounds();
+
+        assert_eq!(unbounded, BoundingBox::infinite());
+        assert_eq!(truncated.min, Tuple::point(-5., -5., -5.));
+        assert_eq!(truncated.max, Tuple::point(5., 3., 5.));
+    }
+}
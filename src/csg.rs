@@ -0,0 +1,282 @@
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use std::any::Any;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference
+}
+
+// Whether a hit on the left (lhit) or right child survives the operation, given whether the ray
+// is currently inside the left (inl) and inside the right (inr) child
+pub fn intersection_allowed(operation: CsgOperation, lhit: bool, inl: bool, inr: bool) -> bool {
+    match operation {
+        CsgOperation::Union => (lhit && !inr) || (!lhit && !inl),
+        CsgOperation::Intersection => (lhit && inr) || (!lhit && inl),
+        CsgOperation::Difference => (lhit && !inr) || (!lhit && inl)
+    }
+}
+
+// Like a group, a CSG shape has no surface of its own and hits are reported on the children
+#[derive(Debug, Clone)]
+pub struct Csg {
+    id: ShapeId,
+    operation: CsgOperation,
+    left: BoxShape,
+    right: BoxShape,
+    transforms: Transforms,
+    material: Material,
+}
+
+impl PartialEq for Csg {
+    fn eq(&self, other: &Self) -> bool {
+        self.operation == other.operation &&
+            self.left.box_eq(other.left.as_any()) &&
+            self.right.box_eq(other.right.as_any()) &&
+            self.transformation() == other.transformation()
+    }
+}
+
+impl Shape for Csg {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let mut xs: Vec<(bool, IntersectionRef)> = self.left.intersect_ref(object_ray).into_iter().map(|i| (true, i))
+            .chain(self.right.intersect_ref(object_ray).into_iter().map(|i| (false, i)))
+            .filter(|(_, i)| !i.t.is_nan())
+            .collect();
+        xs.sort_by(|(_, a), (_, b)| a.t.total_cmp(&b.t));
+        self.filter_intersections(xs)
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        unreachable!("a CSG shape never owns a hit; take the normal from the child that was hit")
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+        self.left.renew_ids();
+        self.right.renew_ids();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = self.left.parent_space_bounds();
+        bounds.add_box(&self.right.parent_space_bounds());
+        bounds
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        self.left.divide(threshold);
+        self.right.divide(threshold);
+    }
+
+    fn set_parent_transformation(&mut self, parent: Matrix) {
+        self.transforms.set_parent(parent);
+        self.update_children();
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transforms.set_transform(transform);
+        self.update_children();
+    }
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: BoxShape, right: BoxShape, transform: Option<Matrix>) -> Self {
+        let mut csg = Self {
+            id: ShapeId::default(),
+            operation,
+            left,
+            right,
+            transforms: Transforms::new(transform),
+            material: Material::default(),
+        };
+        csg.update_children();
+        csg
+    }
+
+    fn update_children(&mut self) {
+        let chain = self.transforms.chain();
+        self.left.set_parent_transformation(chain);
+        self.right.set_parent_transformation(chain);
+    }
+
+    pub fn new_boxed(operation: CsgOperation, left: BoxShape, right: BoxShape, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Csg::new(operation, left, right, transform))
+    }
+
+    pub fn left(&self) -> &dyn Shape {
+        &*self.left
+    }
+
+    pub fn right(&self) -> &dyn Shape {
+        &*self.right
+    }
+
+    // Takes the sorted hits on both children, each flagged with whether it came from the left one
+    pub fn filter_intersections<'a>(&self, xs: Vec<(bool, IntersectionRef<'a>)>) -> Vec<IntersectionRef<'a>> {
+        let (mut inl, mut inr) = (false, false);
+        let mut result = vec![];
+        for (lhit, i) in xs {
+            if intersection_allowed(self.operation, lhit, inl, inr) {
+                result.push(i);
+            }
+            if lhit {
+                inl = !inl;
+            } else {
+                inr = !inr;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn csg_is_created_with_operation_and_two_shapes() {
+        let s1 = Sphere::default_boxed();
+        let s2 = Cube::default_boxed();
+        let c = Csg::new(CsgOperation::Union, s1.clone(), s2.clone(), None);
+
+        assert!(c.left().box_eq(s1.as_any()));
+        assert!(c.right().box_eq(s2.as_any()));
+    }
+
+    #[test]
+    #[should_panic(expected = "a CSG shape never owns a hit")]
+    fn csg_has_no_normal_of_its_own() {
+        let c = Csg::new(CsgOperation::Union, Sphere::default_boxed(), Cube::default_boxed(), None);
+        c.normal_at(Tuple::point(1., 0., 0.));
+    }
+
+    #[test]
+    fn evaluating_rules_for_csg_operations() {
+        use CsgOperation::*;
+        // (lhit, inl, inr) followed by the expected result for union, intersection and difference
+        let table = [
+            (true, true, true, false, true, false),
+            (true, true, false, true, false, true),
+            (true, false, true, false, true, false),
+            (true, false, false, true, false, true),
+            (false, true, true, false, true, true),
+            (false, true, false, false, true, true),
+            (false, false, true, true, false, false),
+            (false, false, false, true, false, false),
+        ];
+        for &(lhit, inl, inr, union, intersection, difference) in table.iter() {
+            assert_eq!(intersection_allowed(Union, lhit, inl, inr), union);
+            assert_eq!(intersection_allowed(Intersection, lhit, inl, inr), intersection);
+            assert_eq!(intersection_allowed(Difference, lhit, inl, inr), difference);
+        }
+    }
+
+    #[test]
+    fn filtering_list_of_intersections() {
+        let s1 = Sphere::default();
+        let s2 = Cube::default();
+        let xs = || vec![
+            (true, IntersectionRef::new(1., &s1)),
+            (false, IntersectionRef::new(2., &s2)),
+            (true, IntersectionRef::new(3., &s1)),
+            (false, IntersectionRef::new(4., &s2)),
+        ];
+        let cases = [
+            (CsgOperation::Union, [1., 4.]),
+            (CsgOperation::Intersection, [2., 3.]),
+            (CsgOperation::Difference, [1., 2.]),
+        ];
+        for (operation, expected) in cases.iter() {
+            let c = Csg::new(*operation, Sphere::default_boxed(), Cube::default_boxed(), None);
+            let ts: Vec<_> = c.filter_intersections(xs()).iter().map(|i| i.t).collect();
+            assert_eq!(ts, expected.to_vec());
+        }
+    }
+
+    #[test]
+    fn ray_misses_csg_object() {
+        let c = Csg::new(CsgOperation::Union, Sphere::default_boxed(), Cube::default_boxed(), None);
+        let r = Ray::new(Tuple::point(0., 2., -5.), Tuple::vector(0., 0., 1.));
+
+        assert!(c.inner_intersect_ref(r).is_empty());
+    }
+
+    #[test]
+    fn ray_hits_csg_object() {
+        let s1 = Sphere::default_boxed();
+        let s2 = Sphere::new_boxed(None, Some(Matrix::translation(0., 0., 0.5)));
+        let c = Csg::new(CsgOperation::Union, s1.clone(), s2.clone(), None);
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = c.inner_intersect_ref(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.);
+        assert!(xs[0].object.box_eq(s1.as_any()));
+        assert_eq!(xs[1].t, 6.5);
+        assert!(xs[1].object.box_eq(s2.as_any()));
+    }
+
+    #[test]
+    fn transforming_csg_moves_both_children() {
+        let c = Csg::new(CsgOperation::Union, Sphere::default_boxed(), Cube::default_boxed(), None)
+            .with_transform(Matrix::translation(5., 0., 0.));
+        let r = Ray::new(Tuple::point(5., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        assert_eq!(c.intersect_ref(r).len(), 2);
+        assert_eq!(c.left().to_object_space(Tuple::point(5., 0., 0.)), Tuple::point(0., 0., 0.));
+        assert_eq!(c.right().to_object_space(Tuple::point(5., 0., 0.)), Tuple::point(0., 0., 0.));
+    }
+
+    #[test]
+    fn difference_carves_sphere_out_of_cube() {
+        let hole = Sphere::new_boxed(None, Some(Matrix::scaling(0.5, 0.5, 0.5)));
+        let c = Csg::new(CsgOperation::Difference, Cube::default_boxed(), hole, Some(Matrix::translation(0., 0., 1.)));
+        let through_hole = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let beside_hole = Ray::new(Tuple::point(0.75, 0., -5.), Tuple::vector(0., 0., 1.));
+
+        let ts: Vec<_> = c.intersect_ref(through_hole).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![5., 5.5, 6.5, 7.]);
+        assert_eq!(c.intersect_ref(beside_hole).len(), 2);
+    }
+}
@@ -0,0 +1,158 @@
+use super::bounds::{check_axis, BoundingBox};
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use std::any::Any;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Cube {
+    id: ShapeId,
+    transforms: Transforms,
+    material: Material,
+}
+
+impl Shape for Cube {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let (xtmin, xtmax) = check_axis(object_ray.origin.x, object_ray.direction.x, -1., 1.);
+        let (ytmin, ytmax) = check_axis(object_ray.origin.y, object_ray.direction.y, -1., 1.);
+        let (ztmin, ztmax) = check_axis(object_ray.origin.z, object_ray.direction.z, -1., 1.);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin > tmax {
+            return vec![];
+        }
+        vec![IntersectionRef::new(tmin, self), IntersectionRef::new(tmax, self)]
+    }
+
+    fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
+        let (x, y, z) = (object_point.x, object_point.y, object_point.z);
+        let maxc = x.abs().max(y.abs()).max(z.abs());
+        if maxc == x.abs() {
+            Tuple::vector(x, 0., 0.)
+        } else if maxc == y.abs() {
+            Tuple::vector(0., y, 0.)
+        } else {
+            Tuple::vector(0., 0., z)
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1., -1., -1.), Tuple::point(1., 1., 1.))
+    }
+}
+
+impl Cube {
+    pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
+        Self {
+            transforms: Transforms::new(transform),
+            material: material.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_boxed(material: Option<Material>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Cube::new(material, transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Cube::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_intersects_each_face_of_cube() {
+        let c = Cube::default();
+        let cases = [
+            (Tuple::point(5., 0.5, 0.), Tuple::vector(-1., 0., 0.), 4., 6.),
+            (Tuple::point(-5., 0.5, 0.), Tuple::vector(1., 0., 0.), 4., 6.),
+            (Tuple::point(0.5, 5., 0.), Tuple::vector(0., -1., 0.), 4., 6.),
+            (Tuple::point(0.5, -5., 0.), Tuple::vector(0., 1., 0.), 4., 6.),
+            (Tuple::point(0.5, 0., 5.), Tuple::vector(0., 0., -1.), 4., 6.),
+            (Tuple::point(0.5, 0., -5.), Tuple::vector(0., 0., 1.), 4., 6.),
+            (Tuple::point(0., 0.5, 0.), Tuple::vector(0., 0., 1.), -1., 1.)];
+
+        for (origin, direction, t1, t2) in cases.iter() {
+            let xs = c.inner_intersect(Ray::new(*origin, *direction));
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0].t, *t1);
+            assert_eq!(xs[1].t, *t2);
+        }
+    }
+
+    #[test]
+    fn ray_misses_cube() {
+        let c = Cube::default();
+        let cases = [
+            (Tuple::point(-2., 0., 0.), Tuple::vector(0.2673, 0.5345, 0.8018)),
+            (Tuple::point(0., -2., 0.), Tuple::vector(0.8018, 0.2673, 0.5345)),
+            (Tuple::point(0., 0., -2.), Tuple::vector(0.5345, 0.8018, 0.2673)),
+            (Tuple::point(2., 0., 2.), Tuple::vector(0., 0., -1.)),
+            (Tuple::point(0., 2., 2.), Tuple::vector(0., -1., 0.)),
+            (Tuple::point(2., 2., 0.), Tuple::vector(-1., 0., 0.))];
+
+        for (origin, direction) in cases.iter() {
+            assert!(c.inner_intersect(Ray::new(*origin, *direction)).is_empty());
+        }
+    }
+
+    #[test]
+    fn normal_on_surface_of_cube() {
+        let c = Cube::default();
+        let cases = [
+            (Tuple::point(1., 0.5, -0.8), Tuple::vector(1., 0., 0.)),
+            (Tuple::point(-1., -0.2, 0.9), Tuple::vector(-1., 0., 0.)),
+            (Tuple::point(-0.4, 1., -0.1), Tuple::vector(0., 1., 0.)),
+            (Tuple::point(0.3, -1., -0.7), Tuple::vector(0., -1., 0.)),
+            (Tuple::point(-0.6, 0.3, 1.), Tuple::vector(0., 0., 1.)),
+            (Tuple::point(0.4, 0.4, -1.), Tuple::vector(0., 0., -1.)),
+            (Tuple::point(1., 1., 1.), Tuple::vector(1., 0., 0.)),
+            (Tuple::point(-1., -1., -1.), Tuple::vector(-1., 0., 0.))];
+
+        for (point, normal) in cases.iter() {
+            assert_eq!(c.inner_normal_at(*point), *normal);
+        }
+    }
+}
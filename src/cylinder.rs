@@ -0,0 +1,271 @@
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use super::{Scalar, EPSILON};
+use std::any::Any;
+
+// Unit-radius cylinder around the y axis, optionally truncated to minimum < y < maximum
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cylinder {
+    id: ShapeId,
+    transforms: Transforms,
+    material: Material,
+    minimum: Scalar,
+    maximum: Scalar,
+    closed: bool,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self {
+            id: ShapeId::default(),
+            transforms: Transforms::default(),
+            material: Material::default(),
+            minimum: Scalar::NEG_INFINITY,
+            maximum: Scalar::INFINITY,
+            closed: false,
+        }
+    }
+}
+
+// Whether the ray at t lies within the unit radius, i.e. on a cap
+fn check_cap(ray: Ray, t: Scalar) -> bool {
+    let x = ray.origin.x + t * ray.direction.x;
+    let z = ray.origin.z + t * ray.direction.z;
+    x * x + z * z <= 1. + EPSILON
+}
+
+// Adds a cap hit at t unless one of the first side_hits entries of xs is already there: a ray
+// through the rim hits the side at the same t, and a second hit would make the hit count odd
+// and confuse inside/outside tracking for refraction. Shared with cones.
+pub fn push_cap_hit_unless_on_rim<'a>(xs: &mut Vec<IntersectionRef<'a>>, side_hits: usize, t: Scalar, shape: &'a dyn Shape) {
+    if !xs[..side_hits].iter().any(|i| super::approx_eq(i.t, t)) {
+        xs.push(IntersectionRef::new(t, shape));
+    }
+}
+
+impl Shape for Cylinder {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let (origin, direction) = (object_ray.origin, object_ray.direction);
+        let mut xs = vec![];
+        let a = direction.x * direction.x + direction.z * direction.z;
+        // A ray parallel to the y axis can only hit the caps
+        if !super::approx_eq(0., a) {
+            let b = 2. * origin.x * direction.x + 2. * origin.z * direction.z;
+            let c = origin.x * origin.x + origin.z * origin.z - 1.;
+            let discriminant = b * b - 4. * a * c;
+            if discriminant < 0. {
+                return xs;
+            }
+            let t0 = (-b - discriminant.sqrt()) / (2. * a);
+            let t1 = (-b + discriminant.sqrt()) / (2. * a);
+            for t in [t0.min(t1), t0.max(t1)].iter() {
+                let y = origin.y + t * direction.y;
+                if self.minimum < y && y < self.maximum {
+                    xs.push(IntersectionRef::new(*t, self));
+                }
+            }
+        }
+        if self.closed && !super::approx_eq(0., direction.y) {
+            let side_hits = xs.len();
+            for bound in [self.minimum, self.maximum].iter() {
+                let t = (bound - origin.y) / direction.y;
+                if check_cap(object_ray, t) {
+                    push_cap_hit_unless_on_rim(&mut xs, side_hits, t, self);
+                }
+            }
+        }
+        xs
+    }
+
+    fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
+        let distance = object_point.x * object_point.x + object_point.z * object_point.z;
+        if distance < 1. && object_point.y >= self.maximum - EPSILON {
+            Tuple::vector(0., 1., 0.)
+        } else if distance < 1. && object_point.y <= self.minimum + EPSILON {
+            Tuple::vector(0., -1., 0.)
+        } else {
+            Tuple::vector(object_point.x, 0., object_point.z)
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1., self.minimum, -1.), Tuple::point(1., self.maximum, 1.))
+    }
+}
+
+impl Cylinder {
+    pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
+        Self {
+            transforms: Transforms::new(transform),
+            material: material.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_boxed(material: Option<Material>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Cylinder::new(material, transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Cylinder::default())
+    }
+
+    pub fn truncated(self, minimum: Scalar, maximum: Scalar, closed: bool) -> Self {
+        Self { minimum, maximum, closed, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_misses_cylinder() {
+        let cyl = Cylinder::default();
+        let cases = [
+            (Tuple::point(1., 0., 0.), Tuple::vector(0., 1., 0.)),
+            (Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.)),
+            (Tuple::point(0., 0., -5.), Tuple::vector(1., 1., 1.))];
+
+        for (origin, direction) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            assert!(cyl.inner_intersect(r).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_cylinder() {
+        let cyl = Cylinder::default();
+        let cases = [
+            (Tuple::point(1., 0., -5.), Tuple::vector(0., 0., 1.), 5., 5.),
+            (Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.), 4., 6.),
+            (Tuple::point(0.5, 0., -5.), Tuple::vector(0.1, 1., 1.), 6.80798, 7.08872)];
+
+        for (origin, direction, t0, t1) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            let xs = cyl.inner_intersect(r);
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < 0.0001);
+            assert!((xs[1].t - t1).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn normal_on_cylinder_side() {
+        let cyl = Cylinder::default();
+        assert_eq!(cyl.inner_normal_at(Tuple::point(1., 0., 0.)), Tuple::vector(1., 0., 0.));
+        assert_eq!(cyl.inner_normal_at(Tuple::point(0., 5., -1.)), Tuple::vector(0., 0., -1.));
+        assert_eq!(cyl.inner_normal_at(Tuple::point(0., -2., 1.)), Tuple::vector(0., 0., 1.));
+        assert_eq!(cyl.inner_normal_at(Tuple::point(-1., 1., 0.)), Tuple::vector(-1., 0., 0.));
+    }
+
+    #[test]
+    fn default_cylinder_is_unbounded_and_open() {
+        let cyl = Cylinder::default();
+        assert_eq!(cyl.minimum, Scalar::NEG_INFINITY);
+        assert_eq!(cyl.maximum, Scalar::INFINITY);
+        assert!(!cyl.closed);
+    }
+
+    #[test]
+    fn intersecting_truncated_cylinder() {
+        let cyl = Cylinder::default().truncated(1., 2., false);
+        let cases = [
+            (Tuple::point(0., 1.5, 0.), Tuple::vector(0.1, 1., 0.), 0),
+            (Tuple::point(0., 3., -5.), Tuple::vector(0., 0., 1.), 0),
+            (Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.), 0),
+            (Tuple::point(0., 2., -5.), Tuple::vector(0., 0., 1.), 0),
+            (Tuple::point(0., 1., -5.), Tuple::vector(0., 0., 1.), 0),
+            (Tuple::point(0., 1.5, -2.), Tuple::vector(0., 0., 1.), 2)];
+
+        for (origin, direction, count) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            assert_eq!(cyl.inner_intersect(r).len(), *count);
+        }
+    }
+
+    #[test]
+    fn intersecting_caps_of_closed_cylinder() {
+        let cyl = Cylinder::default().truncated(1., 2., true);
+        let cases = [
+            (Tuple::point(0., 3., 0.), Tuple::vector(0., -1., 0.), 2),
+            (Tuple::point(0., 3., -2.), Tuple::vector(0., -1., 2.), 2),
+            (Tuple::point(0., 4., -2.), Tuple::vector(0., -1., 1.), 2),
+            (Tuple::point(0., 0., -2.), Tuple::vector(0., 1., 2.), 2),
+            (Tuple::point(0., -1., -2.), Tuple::vector(0., 1., 1.), 2)];
+
+        for (origin, direction, count) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            assert_eq!(cyl.inner_intersect(r).len(), *count);
+        }
+    }
+
+    #[test]
+    fn normal_on_cylinder_end_caps() {
+        let cyl = Cylinder::default().truncated(1., 2., true);
+        let cases = [
+            (Tuple::point(0., 1., 0.), Tuple::vector(0., -1., 0.)),
+            (Tuple::point(0.5, 1., 0.), Tuple::vector(0., -1., 0.)),
+            (Tuple::point(0., 1., 0.5), Tuple::vector(0., -1., 0.)),
+            (Tuple::point(0., 2., 0.), Tuple::vector(0., 1., 0.)),
+            (Tuple::point(0.5, 2., 0.), Tuple::vector(0., 1., 0.)),
+            (Tuple::point(0., 2., 0.5), Tuple::vector(0., 1., 0.))];
+
+        for (point, normal) in cases.iter() {
+            assert_eq!(cyl.inner_normal_at(*point), *normal);
+        }
+    }
+
+    #[test]
+    fn builders_chain_with_truncation() {
+        let c = Cylinder::default()
+            .truncated(0., 1., true)
+            .with_transform(Matrix::translation(0., 1., 0.));
+        let r = Ray::new(Tuple::point(0., 3., 0.), Tuple::vector(0., -1., 0.));
+        let xs = c.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!((xs[0].t, xs[1].t), (1., 2.));
+    }
+}
@@ -0,0 +1,167 @@
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use std::any::Any;
+use super::Scalar;
+
+// Unit disk in the z = 0 plane, facing -z, i.e. towards a camera looking down the z axis as
+// set up by default; a non-zero inner radius cuts out a hole to make a ring
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disk {
+    id: ShapeId,
+    transforms: Transforms,
+    material: Material,
+    inner_radius: Scalar,
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            id: ShapeId::default(),
+            transforms: Transforms::default(),
+            material: Material::default(),
+            inner_radius: 0.,
+        }
+    }
+}
+
+impl Shape for Disk {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        if super::approx_eq(0., object_ray.direction.z) {
+            return vec![];
+        }
+        let t = -object_ray.origin.z / object_ray.direction.z;
+        let (x, y) = (object_ray.origin.x + t * object_ray.direction.x, object_ray.origin.y + t * object_ray.direction.y);
+        let distance = x * x + y * y;
+        if distance > 1. || distance < self.inner_radius * self.inner_radius {
+            return vec![];
+        }
+        vec![IntersectionRef::new(t, self)]
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        Tuple::vector(0., 0., -1.)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1., -1., 0.), Tuple::point(1., 1., 0.))
+    }
+}
+
+impl Disk {
+    pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
+        Self {
+            transforms: Transforms::new(transform),
+            material: material.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_boxed(material: Option<Material>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Disk::new(material, transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Disk::default())
+    }
+
+    // Radius of the hole in the middle, as a fraction of the outer radius
+    pub fn with_inner_radius(self, inner_radius: Scalar) -> Self {
+        Self { inner_radius, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(disk: &Disk, origin: Tuple) -> usize {
+        disk.inner_intersect(Ray::new(origin, Tuple::vector(0., 0., 1.))).len()
+    }
+
+    #[test]
+    fn ray_hits_disk_within_radius() {
+        let disk = Disk::default();
+        let xs = disk.inner_intersect(Ray::new(Tuple::point(0.5, -0.5, -2.), Tuple::vector(0., 0., 1.)));
+
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.);
+        assert_eq!(hits(&disk, Tuple::point(0., 0., -1.)), 1);
+        assert_eq!(hits(&disk, Tuple::point(0.8, 0.7, -1.)), 0);
+        assert_eq!(disk.inner_intersect(Ray::new(Tuple::point(0., -5., -0.5), Tuple::vector(0., 1., 0.))).len(), 0);
+    }
+
+    #[test]
+    fn disk_faces_negative_z() {
+        assert_eq!(Disk::default().normal_at(Tuple::point(0.5, 0.5, 0.)), Tuple::vector(0., 0., -1.));
+    }
+
+    #[test]
+    fn ring_has_a_hole() {
+        let ring = Disk::default().with_inner_radius(0.5);
+
+        assert_eq!(hits(&ring, Tuple::point(0., 0., -1.)), 0);
+        assert_eq!(hits(&ring, Tuple::point(0.3, 0.3, -1.)), 0);
+        assert_eq!(hits(&ring, Tuple::point(0., 0.75, -1.)), 1);
+        assert_eq!(hits(&ring, Tuple::point(0., 1.25, -1.)), 0);
+    }
+
+    #[test]
+    fn transformed_disk() {
+        let disk = Disk::new(None, Some(Matrix::translation(0., 3., 0.) * Matrix::rotation_x(crate::consts::FRAC_PI_2) * Matrix::scaling(2., 2., 2.)));
+        let r = Ray::new(Tuple::point(1.5, 0., 0.), Tuple::vector(0., 1., 0.));
+        let xs = disk.intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 3.);
+        assert_eq!(disk.normal_at(Tuple::point(1.5, 3., 0.)), Tuple::vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn disk_bounds() {
+        let bounds = Disk::default().bounds();
+
+        assert_eq!(bounds.min, Tuple::point(-1., -1., 0.));
+        assert_eq!(bounds.max, Tuple::point(1., 1., 0.));
+    }
+}
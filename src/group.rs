@@ -0,0 +1,438 @@
+use super::bounds::BoundingBox;
+use super::intersection::{IntersectionRef, sort_refs};
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::sampling::Rng;
+use super::instance::Instance;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use super::Scalar;
+use std::any::Any;
+use std::sync::Arc;
+
+// A group has no surface of its own; hits are always reported on its children, which are told
+// about the group's transform (and those of any enclosing groups) as they are added.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Group {
+    id: ShapeId,
+    transforms: Transforms,
+    material: Material,
+    children: Vec<BoxShape>,
+    // Union of the children's bounds in group space, kept up to date by add_child
+    bounds: BoundingBox,
+}
+
+impl Shape for Group {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        if !self.bounds.intersects(object_ray) {
+            return vec![];
+        }
+        let mut xs: Vec<IntersectionRef> = self.children.iter().flat_map(|c| c.intersect_ref(object_ray)).collect();
+        sort_refs(&mut xs);
+        xs
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        unreachable!("a group never owns a hit; take the normal from the child that was hit")
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+        for child in self.children.iter_mut() {
+            child.renew_ids();
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        if threshold <= self.children.len() {
+            let (left, right) = self.partition_children();
+            if self.children.is_empty() && (left.is_empty() || right.is_empty()) {
+                // Degenerate bounds put everything on one side; another level would not split it either
+                self.children = left.into_iter().chain(right).collect();
+            } else {
+                for half in vec![left, right].into_iter().filter(|half| !half.is_empty()) {
+                    self.add_child(Group::new_boxed(None, half));
+                }
+            }
+        }
+        for child in self.children.iter_mut() {
+            child.divide(threshold);
+        }
+        self.sort_children_by_centroid();
+    }
+
+    fn set_parent_transformation(&mut self, parent: Matrix) {
+        self.transforms.set_parent(parent);
+        self.update_children();
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transforms.set_transform(transform);
+        self.update_children();
+    }
+}
+
+impl Group {
+    pub fn new(transform: Option<Matrix>, children: Vec<BoxShape>) -> Self {
+        let mut group = Self { transforms: Transforms::new(transform), ..Default::default() };
+        for child in children {
+            group.add_child(child);
+        }
+        group
+    }
+
+    pub fn new_boxed(transform: Option<Matrix>, children: Vec<BoxShape>) -> BoxShape {
+        Box::new(Group::new(transform, children))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Group::default())
+    }
+
+    // nx by nz instances of the prototype, spacing apart on the xz plane starting at the origin,
+    // e.g. to tile a floor. Each instance is translated on top of the prototype's own transform.
+    pub fn grid(prototype: &Arc<dyn Shape>, nx: usize, nz: usize, spacing: Scalar) -> Self {
+        let instances = (0..nz)
+            .flat_map(|z| (0..nx).map(move |x| (x, z)))
+            .map(|(x, z)| Instance::new_boxed(Arc::clone(prototype), Some(Matrix::translation(x as Scalar * spacing, 0., z as Scalar * spacing))))
+            .collect();
+        Group::new(None, instances)
+    }
+
+    // count instances of the prototype moved to random points inside bounds; a seed always gives
+    // the same placement
+    pub fn scatter(prototype: &Arc<dyn Shape>, count: usize, bounds: BoundingBox, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut between = |min: Scalar, max: Scalar| min + (max - min) * rng.next_scalar();
+        let instances = (0..count)
+            .map(|_| {
+                let (x, y, z) = (between(bounds.min.x, bounds.max.x), between(bounds.min.y, bounds.max.y), between(bounds.min.z, bounds.max.z));
+                Instance::new_boxed(Arc::clone(prototype), Some(Matrix::translation(x, y, z)))
+            })
+            .collect();
+        Group::new(None, instances)
+    }
+
+    pub fn add_child(&mut self, mut child: BoxShape) {
+        child.set_parent_transformation(self.transforms.chain());
+        self.bounds.add_box(&child.parent_space_bounds());
+        self.children.push(child);
+    }
+
+    fn update_children(&mut self) {
+        let chain = self.transforms.chain();
+        for child in self.children.iter_mut() {
+            child.set_parent_transformation(chain);
+        }
+    }
+
+    // Moves the children that fit entirely into either half of the group's bounds out of the group;
+    // children straddling the split (or any child of an unbounded group) stay where they are
+    pub fn partition_children(&mut self) -> (Vec<BoxShape>, Vec<BoxShape>) {
+        if !self.bounds.is_finite() {
+            return (vec![], vec![]);
+        }
+        let (left_bounds, right_bounds) = self.bounds.split();
+        let (mut left, mut right, mut remaining) = (vec![], vec![], vec![]);
+        for child in self.children.drain(..) {
+            let child_bounds = child.parent_space_bounds();
+            if left_bounds.contains_box(&child_bounds) {
+                left.push(child);
+            } else if right_bounds.contains_box(&child_bounds) {
+                right.push(child);
+            } else {
+                remaining.push(child);
+            }
+        }
+        self.children = remaining;
+        (left, right)
+    }
+
+    // Orders the children along the group's widest axis by the centre of their bounds, so children
+    // that are near each other are also tested one after the other. Hits are sorted by t regardless.
+    pub fn sort_children_by_centroid(&mut self) {
+        if !self.bounds.is_finite() {
+            return;
+        }
+        let axis = self.bounds.widest_axis();
+        let key = |child: &BoxShape| {
+            let centroid = child.parent_space_bounds().centroid();
+            [centroid.x, centroid.y, centroid.z][axis]
+        };
+        self.children.sort_by(|a, b| key(a).total_cmp(&key(b)));
+    }
+
+    pub fn children(&self) -> &[BoxShape] {
+        &self.children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use crate::consts::FRAC_PI_2;
+    use crate::Scalar;
+
+    fn only_child(shape: &dyn Shape) -> &dyn Shape {
+        &*shape.as_any().downcast_ref::<Group>().unwrap().children()[0]
+    }
+
+    // A sphere inside a scaled group, inside a rotated group
+    fn nested_groups(scaling: Matrix) -> Group {
+        let s = Sphere::new_boxed(None, Some(Matrix::translation(5., 0., 0.)));
+        let g2 = Group::new_boxed(Some(scaling), vec![s]);
+        Group::new(Some(Matrix::rotation_y(FRAC_PI_2)), vec![g2])
+    }
+
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let g = Group::default();
+        let r = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 1.));
+
+        assert!(g.inner_intersect_ref(r).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "a group never owns a hit")]
+    fn group_has_no_normal_of_its_own() {
+        let g = Group::new(None, vec![Sphere::default_boxed()]);
+        g.normal_at(Tuple::point(0., 1., 0.));
+    }
+
+    #[test]
+    fn intersecting_ray_with_group_of_translated_spheres() {
+        let s1 = Sphere::default_boxed();
+        let s2 = Sphere::new_boxed(None, Some(Matrix::translation(0., 0., -3.)));
+        let s3 = Sphere::new_boxed(None, Some(Matrix::translation(5., 0., 0.)));
+        let g = Group::new(None, vec![s1.clone(), s2.clone(), s3]);
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = g.inner_intersect(r);
+
+        assert_eq!(xs.len(), 4);
+        assert_eq!(&xs[0].object, &s2);
+        assert_eq!(&xs[1].object, &s2);
+        assert_eq!(&xs[2].object, &s1);
+        assert_eq!(&xs[3].object, &s1);
+    }
+
+    #[test]
+    fn intersecting_transformed_group() {
+        let s = Sphere::new_boxed(None, Some(Matrix::translation(5., 0., 0.)));
+        let g = Group::new(Some(Matrix::scaling(2., 2., 2.)), vec![s]);
+        let r = Ray::new(Tuple::point(10., 0., -10.), Tuple::vector(0., 0., 1.));
+
+        assert_eq!(g.intersect(r).len(), 2);
+    }
+
+    #[test]
+    fn converting_point_from_world_to_object_space_through_nested_groups() {
+        let g1 = nested_groups(Matrix::scaling(2., 2., 2.));
+        let s = only_child(only_child(&g1));
+
+        assert_eq!(s.to_object_space(Tuple::point(-2., 0., -10.)), Tuple::point(0., 0., -1.));
+    }
+
+    #[test]
+    fn converting_normal_from_object_to_world_space_through_nested_groups() {
+        let g1 = nested_groups(Matrix::scaling(1., 2., 3.));
+        let s = only_child(only_child(&g1));
+        let v = Scalar::sqrt(3.) / 3.;
+
+        assert_eq!(s.normal_to_world_space(Tuple::vector(v, v, v)), Tuple::vector(2. / 7., 3. / 7., -6. / 7.));
+    }
+
+    #[test]
+    fn finding_normal_on_child_of_scaled_group() {
+        let g1 = nested_groups(Matrix::scaling(1., 2., 3.));
+        let s = only_child(only_child(&g1));
+        let v = Scalar::sqrt(3.) / 3.;
+        let world_point = s.to_world_space(Tuple::point(v, v, v));
+
+        assert_eq!(world_point, Tuple::point(Scalar::sqrt(3.), 2. * v, -5. - v));
+        assert_eq!(s.normal_at(world_point), Tuple::vector(2. / 7., 3. / 7., -6. / 7.));
+    }
+
+    #[test]
+    fn child_added_after_nesting_sees_every_ancestor() {
+        let mut g1 = nested_groups(Matrix::scaling(2., 2., 2.));
+        g1.add_child(Sphere::new_boxed(None, None));
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = g1.intersect_ref(r);
+        let hit = xs.iter().find(|i| i.t > 0.).unwrap();
+
+        assert_eq!(hit.t, 4.);
+        assert_eq!(hit.object.normal_at(r.position(hit.t)), Tuple::vector(0., 0., -1.));
+    }
+
+    #[test]
+    fn setting_group_transform_moves_its_children() {
+        let mut g1 = nested_groups(Matrix::scaling(2., 2., 2.));
+        g1.set_transform(Matrix::translation(0., 10., 0.));
+        let s = only_child(only_child(&g1));
+
+        assert_eq!(s.to_world_space(Tuple::point(0., 0., -1.)), Tuple::point(10., 10., -2.));
+    }
+
+    fn primitive_count(shape: &dyn Shape) -> usize {
+        match shape.as_any().downcast_ref::<Group>() {
+            Some(g) => g.children().iter().map(|c| primitive_count(&**c)).sum(),
+            None => 1
+        }
+    }
+
+    #[test]
+    fn group_bounds_contain_transformed_children() {
+        use crate::cylinder::Cylinder;
+
+        let s = Sphere::new_boxed(None, Some(Matrix::translation(2., 5., -3.) * Matrix::scaling(2., 2., 2.)));
+        let c = Cylinder::new(None, Some(Matrix::translation(-4., -1., 4.) * Matrix::scaling(0.5, 1., 0.5)))
+            .truncated(-2., 2., false);
+        let g = Group::new(None, vec![s, Box::new(c)]);
+
+        assert_eq!(g.bounds().min, Tuple::point(-4.5, -3., -5.));
+        assert_eq!(g.bounds().max, Tuple::point(4., 7., 4.5));
+    }
+
+    #[test]
+    fn ray_missing_group_bounds_misses_group() {
+        let s = Sphere::new_boxed(None, Some(Matrix::translation(5., 0., 0.)));
+        let g = Group::new(None, vec![s]);
+
+        assert!(!g.bounds().intersects(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.))));
+        assert!(g.inner_intersect_ref(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.))).is_empty());
+        assert_eq!(g.inner_intersect_ref(Ray::new(Tuple::point(5., 0., -5.), Tuple::vector(0., 0., 1.))).len(), 2);
+    }
+
+    #[test]
+    fn partitioning_children_by_bounds() {
+        let s1 = Sphere::new_boxed(None, Some(Matrix::translation(-2., 0., 0.)));
+        let s2 = Sphere::new_boxed(None, Some(Matrix::translation(2., 0., 0.)));
+        let s3 = Sphere::default_boxed();
+        let mut g = Group::new(None, vec![s1.clone(), s2.clone(), s3.clone()]);
+        let (left, right) = g.partition_children();
+
+        assert_eq!(g.children(), &[s3][..]);
+        assert_eq!(left, vec![s1]);
+        assert_eq!(right, vec![s2]);
+    }
+
+    #[test]
+    fn dividing_group_builds_hierarchy_and_keeps_primitives() {
+        let s1 = Sphere::new_boxed(None, Some(Matrix::translation(-2., -2., 0.)));
+        let s2 = Sphere::new_boxed(None, Some(Matrix::translation(-2., 2., 0.)));
+        let s3 = Sphere::new_boxed(None, Some(Matrix::scaling(4., 4., 4.)));
+        let mut g = Group::new(None, vec![s1.clone(), s2.clone(), s3.clone()]);
+        let r = Ray::new(Tuple::point(-2., 2., -10.), Tuple::vector(0., 0., 1.));
+        let before: Vec<Scalar> = g.inner_intersect_ref(r).iter().map(|i| i.t).collect();
+        g.divide(1);
+
+        assert_eq!(primitive_count(&g), 3);
+        assert_eq!(&g.children()[1], &s3);
+        let subgroup = g.children()[0].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(subgroup.children().len(), 2);
+        assert!(only_child(&*subgroup.children()[0]).box_eq(s1.as_any()));
+        assert!(only_child(&*subgroup.children()[1]).box_eq(s2.as_any()));
+        assert_eq!(g.inner_intersect_ref(r).iter().map(|i| i.t).collect::<Vec<Scalar>>(), before);
+    }
+
+    #[test]
+    fn dividing_group_with_too_few_children_leaves_it_alone() {
+        let s1 = Sphere::new_boxed(None, Some(Matrix::translation(-2., 0., 0.)));
+        let s2 = Sphere::new_boxed(None, Some(Matrix::translation(2., 0., 0.)));
+        let mut g = Group::new(None, vec![s1.clone(), s2.clone()]);
+        g.divide(3);
+
+        assert_eq!(g.children(), &[s1, s2][..]);
+    }
+
+    #[test]
+    fn dividing_sorts_children_by_centroid_along_widest_axis() {
+        let xs = [3., -4., 0.5, -1.];
+        let children = xs.iter().map(|&x| Sphere::new_boxed(None, Some(Matrix::translation(x, 0., 0.)))).collect();
+        let mut g = Group::new(None, children);
+        let r = Ray::new(Tuple::point(-10., 0., 0.), Tuple::vector(1., 0., 0.));
+        let before: Vec<Scalar> = g.inner_intersect_ref(r).iter().map(|i| i.t).collect();
+        g.divide(5);
+
+        let centres: Vec<Scalar> = g.children().iter().map(|c| c.transformation() * Tuple::point(0., 0., 0.)).map(|p| p.x).collect();
+        assert_eq!(centres, vec![-4., -1., 0.5, 3.]);
+        assert_eq!(g.inner_intersect_ref(r).iter().map(|i| i.t).collect::<Vec<Scalar>>(), before);
+    }
+
+    fn instance_positions(g: &Group) -> Vec<Tuple> {
+        g.children().iter().map(|c| c.transformation() * Tuple::point(0., 0., 0.)).collect()
+    }
+
+    #[test]
+    fn grid_places_instances_spacing_apart() {
+        let prototype: Arc<dyn Shape> = Arc::new(Sphere::new(None, Some(Matrix::scaling(0.5, 0.5, 0.5))));
+        let g = Group::grid(&prototype, 2, 3, 1.5);
+
+        assert_eq!(instance_positions(&g), vec![
+            Tuple::point(0., 0., 0.), Tuple::point(1.5, 0., 0.),
+            Tuple::point(0., 0., 1.5), Tuple::point(1.5, 0., 1.5),
+            Tuple::point(0., 0., 3.), Tuple::point(1.5, 0., 3.)]);
+        assert_eq!(Arc::strong_count(&prototype), 7);
+        let r = Ray::new(Tuple::point(1.5, 5., 3.), Tuple::vector(0., -1., 0.));
+        assert_eq!(g.intersect(r).hit().unwrap().t, 4.5);
+    }
+
+    #[test]
+    fn scatter_is_reproducible_and_stays_in_bounds() {
+        let prototype: Arc<dyn Shape> = Arc::new(Sphere::default());
+        let bounds = BoundingBox::new(Tuple::point(-5., 0., -2.), Tuple::point(5., 1., 2.));
+        let g = Group::scatter(&prototype, 20, bounds, 7);
+
+        assert_eq!(g.children().len(), 20);
+        assert_eq!(instance_positions(&g), instance_positions(&Group::scatter(&prototype, 20, bounds, 7)));
+        assert_ne!(instance_positions(&g), instance_positions(&Group::scatter(&prototype, 20, bounds, 8)));
+        assert!(instance_positions(&g).iter().all(|&p| bounds.contains_point(p)));
+    }
+
+    #[test]
+    fn dividing_stacked_children_terminates() {
+        let children = (0..4).map(|_| Sphere::default_boxed()).collect();
+        let mut g = Group::new(None, children);
+        g.divide(1);
+
+        assert_eq!(primitive_count(&g), 4);
+    }
+}
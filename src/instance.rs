@@ -0,0 +1,162 @@
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use std::any::Any;
+use std::sync::Arc;
+
+// One placement of a shape shared with other instances, e.g. a tile of a floor. The shape is
+// never told where the instance is; hits carry the placement instead (see IntersectionRef::placed),
+// so a prototype can be stamped many times without copying its geometry. Divide a prototype
+// group before sharing it, as shared shapes can't be changed anymore.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    id: ShapeId,
+    prototype: Arc<dyn Shape>,
+    transforms: Transforms,
+    material: Material,
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        self.prototype.box_eq(other.prototype.as_any()) && self.transformation() == other.transformation()
+    }
+}
+
+impl Shape for Instance {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let (id, transform) = (self.id(), self.transforms.chain());
+        self.prototype.intersect_ref(object_ray).into_iter().map(|i| i.placed(id, transform)).collect()
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        unreachable!("an instance never owns a hit; take the normal from the shape that was hit")
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    // The prototype keeps its ids; hits through this instance are told apart by its id
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.prototype.parent_space_bounds()
+    }
+}
+
+impl Instance {
+    pub fn new(prototype: Arc<dyn Shape>, transform: Option<Matrix>) -> Self {
+        Self { id: ShapeId::default(), prototype, transforms: Transforms::new(transform), material: Material::default() }
+    }
+
+    pub fn new_boxed(prototype: Arc<dyn Shape>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Instance::new(prototype, transform))
+    }
+
+    pub fn prototype(&self) -> &Arc<dyn Shape> {
+        &self.prototype
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::Group;
+    use crate::intersection::hit_ref;
+    use crate::sphere::Sphere;
+    use std::collections::HashSet;
+
+    #[test]
+    fn hit_through_instance_is_moved_to_the_instance() {
+        let prototype: Arc<dyn Shape> = Arc::new(Sphere::new(None, Some(Matrix::scaling(2., 2., 2.))));
+        let g = Group::new(Some(Matrix::translation(0., 0., 10.)), vec![Instance::new_boxed(prototype, Some(Matrix::translation(5., 0., 0.)))]);
+        let r = Ray::new(Tuple::point(5., 0., 0.), Tuple::vector(0., 0., 1.));
+        let hit = hit_ref(&g.intersect_ref(r)).unwrap().into_owned();
+
+        assert_eq!(hit.t, 8.);
+        assert_eq!(hit.object.normal_at(r.position(hit.t)), Tuple::vector(0., 0., -1.));
+        assert_eq!(hit.object.to_object_space(Tuple::point(5., 0., 10.)), Tuple::point(0., 0., 0.));
+    }
+
+    #[test]
+    fn instances_share_the_prototype() {
+        let prototype: Arc<dyn Shape> = Arc::new(Sphere::default());
+        let a = Instance::new(Arc::clone(&prototype), None);
+        let b = Instance::new(Arc::clone(&prototype), Some(Matrix::translation(0., 3., 0.)));
+
+        assert!(Arc::ptr_eq(a.prototype(), b.prototype()));
+        assert_eq!(Arc::strong_count(&prototype), 3);
+    }
+
+    #[test]
+    fn overlapping_instances_of_one_glass_sphere_are_tracked_independently() {
+        let glass = Material { transparency: 1., refractive_index: 1.5, ..Default::default() };
+        let prototype: Arc<dyn Shape> = Arc::new(Sphere::new(Some(glass), None));
+        let a = Instance::new_boxed(Arc::clone(&prototype), None);
+        let b = Instance::new_boxed(prototype, Some(Matrix::translation(0., 0., 1.)));
+        let g = Group::new(None, vec![a, b]);
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = g.intersect_ref(r);
+
+        assert_eq!(xs.iter().map(|i| i.t).collect::<Vec<_>>(), vec![4., 5., 6., 7.]);
+        assert_ne!(xs[0].key(), xs[1].key());
+        let entering_b = xs[1].into_owned().prepare_computations_with(r, &xs);
+        assert_eq!((entering_b.n1, entering_b.n2), (1.5, 1.5));
+        let leaving_a = xs[2].into_owned().prepare_computations_with(r, &xs);
+        assert_eq!((leaving_a.n1, leaving_a.n2), (1.5, 1.5));
+    }
+
+    #[test]
+    fn nested_instances_combine_their_placements() {
+        let sphere: Arc<dyn Shape> = Arc::new(Sphere::default());
+        let row: Arc<dyn Shape> = Arc::new(Group::new(None, vec![
+            Instance::new_boxed(Arc::clone(&sphere), None),
+            Instance::new_boxed(sphere, Some(Matrix::translation(3., 0., 0.)))]));
+        let g = Group::new(None, vec![
+            Instance::new_boxed(Arc::clone(&row), None),
+            Instance::new_boxed(row, Some(Matrix::translation(0., 3., 0.)))]);
+        let r = Ray::new(Tuple::point(3., 3., -5.), Tuple::vector(0., 0., 1.));
+        let xs = g.intersect_ref(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].into_owned().object.to_object_space(Tuple::point(3., 3., 0.)), Tuple::point(0., 0., 0.));
+        let keys: HashSet<_> = [Tuple::point(0., 0., -5.), Tuple::point(3., 0., -5.), Tuple::point(0., 3., -5.), Tuple::point(3., 3., -5.)].iter()
+            .map(|&origin| g.intersect_ref(Ray::new(origin, Tuple::vector(0., 0., 1.)))[0].key())
+            .collect();
+        assert_eq!(keys.len(), 4);
+    }
+}
@@ -3,29 +3,69 @@ use super::EPSILON;
 use super::shape::*;
 use super::ray::Ray;
 use super::precomputed_data::PrecomputedData;
+use super::material::DEFAULT_REFRACTIVE_INDEX;
+use super::matrix::Matrix;
+use super::sampling::Rng;
+use super::Scalar;
+
+// Identifies what was hit: the shape's id and, for hits through an Instance, which instance.
+// Instances share their shapes, so the shape id alone can't tell them apart.
+pub type ObjectKey = (usize, Option<usize>);
+
+// Where a shape shared by instances was hit: the id of the instance (a mix of all of them for
+// instances nested in instances) and the transform from the shape's own root to world space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Placement {
+    pub id: usize,
+    pub transform: Matrix
+}
 
 #[derive(Debug, Clone)]
 pub struct Intersection {
-    pub t: f64,
-    pub object: BoxShape
+    pub t: Scalar,
+    pub object: BoxShape,
+    // Barycentric coordinates of the hit, set by triangles for normal interpolation
+    pub u: Option<Scalar>,
+    pub v: Option<Scalar>,
+    // Set for hits through an Instance; object is then already moved to where the instance put it
+    pub instance: Option<usize>
 }
 
 impl PartialEq for Intersection {
     fn eq(&self, other: &Intersection) -> bool {
         self.t == other.t &&
-            &self.object == &other.object
+            self.object.box_eq(other.object.as_any()) &&
+            self.instance == other.instance
     }
 }
 
 impl Intersection {
-    pub fn new(t: f64, object: BoxShape) -> Self {
-        Intersection { t, object }
+    pub fn new(t: Scalar, object: BoxShape) -> Self {
+        Intersection { t, object, u: None, v: None, instance: None }
+    }
+
+    pub fn new_with_uv(t: Scalar, object: BoxShape, u: Scalar, v: Scalar) -> Self {
+        Intersection { t, object, u: Some(u), v: Some(v), instance: None }
+    }
+
+    pub fn key(&self) -> ObjectKey {
+        (self.object.id(), self.instance)
     }
 
+    // Treats this intersection as the only one along the ray, i.e. the ray enters the object from vacuum
     pub fn prepare_computations(&self, ray: Ray) -> PrecomputedData {
+        self.prepare_computations_with(ray, &[IntersectionRef::new(self.t, &*self.object)])
+    }
+
+    // xs must be every intersection along the ray, sorted by t, so the refractive indices on
+    // either side of this hit can be found
+    pub fn prepare_computations_with(&self, ray: Ray, xs: &[IntersectionRef]) -> PrecomputedData {
         let point = ray.position(self.t);
         let eyev = -ray.direction;
-        let mut normalv = self.object.normal_at(point);
+        let mut normalv = self.object.normal_at_hit(point, self);
+        if let Some(perturb) = self.object.material().normal_perturb {
+            normalv = perturb(point, normalv).normalize();
+        }
         let inside = if normalv.dot(&eyev) < 0. {
             normalv = -normalv;
             true
@@ -33,19 +73,123 @@ impl Intersection {
             false
         };
         let over_point = point + normalv * EPSILON;
-
-        PrecomputedData::new(
-            self.t,
-            self.object.clone(),
+        let under_point = point - normalv * EPSILON;
+        let reflectv = ray.direction.reflect(normalv);
+        let (n1, n2) = self.refractive_indices(xs);
+
+        PrecomputedData {
+            t: self.t,
+            object: self.object.clone(),
+            instance: self.instance,
             point,
             eyev,
             normalv,
             inside,
-            over_point
-        )
+            over_point,
+            under_point,
+            reflectv,
+            n1,
+            n2
+        }
+    }
+
+    // Walks the intersections keeping a stack of the objects the ray is currently inside:
+    // meeting an object already on the stack means leaving it, otherwise the ray enters it.
+    // Objects are matched by key, so identical but separate shapes are tracked independently.
+    fn refractive_indices(&self, xs: &[IntersectionRef]) -> (Scalar, Scalar) {
+        let outermost = |containers: &Vec<(ObjectKey, &dyn Shape)>| containers.last()
+            .map_or(DEFAULT_REFRACTIVE_INDEX, |(_, o)| o.material().refractive_index);
+        let mut containers: Vec<(ObjectKey, &dyn Shape)> = vec![];
+        let mut n1 = DEFAULT_REFRACTIVE_INDEX;
+        for i in xs {
+            let is_hit = i.t == self.t && i.key() == self.key();
+            if is_hit {
+                n1 = outermost(&containers);
+            }
+            match containers.iter().position(|(key, _)| *key == i.key()) {
+                Some(index) => { containers.remove(index); }
+                None => containers.push((i.key(), i.object))
+            }
+            if is_hit {
+                return (n1, outermost(&containers));
+            }
+        }
+        (n1, n1)
     }
 }
 
+// Borrowed counterpart of Intersection, valid while the intersected shape is alive.
+// Shapes produce these without cloning themselves; only the hit that actually gets
+// shaded is converted into an owned Intersection.
+#[derive(Debug, Copy, Clone)]
+pub struct IntersectionRef<'a> {
+    pub t: Scalar,
+    pub object: &'a dyn Shape,
+    pub u: Option<Scalar>,
+    pub v: Option<Scalar>,
+    // Set for hits through an Instance, whose shapes don't know where the instance put them
+    pub placement: Option<Placement>
+}
+
+impl<'a> IntersectionRef<'a> {
+    pub fn new(t: Scalar, object: &'a dyn Shape) -> Self {
+        IntersectionRef { t, object, u: None, v: None, placement: None }
+    }
+
+    pub fn new_with_uv(t: Scalar, object: &'a dyn Shape, u: Scalar, v: Scalar) -> Self {
+        IntersectionRef { t, object, u: Some(u), v: Some(v), placement: None }
+    }
+
+    pub fn key(&self) -> ObjectKey {
+        (self.object.id(), self.placement.map(|p| p.id))
+    }
+
+    // The same hit seen from outside the instance with the given id and transform (its own
+    // combined with that of its enclosing groups)
+    pub fn placed(self, instance: usize, transform: Matrix) -> Self {
+        let placement = match self.placement {
+            None => Placement { id: instance, transform },
+            Some(inner) => Placement {
+                id: Rng::new(((instance as u64) << 32) ^ inner.id as u64).next_u64() as usize,
+                transform: transform * inner.transform
+            }
+        };
+        IntersectionRef { placement: Some(placement), ..self }
+    }
+
+    // Hits through an instance get a copy of the shape moved to where the instance put it, so
+    // normals and patterns come out right without asking the instance again
+    pub fn into_owned(self) -> Intersection {
+        let mut object = self.object.box_clone();
+        if let Some(placement) = self.placement {
+            let parent = placement.transform * object.parent_transformation();
+            object.set_parent_transformation(parent);
+        }
+        Intersection { t: self.t, object, u: self.u, v: self.v, instance: self.placement.map(|p| p.id) }
+    }
+}
+
+pub fn hit_ref<'a>(range: &[IntersectionRef<'a>]) -> Option<IntersectionRef<'a>> {
+    range.iter()
+        .filter(|i| i.t >= 0.)
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+        .copied()
+}
+
+// Like hit_ref, but skipping objects whose material doesn't cast shadows
+pub fn shadow_hit_ref<'a>(range: &[IntersectionRef<'a>]) -> Option<IntersectionRef<'a>> {
+    range.iter()
+        .filter(|i| i.t >= 0. && i.object.material().casts_shadow)
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+        .copied()
+}
+
+// Degenerate geometry can produce NaN roots; those are dropped rather than sorted
+pub fn sort_refs(range: &mut Vec<IntersectionRef>) {
+    range.retain(|i| !i.t.is_nan());
+    range.sort_by(|a, b| a.t.total_cmp(&b.t));
+}
+
 #[derive(Debug)]
 pub struct Intersections {
     inner: Vec<Intersection>,
@@ -59,11 +203,21 @@ impl ops::Index<usize> for Intersections {
     }
 }
 
+impl<'a> IntoIterator for &'a Intersections {
+    type Item = &'a Intersection;
+    type IntoIter = std::slice::Iter<'a, Intersection>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
 impl Intersections {
 
-    pub fn new(range: Vec<Intersection>) -> Intersections {
+    // Intersections with a NaN t are dropped, as they cannot be ordered or hit
+    pub fn new(mut range: Vec<Intersection>) -> Intersections {
+        range.retain(|i| !i.t.is_nan());
         let mut xs = Intersections { inner: range, current_hit: None };
-        xs.inner.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        xs.inner.sort_by(|a, b| a.t.total_cmp(&b.t));
         for i in xs.inner.iter() {
             if i.t >= 0. {
                 xs.current_hit = Some(i.clone());
@@ -73,28 +227,64 @@ impl Intersections {
         xs
     }
 
+    pub fn from_refs(range: Vec<IntersectionRef>) -> Intersections {
+        Intersections::new(range.into_iter().map(IntersectionRef::into_owned).collect())
+    }
+
     pub fn extend(&mut self, range: Intersections) {
         self.inner.extend(range.inner);
-        match range.current_hit {
-            Some(range_hit) =>
-                match &self.current_hit {
-                    None => self.current_hit = Some(range_hit.clone()),
-                    Some(i) => if i.t > range_hit.t { self.current_hit = Some(range_hit.clone());}
-                }
-            _ => ()
+        if let Some(range_hit) = range.current_hit {
+            match &self.current_hit {
+                None => self.current_hit = Some(range_hit),
+                Some(i) => if i.t > range_hit.t { self.current_hit = Some(range_hit); }
+            }
         }
-        self.inner.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        self.inner.sort_by(|a, b| a.t.total_cmp(&b.t));
+    }
+
+    // Collapses entries hitting the same object, by key, at the same t. Since the list is sorted,
+    // only the trailing run of kept entries within EPSILON of t needs checking.
+    pub fn dedup(&mut self) {
+        let mut kept: Vec<Intersection> = Vec::with_capacity(self.inner.len());
+        for i in self.inner.drain(..) {
+            let duplicate = kept.iter().rev()
+                .take_while(|k| super::approx_eq(k.t, i.t))
+                .any(|k| k.key() == i.key());
+            if !duplicate {
+                kept.push(i);
+            }
+        }
+        self.inner = kept;
     }
 
     pub fn len(&self) -> usize {
         self.inner.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     pub fn hit(&self) -> Option<&Intersection> {
-        match &self.current_hit {
-            None => None,
-            Some(i) => Some(i).clone()
-        }
+        self.current_hit.as_ref()
+    }
+
+    // Lowest non-negative intersection with an object that casts shadows
+    pub fn shadow_hit(&self) -> Option<&Intersection> {
+        self.inner.iter().find(|i| i.t >= 0. && i.object.material().casts_shadow)
+    }
+
+    // Walks the intersections in ascending t order
+    pub fn iter(&self) -> std::slice::Iter<'_, Intersection> {
+        self.inner.iter()
+    }
+
+    pub fn first(&self) -> Option<&Intersection> {
+        self.inner.first()
+    }
+
+    pub fn last(&self) -> Option<&Intersection> {
+        self.inner.last()
     }
 }
 
@@ -104,6 +294,8 @@ mod tests {
     use crate::matrix::Matrix;
     use crate::tuple::Tuple;
     use crate::sphere::Sphere;
+    use crate::material::Material;
+    use crate::Scalar;
 
     #[test]
     fn intersection_encapsulates_t_and_object() {
@@ -142,6 +334,32 @@ mod tests {
         assert_eq!(4., xs[3].t);
     }
 
+    #[test]
+    fn iterating_intersections_in_t_order() {
+        let s = Sphere::default_boxed();
+        let xs = Intersections::new(vec![
+            Intersection::new(5., s.clone()),
+            Intersection::new(-3., s.clone()),
+            Intersection::new(2., s.clone()),
+            Intersection::new(7., s)]);
+        let ts: Vec<Scalar> = xs.iter().map(|i| i.t).collect();
+
+        assert_eq!(ts, vec![-3., 2., 5., 7.]);
+        assert_eq!(xs.iter().count(), xs.len());
+        assert_eq!((&xs).into_iter().map(|i| i.t).collect::<Vec<Scalar>>(), ts);
+        assert_eq!(xs.first().map(|i| i.t), Some(-3.));
+        assert_eq!(xs.last().map(|i| i.t), Some(7.));
+    }
+
+    #[test]
+    fn first_and_last_of_empty_intersections() {
+        let xs = Intersections::new(vec![]);
+
+        assert!(xs.first().is_none());
+        assert!(xs.last().is_none());
+        assert_eq!(xs.iter().count(), 0);
+    }
+
     #[test]
     fn intersect_sets_object_on_intersection() {
         let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
@@ -199,6 +417,34 @@ mod tests {
         assert_eq!(*i, i4);
     }
 
+    #[test]
+    fn shadow_hit_skips_objects_that_cast_no_shadow() {
+        let glass = Sphere::new_boxed(Some(Material { casts_shadow: false, ..Default::default() }), None);
+        let opaque = Sphere::default_boxed();
+        let xs = Intersections::new(vec![
+            Intersection::new(1., glass.clone()),
+            Intersection::new(-1., opaque.clone()),
+            Intersection::new(3., opaque.clone())]);
+
+        assert_eq!(xs.hit().unwrap().t, 1.);
+        assert_eq!(xs.shadow_hit().unwrap().t, 3.);
+        let refs = vec![IntersectionRef::new(1., glass.as_ref()), IntersectionRef::new(3., opaque.as_ref())];
+        assert_eq!(shadow_hit_ref(&refs).unwrap().t, 3.);
+    }
+
+    #[test]
+    fn shadow_hit_ignores_negative_shadow_casters() {
+        let glass = Sphere::new_boxed(Some(Material { casts_shadow: false, ..Default::default() }), None);
+        let opaque = Sphere::default_boxed();
+        let xs = Intersections::new(vec![
+            Intersection::new(-2., opaque.clone()),
+            Intersection::new(2., glass),
+            Intersection::new(-1., opaque)]);
+
+        assert!(xs.hit().is_some());
+        assert!(xs.shadow_hit().is_none());
+    }
+
     #[test]
     fn extend_intersections_gets_union() {
         let s1 = Sphere::default_boxed();
@@ -220,6 +466,58 @@ mod tests {
         assert_eq!(*xs1.hit().unwrap(), i6);
     }
 
+    #[test]
+    fn borrowed_intersections_match_owned_intersections() {
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let s = Sphere::new_boxed(None, Some(Matrix::translation(0., 0., 1.)));
+        let owned = s.intersect(r);
+        let mut borrowed = s.intersect_ref(r);
+        sort_refs(&mut borrowed);
+
+        assert_eq!(owned.len(), borrowed.len());
+        for (i, b) in borrowed.iter().enumerate() {
+            assert_eq!(b.t, owned[i].t);
+            assert_eq!(b.into_owned(), owned[i]);
+        }
+    }
+
+    #[test]
+    fn hit_ref_is_lowest_non_negative_intersection() {
+        let s = Sphere::default();
+        let xs = vec![
+            IntersectionRef::new(5., &s),
+            IntersectionRef::new(-3., &s),
+            IntersectionRef::new(2., &s),
+            IntersectionRef::new(7., &s)];
+
+        assert_eq!(hit_ref(&xs).unwrap().t, 2.);
+        assert!(hit_ref(&xs[1..2]).is_none());
+    }
+
+    #[test]
+    fn dedup_collapses_identical_hits() {
+        let s1 = Sphere::default_boxed();
+        let s2 = Sphere::new_boxed(None, Some(Matrix::translation(1., 0., 0.)));
+        // Equal to s1 as a value, but a separate object
+        let s3 = Sphere::default_boxed();
+        let mut xs = Intersections::new(vec![
+            Intersection::new(2., s1.clone()),
+            Intersection::new(-1., s1.clone()),
+            Intersection::new(2. + EPSILON / 10., s1.clone()),
+            Intersection::new(2., s2.clone()),
+            Intersection::new(3., s1.clone()),
+            Intersection::new(3., s3.clone()),
+            Intersection::new(3., s1.clone())]);
+        xs.dedup();
+
+        assert_eq!(xs.len(), 5);
+        assert_eq!(xs[0].t, -1.);
+        assert_eq!(xs[1].t, 2.);
+        assert_eq!((xs[3].t, xs[3].object.id()), (3., s1.id()));
+        assert_eq!((xs[4].t, xs[4].object.id()), (3., s3.id()));
+        assert_eq!(xs.hit().unwrap().t, 2.);
+    }
+
     #[test]
     fn precompute_state_of_intersection() {
         let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
@@ -265,4 +563,140 @@ mod tests {
         assert!(comps.over_point.z < - EPSILON / 2.);
         assert!(comps.point.z > comps.over_point.z);
     }
-}
\ No newline at end of file
+
+    fn glass_sphere(transform: Matrix, refractive_index: Scalar) -> BoxShape {
+        let m = Material { transparency: 1., refractive_index, ..Default::default() };
+        Sphere::new_boxed(Some(m), Some(transform))
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        let a = glass_sphere(Matrix::scaling(2., 2., 2.), 1.5);
+        let b = glass_sphere(Matrix::translation(0., 0., -0.25), 2.);
+        let c = glass_sphere(Matrix::translation(0., 0., 0.25), 2.5);
+        let r = Ray::new(Tuple::point(0., 0., -4.), Tuple::vector(0., 0., 1.));
+        let xs = vec![
+            IntersectionRef::new(2., &*a),
+            IntersectionRef::new(2.75, &*b),
+            IntersectionRef::new(3.25, &*c),
+            IntersectionRef::new(4.75, &*b),
+            IntersectionRef::new(5.25, &*c),
+            IntersectionRef::new(6., &*a)];
+        let expected = [(1., 1.5), (1.5, 2.), (2., 2.5), (2.5, 2.5), (2.5, 1.5), (1.5, 1.)];
+
+        for (i, (n1, n2)) in xs.iter().zip(expected.iter()) {
+            let comps = i.into_owned().prepare_computations_with(r, &xs);
+            assert_eq!(comps.n1, *n1);
+            assert_eq!(comps.n2, *n2);
+        }
+    }
+
+    fn ripples(point: Tuple, normal: Tuple) -> Tuple {
+        normal + Tuple::vector(point.x.sin() * 0.5, 0., 0.)
+    }
+
+    #[test]
+    fn normal_perturbation_tilts_the_shading_normal() {
+        use crate::color::WHITE;
+        use crate::light::PointLight;
+        use crate::plane::Plane;
+
+        let bumpy = Plane::new_boxed(Some(Material { normal_perturb: Some(ripples), ..Default::default() }), None);
+        let flat = Plane::default_boxed();
+        let r = Ray::new(Tuple::point(1., 1., 0.), Tuple::vector(0., -1., 0.));
+        let bumpy_comps = Intersection::new(1., bumpy.clone()).prepare_computations(r);
+        let flat_comps = Intersection::new(1., flat.clone()).prepare_computations(r);
+        let tilted = Tuple::vector(Scalar::sin(1.) * 0.5, 1., 0.).normalize();
+
+        assert_eq!(bumpy_comps.normalv, tilted);
+        assert_eq!(bumpy_comps.over_point, bumpy_comps.point + tilted * EPSILON);
+        assert_eq!(flat_comps.normalv, Tuple::vector(0., 1., 0.));
+
+        let light = PointLight::new(Tuple::point(1., 10., 0.), WHITE);
+        let shade = |comps: &PrecomputedData, object: &BoxShape| object.material()
+            .lighting(&**object, &light, comps.point, comps.eyev, comps.normalv, false);
+        let (lit_bumpy, lit_flat) = (shade(&bumpy_comps, &bumpy), shade(&flat_comps, &flat));
+        assert!(lit_bumpy.r < lit_flat.r);
+        assert_eq!(lit_bumpy, shade(&Intersection::new(1., bumpy.clone()).prepare_computations(r), &bumpy));
+    }
+
+    #[test]
+    fn identical_glass_spheres_are_tracked_independently() {
+        let a = glass_sphere(Matrix::default(), 1.5);
+        let b = glass_sphere(Matrix::default(), 1.5);
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let mut xs: Vec<IntersectionRef> = a.intersect_ref(r).into_iter().chain(b.intersect_ref(r)).collect();
+        sort_refs(&mut xs);
+        let ids: Vec<usize> = xs.iter().map(|i| i.object.id()).collect();
+        assert_eq!(ids, vec![a.id(), b.id(), a.id(), b.id()]);
+
+        // Entering b while already inside a, then leaving a while still inside b
+        let entering_b = xs[1].into_owned().prepare_computations_with(r, &xs);
+        let leaving_a = xs[2].into_owned().prepare_computations_with(r, &xs);
+        let leaving_b = xs[3].into_owned().prepare_computations_with(r, &xs);
+        assert_eq!((entering_b.n1, entering_b.n2), (1.5, 1.5));
+        assert_eq!((leaving_a.n1, leaving_a.n2), (1.5, 1.5));
+        assert_eq!((leaving_b.n1, leaving_b.n2), (1.5, 1.));
+    }
+
+    #[test]
+    fn duplicates_of_one_template_are_tracked_independently() {
+        let template = glass_sphere(Matrix::default(), 1.5);
+        let (a, b) = (template.duplicate(), template.duplicate());
+        assert_ne!(a.id(), b.id());
+
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let mut xs: Vec<IntersectionRef> = a.intersect_ref(r).into_iter().chain(b.intersect_ref(r)).collect();
+        sort_refs(&mut xs);
+        let entering_b = xs[1].into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!(entering_b.object.id(), b.id());
+        assert_eq!((entering_b.n1, entering_b.n2), (1.5, 1.5));
+    }
+
+    #[test]
+    fn cloned_intersection_still_finds_its_own_hit() {
+        let i = Intersection::new(5., glass_sphere(Matrix::translation(0., 0., 1.), 1.5));
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let comps = i.clone().prepare_computations(r);
+
+        assert_eq!((comps.n1, comps.n2), (1., 1.5));
+    }
+
+    #[test]
+    fn under_point_is_offset_below_surface() {
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let shape = glass_sphere(Matrix::translation(0., 0., 1.), 1.5);
+        let i = Intersection::new(5., shape);
+        let comps = i.prepare_computations(r);
+
+        assert!(comps.under_point.z > EPSILON / 2.);
+        assert!(comps.point.z < comps.under_point.z);
+        assert_eq!((comps.n1, comps.n2), (1., 1.5));
+    }
+
+    #[test]
+    fn nan_intersections_are_dropped() {
+        let s = Sphere::default_boxed();
+        let xs = Intersections::new(vec![
+            Intersection::new(2., s.clone()),
+            Intersection::new(Scalar::NAN, s.clone()),
+            Intersection::new(-1., s.clone()),
+            Intersection::new(1., s)]);
+
+        assert_eq!(xs.len(), 3);
+        assert_eq!(xs[0].t, -1.);
+        assert_eq!(xs[2].t, 2.);
+        assert_eq!(xs.hit().unwrap().t, 1.);
+    }
+
+    #[test]
+    fn sorting_refs_drops_nan() {
+        let s = Sphere::default();
+        let mut xs = vec![IntersectionRef::new(Scalar::NAN, &s), IntersectionRef::new(3., &s), IntersectionRef::new(0.5, &s)];
+        sort_refs(&mut xs);
+
+        assert_eq!(xs.iter().map(|i| i.t).collect::<Vec<_>>(), vec![0.5, 3.]);
+        assert_eq!(hit_ref(&xs).unwrap().t, 0.5);
+    }
+}
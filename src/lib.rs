@@ -4,19 +4,88 @@ pub mod canvas;
 pub mod matrix;
 pub mod transform;
 pub mod ray;
+pub mod bounds;
 pub mod shape;
 pub mod sphere;
 pub mod plane;
+pub mod disk;
+pub mod cube;
+pub mod cylinder;
+pub mod cone;
+pub mod torus;
+pub mod solver;
+pub mod triangle;
+pub mod clipped;
+pub mod group;
+pub mod csg;
+pub mod instance;
+pub mod obj_parser;
 pub mod intersection;
 pub mod light;
 pub mod material;
 pub mod pattern;
+pub mod noise;
 pub mod world;
 pub mod precomputed_data;
 pub mod camera;
+pub mod sampling;
+pub mod scene_loader;
 
-pub const EPSILON: f64 = 0.00001;
+#[cfg(not(feature = "f32"))]
+pub type Scalar = f64;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Scalar = 0.00001;
 
-pub fn approx_eq(a: f64, b: f64) -> bool {
-    (a - b).abs() < EPSILON
-}
\ No newline at end of file
+#[cfg(feature = "f32")]
+pub type Scalar = f32;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+#[cfg(feature = "f32")]
+pub const EPSILON: Scalar = 0.0001;
+
+pub fn approx_eq(a: Scalar, b: Scalar) -> bool {
+    approx_eq_eps(a, b, EPSILON)
+}
+
+// For checks that need a coarser or tighter tolerance than EPSILON
+pub fn approx_eq_eps(a: Scalar, b: Scalar, eps: Scalar) -> bool {
+    a == b || (a - b).abs() < eps
+}
+
+#[cfg(all(test, feature = "f32"))]
+mod f32_tests {
+    use super::*;
+    use crate::matrix::Matrix;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::sphere::Sphere;
+    use crate::tuple::Tuple;
+
+    #[test]
+    fn scalar_is_single_precision() {
+        assert_eq!(std::mem::size_of::<Scalar>(), 4);
+        assert!(approx_eq(1., 1. + EPSILON / 2.));
+    }
+
+    #[test]
+    fn chained_transformations_within_f32_tolerance() {
+        let t = Matrix::translation(10., 5., 7.) * Matrix::scaling(5., 5., 5.) * Matrix::rotation_x(consts::FRAC_PI_2);
+        let p = t * Tuple::point(1., 0., 1.);
+
+        assert_eq!(p, Tuple::point(15., 0., 7.));
+        assert_eq!(t.inverse().unwrap() * p, Tuple::point(1., 0., 1.));
+    }
+
+    #[test]
+    fn sphere_intersection_within_f32_tolerance() {
+        let s = Sphere::new(None, Some(Matrix::scaling(2., 2., 2.)));
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = s.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert!(approx_eq(xs[0].t, 3.));
+        assert!(approx_eq(xs[1].t, 7.));
+    }
+}
@@ -1,5 +1,43 @@
 use super::color::Color;
+use super::sampling::Rng;
 use super::tuple::Tuple;
+use super::Scalar;
+use std::any::Any;
+use std::fmt;
+
+pub trait Light: Any + fmt::Debug + Send + Sync {
+    fn box_clone(&self) -> BoxLight;
+    fn box_eq(&self, other: &dyn Any) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    // Light arriving at the point, before shadowing
+    fn intensity_at(&self, point: Tuple) -> Color;
+    // Unit vector from the point towards the light
+    fn direction_to(&self, point: Tuple) -> Tuple;
+    // How far a shadow ray from the point travels before it reaches the light
+    fn distance_from(&self, point: Tuple) -> Scalar;
+    // Lights without a location (e.g. directional ones) cannot end up inside an object
+    fn position(&self) -> Option<Tuple> {
+        None
+    }
+    // Points spread over the light for soft shadows; empty for lights with no extent
+    fn surface_samples(&self, _rng: &mut Rng) -> Vec<Tuple> {
+        vec![]
+    }
+}
+
+pub type BoxLight = Box<dyn Light>;
+
+impl Clone for BoxLight {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl PartialEq for BoxLight {
+    fn eq(&self, other: &BoxLight) -> bool {
+        self.box_eq(other.as_any())
+    }
+}
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct PointLight {
@@ -11,12 +49,240 @@ impl PointLight {
     pub fn new(position: Tuple, intensity: Color) -> PointLight {
         PointLight { position, intensity }
     }
+
+    pub fn new_boxed(position: Tuple, intensity: Color) -> BoxLight {
+        Box::new(PointLight::new(position, intensity))
+    }
+}
+
+impl Light for PointLight {
+    fn box_clone(&self) -> BoxLight {
+        Box::new(*self)
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn intensity_at(&self, _point: Tuple) -> Color {
+        self.intensity
+    }
+
+    fn direction_to(&self, point: Tuple) -> Tuple {
+        (self.position - point).normalize()
+    }
+
+    fn distance_from(&self, point: Tuple) -> Scalar {
+        (self.position - point).magnitude()
+    }
+
+    fn position(&self) -> Option<Tuple> {
+        Some(self.position)
+    }
+}
+
+// Sun-like light: every point is lit from the same direction, by a source infinitely far away
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct DirectionalLight {
+    pub direction: Tuple,
+    pub intensity: Color
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Tuple, intensity: Color) -> DirectionalLight {
+        DirectionalLight { direction: direction.normalize(), intensity }
+    }
+
+    pub fn new_boxed(direction: Tuple, intensity: Color) -> BoxLight {
+        Box::new(DirectionalLight::new(direction, intensity))
+    }
+}
+
+impl Light for DirectionalLight {
+    fn box_clone(&self) -> BoxLight {
+        Box::new(*self)
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn intensity_at(&self, _point: Tuple) -> Color {
+        self.intensity
+    }
+
+    fn direction_to(&self, _point: Tuple) -> Tuple {
+        -self.direction
+    }
+
+    fn distance_from(&self, _point: Tuple) -> Scalar {
+        Scalar::INFINITY
+    }
+}
+
+// Point light restricted to a cone around `direction`: full intensity within inner_angle of the
+// axis, none past outer_angle, and a smoothstep falloff in between. Angles are half-angles in radians.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SpotLight {
+    pub position: Tuple,
+    pub direction: Tuple,
+    pub intensity: Color,
+    pub inner_angle: Scalar,
+    pub outer_angle: Scalar
+}
+
+impl SpotLight {
+    pub fn new(position: Tuple, direction: Tuple, intensity: Color, inner_angle: Scalar, outer_angle: Scalar) -> SpotLight {
+        SpotLight { position, direction: direction.normalize(), intensity, inner_angle, outer_angle }
+    }
+
+    pub fn new_boxed(position: Tuple, direction: Tuple, intensity: Color, inner_angle: Scalar, outer_angle: Scalar) -> BoxLight {
+        Box::new(SpotLight::new(position, direction, intensity, inner_angle, outer_angle))
+    }
+
+    // Fraction of the intensity reaching the point, in 0..=1
+    fn falloff(&self, point: Tuple) -> Scalar {
+        let cos_angle = (point - self.position).normalize().dot(&self.direction);
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_inner - cos_outer <= 0. {
+            return if cos_angle >= cos_outer { 1. } else { 0. };
+        }
+        let t = ((cos_angle - cos_outer) / (cos_inner - cos_outer)).clamp(0., 1.);
+        t * t * (3. - 2. * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn box_clone(&self) -> BoxLight {
+        Box::new(*self)
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn intensity_at(&self, point: Tuple) -> Color {
+        self.intensity * self.falloff(point)
+    }
+
+    fn direction_to(&self, point: Tuple) -> Tuple {
+        (self.position - point).normalize()
+    }
+
+    fn distance_from(&self, point: Tuple) -> Scalar {
+        (self.position - point).magnitude()
+    }
+
+    fn position(&self) -> Option<Tuple> {
+        Some(self.position)
+    }
+}
+
+// Rectangle spanning corner + full_uvec and corner + full_vvec, shadow tested against one sample
+// per cell of a usteps x vsteps grid. Shading still uses the direction to the center; only the
+// shadows get softer. Jittered samples land anywhere in their cell instead of its center,
+// trading banding in the penumbra for noise.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AreaLight {
+    pub corner: Tuple,
+    pub uvec: Tuple,
+    pub usteps: usize,
+    pub vvec: Tuple,
+    pub vsteps: usize,
+    pub intensity: Color,
+    pub jitter: bool
+}
+
+impl AreaLight {
+    // A light is sampled at least once along each edge, so zero steps count as one
+    pub fn new(corner: Tuple, full_uvec: Tuple, usteps: usize, full_vvec: Tuple, vsteps: usize, intensity: Color) -> AreaLight {
+        let (usteps, vsteps) = (usteps.max(1), vsteps.max(1));
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as Scalar,
+            usteps,
+            vvec: full_vvec / vsteps as Scalar,
+            vsteps,
+            intensity,
+            jitter: false
+        }
+    }
+
+    pub fn new_boxed(corner: Tuple, full_uvec: Tuple, usteps: usize, full_vvec: Tuple, vsteps: usize, intensity: Color) -> BoxLight {
+        Box::new(AreaLight::new(corner, full_uvec, usteps, full_vvec, vsteps, intensity))
+    }
+
+    pub fn jittered(self) -> Self {
+        Self { jitter: true, ..self }
+    }
+
+    pub fn center(&self) -> Tuple {
+        self.corner + self.uvec * (self.usteps as Scalar / 2.) + self.vvec * (self.vsteps as Scalar / 2.)
+    }
+
+    // Offsets within the cell are in [0, 1); unjittered samples sit at the middle
+    pub fn point_on_light(&self, u: usize, v: usize, rng: &mut Rng) -> Tuple {
+        let (du, dv) = if self.jitter { (rng.next_scalar(), rng.next_scalar()) } else { (0.5, 0.5) };
+        self.corner + self.uvec * (u as Scalar + du) + self.vvec * (v as Scalar + dv)
+    }
+}
+
+impl Light for AreaLight {
+    fn box_clone(&self) -> BoxLight {
+        Box::new(*self)
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn intensity_at(&self, _point: Tuple) -> Color {
+        self.intensity
+    }
+
+    fn direction_to(&self, point: Tuple) -> Tuple {
+        (self.center() - point).normalize()
+    }
+
+    fn distance_from(&self, point: Tuple) -> Scalar {
+        (self.center() - point).magnitude()
+    }
+
+    fn position(&self) -> Option<Tuple> {
+        Some(self.center())
+    }
+
+    fn surface_samples(&self, rng: &mut Rng) -> Vec<Tuple> {
+        (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .map(|(u, v)| self.point_on_light(u, v, rng))
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::color::WHITE;
+    use crate::color::{BLACK, WHITE};
+    use crate::consts::{FRAC_PI_3, FRAC_PI_4, FRAC_PI_6};
+    use crate::tuple::ORIGO;
+    use crate::EPSILON;
 
     #[test]
     fn point_light_has_position_and_intensity() {
@@ -27,4 +293,121 @@ mod tests {
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn point_light_direction_depends_on_position() {
+        let light = PointLight::new(Tuple::point(0., 10., 0.), WHITE);
+
+        assert_eq!(light.direction_to(Tuple::point(0., 0., 0.)), Tuple::vector(0., 1., 0.));
+        assert_eq!(light.direction_to(Tuple::point(10., 10., 0.)), Tuple::vector(-1., 0., 0.));
+        assert_eq!(light.distance_from(Tuple::point(0., 0., 0.)), 10.);
+    }
+
+    #[test]
+    fn directional_light_is_parallel_everywhere() {
+        let light = DirectionalLight::new(Tuple::vector(0., -2., 0.), WHITE);
+
+        assert_eq!(light.direction_to(Tuple::point(0., 0., 0.)), Tuple::vector(0., 1., 0.));
+        assert_eq!(light.direction_to(Tuple::point(100., -50., 3.)), Tuple::vector(0., 1., 0.));
+        assert_eq!(light.distance_from(Tuple::point(100., -50., 3.)), Scalar::INFINITY);
+        assert_eq!(light.position(), None);
+    }
+
+    #[test]
+    fn boxed_lights_compare_by_type_and_value() {
+        let point = PointLight::new_boxed(Tuple::point(0., 1., 0.), WHITE);
+        let directional = DirectionalLight::new_boxed(Tuple::vector(0., 1., 0.), WHITE);
+
+        assert_eq!(&point.clone(), &point);
+        assert_ne!(&point, &directional);
+    }
+
+    fn downward_spot() -> SpotLight {
+        SpotLight::new(Tuple::point(0., 10., 0.), Tuple::vector(0., -1., 0.), WHITE, FRAC_PI_6, FRAC_PI_4)
+    }
+
+    // Point on the floor seen from the spot at the given angle off its axis
+    fn floor_point_at(angle: Scalar) -> Tuple {
+        Tuple::point(10. * angle.tan(), 0., 0.)
+    }
+
+    #[test]
+    fn spot_light_is_full_inside_inner_cone() {
+        let spot = downward_spot();
+
+        assert_eq!(spot.intensity_at(Tuple::point(0., 0., 0.)), WHITE);
+        assert_eq!(spot.intensity_at(floor_point_at(FRAC_PI_6 - 0.01)), WHITE);
+    }
+
+    #[test]
+    fn spot_light_fades_to_nothing_at_outer_cone() {
+        let spot = downward_spot();
+        let halfway = spot.intensity_at(floor_point_at((FRAC_PI_6 + FRAC_PI_4) / 2.)).r;
+
+        assert!(0. < halfway && halfway < 1.);
+        assert!(spot.intensity_at(floor_point_at(FRAC_PI_4 - 0.01)).r < halfway);
+        assert!(spot.intensity_at(floor_point_at(FRAC_PI_4)).r < EPSILON);
+        assert_eq!(spot.intensity_at(floor_point_at(FRAC_PI_3)), BLACK);
+        assert_eq!(spot.intensity_at(Tuple::point(0., 20., 0.)), BLACK);
+    }
+
+    #[test]
+    fn spot_light_without_falloff_band_has_hard_edge() {
+        let spot = SpotLight::new(Tuple::point(0., 10., 0.), Tuple::vector(0., -1., 0.), WHITE, FRAC_PI_4, FRAC_PI_4);
+
+        assert_eq!(spot.intensity_at(floor_point_at(FRAC_PI_4 - 0.01)), WHITE);
+        assert_eq!(spot.intensity_at(floor_point_at(FRAC_PI_4 + 0.01)), BLACK);
+    }
+
+    #[test]
+    fn area_light_cells_and_center() {
+        let light = AreaLight::new(ORIGO, Tuple::vector(2., 0., 0.), 4, Tuple::vector(0., 0., 1.), 2, WHITE);
+
+        assert_eq!(light.uvec, Tuple::vector(0.5, 0., 0.));
+        assert_eq!(light.vvec, Tuple::vector(0., 0., 0.5));
+        assert_eq!(light.position(), Some(Tuple::point(1., 0., 0.5)));
+        assert_eq!(light.distance_from(Tuple::point(1., 2., 0.5)), 2.);
+    }
+
+    #[test]
+    fn area_light_samples_cell_centers() {
+        let light = AreaLight::new(ORIGO, Tuple::vector(2., 0., 0.), 4, Tuple::vector(0., 0., 1.), 2, WHITE);
+        let mut rng = Rng::new(0);
+        let cases = [
+            (0, 0, Tuple::point(0.25, 0., 0.25)),
+            (1, 0, Tuple::point(0.75, 0., 0.25)),
+            (0, 1, Tuple::point(0.25, 0., 0.75)),
+            (2, 0, Tuple::point(1.25, 0., 0.25)),
+            (3, 1, Tuple::point(1.75, 0., 0.75))];
+
+        for (u, v, expected) in cases.iter() {
+            assert_eq!(light.point_on_light(*u, *v, &mut rng), *expected);
+        }
+        assert_eq!(light.surface_samples(&mut rng).len(), 8);
+        assert!(PointLight::new(ORIGO, WHITE).surface_samples(&mut rng).is_empty());
+    }
+
+    #[test]
+    fn area_light_with_zero_steps_has_one_cell() {
+        let light = AreaLight::new(ORIGO, Tuple::vector(2., 0., 0.), 0, Tuple::vector(0., 0., 1.), 0, WHITE);
+
+        assert_eq!((light.usteps, light.vsteps), (1, 1));
+        assert_eq!(light.center(), Tuple::point(1., 0., 0.5));
+        assert_eq!(light.surface_samples(&mut Rng::new(0)), vec![Tuple::point(1., 0., 0.5)]);
+    }
+
+    #[test]
+    fn jittered_samples_stay_in_their_cells() {
+        let light = AreaLight::new(ORIGO, Tuple::vector(2., 0., 0.), 4, Tuple::vector(0., 0., 1.), 2, WHITE).jittered();
+        let samples = light.surface_samples(&mut Rng::new(3));
+
+        assert_eq!(samples, light.surface_samples(&mut Rng::new(3)));
+        assert_ne!(samples, light.surface_samples(&mut Rng::new(4)));
+        for (i, p) in samples.iter().enumerate() {
+            let (u, v) = ((i % 4) as Scalar, (i / 4) as Scalar);
+            assert!(p.x >= u * 0.5 && p.x < (u + 1.) * 0.5);
+            assert!(p.z >= v * 0.5 && p.z < (v + 1.) * 0.5);
+            assert_eq!(p.y, 0.);
+        }
+    }
+}
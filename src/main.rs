@@ -1,4 +1,5 @@
-use std::f64::consts::*;
+use rustracer::consts::*;
+use rustracer::Scalar;
 use rustracer::color::*;
 use rustracer::canvas::*;
 use rustracer::tuple::*;
@@ -27,23 +28,22 @@ fn circle_shadow(filename: &str)
     const WALL_SIZE:usize = 7;
     let ray_origin = Tuple::point(0., 0., -5.);
     let wall_z = 10.0;
-    let pixel_size = WALL_SIZE as f64 / CANVAS_PIXELS as f64;
-    let half = WALL_SIZE as f64 / 2.0;
+    let pixel_size = WALL_SIZE as Scalar / CANVAS_PIXELS as Scalar;
+    let half = WALL_SIZE as Scalar / 2.0;
 
     let mut canvas = Canvas::new(CANVAS_PIXELS, CANVAS_PIXELS);
     let color = Color::new(1., 0., 0.);
     let tr = Matrix::shearing(1., 0., 0.5, 0., 0., 0.) * Matrix::scaling(1., 0.5, 1.);
     let shape = Sphere::new(None, Some(tr));
     for y in 0..CANVAS_PIXELS {
-        let world_y = half - pixel_size * (y as f64);
+        let world_y = half - pixel_size * (y as Scalar);
         for x in 0..CANVAS_PIXELS {
-            let world_x = -half + pixel_size * (x as f64);
+            let world_x = -half + pixel_size * (x as Scalar);
             let position = Tuple::point(world_x, world_y, wall_z);
             let r = Ray::new(ray_origin, (position - ray_origin).normalize());
             let xs = shape.intersect(r);
-            match xs.hit() {
-                Some(_i) => canvas.write_pixel(x, y, color),
-                None => ()
+            if xs.hit().is_some() {
+                canvas.write_pixel(x, y, color);
             }
         }
     }
@@ -56,13 +56,15 @@ fn rendered_sphere(filename: &str)
     const WALL_SIZE:usize = 7;
     let ray_origin = Tuple::point(0., 0., -5.);
     let wall_z = 10.0;
-    let pixel_size = WALL_SIZE as f64 / CANVAS_PIXELS as f64;
-    let half = WALL_SIZE as f64 / 2.0;
+    let pixel_size = WALL_SIZE as Scalar / CANVAS_PIXELS as Scalar;
+    let half = WALL_SIZE as Scalar / 2.0;
 
     let mut canvas = Canvas::new(CANVAS_PIXELS, CANVAS_PIXELS);
-    let mut m = Material::default();
-    m.color = Color::new(1., 0.2, 1.);
-    m.pattern = Some(StripePattern::new_boxed(GREEN, RED, Some(Matrix::scaling(0.1, 0.1, 0.1))));
+    let m = Material {
+        color: Color::new(1., 0.2, 1.),
+        pattern: Some(StripePattern::new_boxed(GREEN, RED, Some(Matrix::scaling(0.1, 0.1, 0.1)))),
+        ..Default::default()
+    };
     let tr = Matrix::shearing(1., 0., 0.5, 0., 0., 0.) * Matrix::scaling(1., 0.5, 1.);
     let shape = Sphere::new(Some(m), Some(tr));
     let light_position = Tuple::point(-10., 10., -10.);
@@ -70,21 +72,18 @@ fn rendered_sphere(filename: &str)
     let light = PointLight::new(light_position, light_color);
 
     for y in 0..CANVAS_PIXELS {
-        let world_y = half - pixel_size * (y as f64);
+        let world_y = half - pixel_size * (y as Scalar);
         for x in 0..CANVAS_PIXELS {
-            let world_x = -half + pixel_size * (x as f64);
+            let world_x = -half + pixel_size * (x as Scalar);
             let position = Tuple::point(world_x, world_y, wall_z);
             let r = Ray::new(ray_origin, (position - ray_origin).normalize());
             let xs = shape.intersect(r);
-            match xs.hit() {
-                Some(h) => {
-                    let point = r.position(h.t);
-                    let normal = h.object.normal_at(point);
-                    let eye = - r.direction;
-                    let color = h.object.material().lighting(&*h.object, &light, point, eye, normal, false);
-                    canvas.write_pixel(x, y, color);
-                },
-                _ => ()
+            if let Some(h) = xs.hit() {
+                let point = r.position(h.t);
+                let normal = h.object.normal_at(point);
+                let eye = - r.direction;
+                let color = h.object.material().lighting(&*h.object, &light, point, eye, normal, false);
+                canvas.write_pixel(x, y, color);
             }
         }
     }
@@ -92,7 +91,7 @@ fn rendered_sphere(filename: &str)
 }
 
 fn camera_render_world(filename: &str) {
-    let floor_material = Material::new(Color::new(1., 0.9, 0.9), DEFAULT_AMBIENT, DEFAULT_DIFFUSE, 0., DEFAULT_SHININESS, None);
+    let floor_material = Material::new(Color::new(1., 0.9, 0.9), DEFAULT_AMBIENT, DEFAULT_DIFFUSE, 0., DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
     let floor_transform = Matrix::scaling(10., 0.01, 10.);
     let floor = Sphere::new_boxed(Some(floor_material.clone()), Some(floor_transform));
 
@@ -111,22 +110,22 @@ fn camera_render_world(filename: &str) {
     let right_wall = Sphere::new_boxed(Some(floor_material), Some(right_wall_transform));
 
     let middle_transform = Matrix::translation(-0.5, 1., 0.5);
-    let middle_material = Material::new(Color::new(0.1, 1., 0.5), DEFAULT_AMBIENT, 0.7, 0.3, DEFAULT_SHININESS, None);
+    let middle_material = Material::new(Color::new(0.1, 1., 0.5), DEFAULT_AMBIENT, 0.7, 0.3, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
     let middle = Sphere::new_boxed(Some(middle_material), Some(middle_transform));
 
     let right_transform = Matrix::translation(1.5, 0.5, -0.5) * Matrix::scaling(0.5, 0.5, 0.5);
-    let right_material = Material::new(Color::new(0.5, 1., 0.1), DEFAULT_AMBIENT, 0.7, 0.3, DEFAULT_SHININESS, None);
+    let right_material = Material::new(Color::new(0.5, 1., 0.1), DEFAULT_AMBIENT, 0.7, 0.3, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
     let right = Sphere::new_boxed(Some(right_material), Some(right_transform));
 
     let left_transform = Matrix::translation(-1.5, 0.33, -0.75) * Matrix::scaling(0.33, 0.33, 0.33);
-    let left_material = Material::new(Color::new(1., 0.8, 0.1), DEFAULT_AMBIENT, 0.7, 0.3, DEFAULT_SHININESS, None);
+    let left_material = Material::new(Color::new(1., 0.8, 0.1), DEFAULT_AMBIENT, 0.7, 0.3, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
     let left = Sphere::new_boxed(Some(left_material), Some(left_transform));
 
     let light = Some(PointLight::new(Tuple::point(-10., 10., -10.), WHITE));
     let world = World::new(light, vec![floor, left_wall, right_wall, middle, right, left]);
     let view_transform = Matrix::view_transform(Tuple::point(0., 1.5, -5.), Tuple::point(0., 1., 0.), Tuple::vector(0., 1., 0.));
     let camera = Camera::new(700, 500, FRAC_PI_3, Some(view_transform));
-    let canvas = camera.render(world);
+    let canvas = camera.render(world, &RenderSettings::default());
 
     canvas.save(filename).unwrap();
 }
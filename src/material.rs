@@ -1,49 +1,117 @@
 use super::color::{Color, BLACK, WHITE};
 use super::tuple::Tuple;
-use super::light::PointLight;
+use super::light::Light;
 use super::pattern::BoxPattern;
 use super::shape::Shape;
+use super::Scalar;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Material {
     pub color: Color,
-    pub ambient: f64,
-    pub diffuse: f64,
-    pub specular: f64,
-    pub shininess: f64,
+    pub ambient: Scalar,
+    pub diffuse: Scalar,
+    pub specular: Scalar,
+    pub shininess: Scalar,
+    pub reflective: Scalar,
+    pub transparency: Scalar,
+    pub refractive_index: Scalar,
+    // Tint and wrap weight for light bleeding past the terminator; a zero weight disables it
+    pub subsurface: (Color, Scalar),
+    // Whether the object blocks light for shadow rays, off for glass panes or helper objects
+    pub casts_shadow: bool,
+    // Bump mapping: maps the world space hit point and geometric normal to a new normal
+    pub normal_perturb: Option<fn(Tuple, Tuple) -> Tuple>,
     pub pattern: Option<BoxPattern>
 }
 
-pub const DEFAULT_AMBIENT: f64 = 0.1;
-pub const DEFAULT_DIFFUSE: f64 = 0.9;
-pub const DEFAULT_SPECULAR: f64 = 0.9;
-pub const DEFAULT_SHININESS: f64 = 200.0;
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        // Function addresses aren't guaranteed unique, so equal perturbations may still compare unequal
+        let same_perturb = match (self.normal_perturb, other.normal_perturb) {
+            (Some(a), Some(b)) => std::ptr::fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none()
+        };
+        self.color == other.color &&
+            self.ambient == other.ambient &&
+            self.diffuse == other.diffuse &&
+            self.specular == other.specular &&
+            self.shininess == other.shininess &&
+            self.reflective == other.reflective &&
+            self.transparency == other.transparency &&
+            self.refractive_index == other.refractive_index &&
+            self.subsurface == other.subsurface &&
+            self.casts_shadow == other.casts_shadow &&
+            same_perturb &&
+            self.pattern == other.pattern
+    }
+}
+
+pub const DEFAULT_AMBIENT: Scalar = 0.1;
+pub const DEFAULT_DIFFUSE: Scalar = 0.9;
+pub const DEFAULT_SPECULAR: Scalar = 0.9;
+pub const DEFAULT_SHININESS: Scalar = 200.0;
+pub const DEFAULT_REFLECTIVE: Scalar = 0.0;
+pub const DEFAULT_TRANSPARENCY: Scalar = 0.0;
+pub const DEFAULT_REFRACTIVE_INDEX: Scalar = 1.0;
 pub const DEFAULT_MATERIAL: Material = Material {
     color: WHITE,
     ambient: DEFAULT_AMBIENT,
     diffuse: DEFAULT_DIFFUSE,
     specular: DEFAULT_SPECULAR,
     shininess: DEFAULT_SHININESS,
+    reflective: DEFAULT_REFLECTIVE,
+    transparency: DEFAULT_TRANSPARENCY,
+    refractive_index: DEFAULT_REFRACTIVE_INDEX,
+    subsurface: (BLACK, 0.),
+    casts_shadow: true,
+    normal_perturb: None,
     pattern: None };
 
 impl Default for Material {
     fn default() -> Self {
-        Material::new(WHITE, DEFAULT_AMBIENT, DEFAULT_DIFFUSE, DEFAULT_SPECULAR, DEFAULT_SHININESS, None)
+        Material::new(WHITE, DEFAULT_AMBIENT, DEFAULT_DIFFUSE, DEFAULT_SPECULAR, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None)
     }
 }
 
 impl Material {
-    pub fn new(color: Color, ambient: f64, diffuse: f64, specular: f64, shininess: f64, pattern: Option<BoxPattern>) -> Material {
-        Material { color, ambient, diffuse, specular, shininess, pattern }
+    pub fn new(color: Color, ambient: Scalar, diffuse: Scalar, specular: Scalar, shininess: Scalar, reflective: Scalar, pattern: Option<BoxPattern>) -> Material {
+        Material {
+            color, ambient, diffuse, specular, shininess, reflective,
+            transparency: DEFAULT_TRANSPARENCY,
+            refractive_index: DEFAULT_REFRACTIVE_INDEX,
+            subsurface: (BLACK, 0.),
+            casts_shadow: true,
+            normal_perturb: None,
+            pattern
+        }
+    }
+
+    pub fn lighting(&self, object: &dyn Shape, light: &dyn Light, point: Tuple, eyev: Tuple, normalv: Tuple, in_shadow: bool) -> Color {
+        self.lighting_at_time(object, light, point, eyev, normalv, in_shadow, 0.)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn lighting_at_time(&self, object: &dyn Shape, light: &dyn Light, point: Tuple, eyev: Tuple, normalv: Tuple, in_shadow: bool, time: Scalar) -> Color {
+        let visibility = if in_shadow { 0. } else { 1. };
+        self.lighting_with_visibility(object, light, point, eyev, normalv, visibility, time)
+    }
+
+    // Visibility is the unshadowed fraction of the light, between 0 and 1 for partly blocked area lights
+    #[allow(clippy::too_many_arguments)]
+    pub fn lighting_with_visibility(&self, object: &dyn Shape, light: &dyn Light, point: Tuple, eyev: Tuple, normalv: Tuple, visibility: Scalar, time: Scalar) -> Color {
+        self.surface_lighting(object, light, point, eyev, normalv, visibility, time) + self.subsurface_at(light, point, normalv) * visibility
     }
 
-    pub fn lighting(&self, object: &dyn Shape, light: &PointLight, point: Tuple, eyev: Tuple, normalv: Tuple, in_shadow: bool) -> Color {
+    // The ambient, diffuse and specular terms, i.e. all but subsurface_at
+    #[allow(clippy::too_many_arguments)]
+    pub fn surface_lighting(&self, object: &dyn Shape, light: &dyn Light, point: Tuple, eyev: Tuple, normalv: Tuple, visibility: Scalar, time: Scalar) -> Color {
         let color = match &self.pattern {
-            Some(p) => p.pattern_at_shape(object, point),
+            Some(p) => p.pattern_at_shape_at_time(object, point, time),
             None => self.color
         };
-        let effective_color = color * light.intensity;
-        let lightv = (light.position - point).normalize();
+        let intensity = light.intensity_at(point);
+        let effective_color = color * intensity;
+        let lightv = light.direction_to(point);
         let ambient = effective_color * self.ambient;
         let light_dot_normal = lightv.dot(&normalv);
         let (diffuse, specular) =
@@ -59,17 +127,30 @@ impl Material {
                  }
                  else {
                      let factor = reflect_dot_eye.powf(self.shininess);
-                     light.intensity * self.specular * factor
+                     intensity * self.specular * factor
                  }
                 )
             };
-        ambient + if in_shadow { BLACK } else { diffuse + specular }
+        ambient + (diffuse + specular) * visibility
+    }
+
+    // Wrap-around diffuse: light bleeds up to `weight` past the terminator, fading smoothly to zero.
+    // World::shade_hit doesn't let the object shadow this term, as the light is scattered through it.
+    pub fn subsurface_at(&self, light: &dyn Light, point: Tuple, normalv: Tuple) -> Color {
+        let (tint, weight) = self.subsurface;
+        if weight <= 0. {
+            return BLACK;
+        }
+        let light_dot_normal = light.direction_to(point).dot(&normalv);
+        let wrap = ((light_dot_normal + weight) / (1. + weight)).max(0.);
+        tint * light.intensity_at(point) * self.diffuse * wrap
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::light::{DirectionalLight, PointLight};
     use crate::tuple::ORIGO;
     use crate::sphere::Sphere;
     use crate::pattern::StripePattern;
@@ -81,6 +162,10 @@ mod tests {
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.shininess, 200.);
+        assert_eq!(m.reflective, 0.);
+        assert_eq!(m.transparency, 0.);
+        assert_eq!(m.refractive_index, 1.);
+        assert!(m.casts_shadow);
     }
 
     #[test]
@@ -101,7 +186,7 @@ mod tests {
         let object = Sphere::new(None, None);
         let m = Material::default();
         let position = ORIGO;
-        let pv = 2.0f64.sqrt() / 2.0;
+        let pv = Scalar::sqrt(2.0) / 2.0;
         let eyev = Tuple::vector(0., pv, -pv);
         let normalv = Tuple::vector(0., 0., -1.);
         let light = PointLight::new(Tuple::point(0., 0., -10.), WHITE);
@@ -128,7 +213,7 @@ mod tests {
         let object = Sphere::new(None, None);
         let m = Material::default();
         let position = ORIGO;
-        let pv = -2.0f64.sqrt() / 2.0;
+        let pv = -Scalar::sqrt(2.0) / 2.0;
         let eyev = Tuple::vector(0., pv, pv);
         let normalv = Tuple::vector(0., 0., -1.);
         let light = PointLight::new(Tuple::point(0., 10., -10.), WHITE);
@@ -167,7 +252,7 @@ mod tests {
     #[test]
     fn lighting_with_pattern_applied() {
         let object = Sphere::new(None, None);
-        let m = Material::new(WHITE, 1., 0., 0., DEFAULT_SHININESS, Some(StripePattern::new_boxed(WHITE, BLACK, None)));
+        let m = Material::new(WHITE, 1., 0., 0., DEFAULT_SHININESS, DEFAULT_REFLECTIVE, Some(StripePattern::new_boxed(WHITE, BLACK, None)));
         let eyev = Tuple::vector(0., 0., -1.);
         let normalv = Tuple::vector(0., 0., -1.);
         let light = PointLight::new(Tuple::point(0., 0., -10.), WHITE);
@@ -177,4 +262,47 @@ mod tests {
         assert_eq!(c1, WHITE);
         assert_eq!(c2, BLACK);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn subsurface_lights_surface_just_past_terminator() {
+        let object = Sphere::new(None, None);
+        let eyev = Tuple::vector(0., 0., -1.);
+        let normalv = Tuple::vector(0., 0., -1.);
+        let light = PointLight::new(Tuple::point(10., 0., 1.), WHITE);
+        let position = ORIGO;
+        let plain = Material { ambient: 0., ..Default::default() };
+        let waxy = Material { subsurface: (Color::new(1., 0.5, 0.5), 0.3), ..plain.clone() };
+
+        assert_eq!(plain.lighting(&object, &light, position, eyev, normalv, false), BLACK);
+        let c = waxy.lighting(&object, &light, position, eyev, normalv, false);
+        assert!(c.r > 0. && c.r < 0.5);
+        assert!(c.g > 0. && c.g < c.r);
+    }
+
+    #[test]
+    fn subsurface_is_continuous_across_terminator() {
+        let object = Sphere::new(None, None);
+        let eyev = Tuple::vector(0., 0., -1.);
+        let normalv = Tuple::vector(0., 0., -1.);
+        let waxy = Material { ambient: 0., specular: 0., subsurface: (WHITE, 0.3), ..Default::default() };
+        let lit_at = |z: Scalar| waxy.lighting(&object, &PointLight::new(Tuple::point(10., 0., z), WHITE), ORIGO, eyev, normalv, false);
+        let (before, after) = (lit_at(-0.01), lit_at(0.01));
+
+        assert!((before.r - after.r).abs() < 0.01);
+        assert!(after.r > 0.2);
+    }
+
+    #[test]
+    fn directional_light_reaches_distant_surfaces_at_the_same_angle() {
+        let object = Sphere::new(None, None);
+        let m = Material::default();
+        let eyev = Tuple::vector(0., 0., -1.);
+        let normalv = Tuple::vector(0., 0., -1.);
+        let sun = DirectionalLight::new(Tuple::vector(0., -1., 1.), WHITE);
+        let near = m.lighting(&object, &sun, ORIGO, eyev, normalv, false);
+        let far = m.lighting(&object, &sun, Tuple::point(50., -20., 3.), eyev, normalv, false);
+
+        assert_eq!(near, Color::new(0.7364, 0.7364, 0.7364));
+        assert_eq!(near, far);
+    }
+}
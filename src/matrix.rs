@@ -1,14 +1,26 @@
 use core::ops;
 use super::tuple::Tuple;
+use super::Scalar;
+
+// Per thread, so tests running in parallel don't see each other's inversions
+#[cfg(test)]
+thread_local! {
+    static INVERSIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+pub(crate) fn inversions_on_this_thread() -> usize {
+    INVERSIONS.with(|n| n.get())
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct Row {
-    inner: [f64; 4],
+    inner: [Scalar; 4],
     size: usize
 }
 
 impl ops::Index<usize> for Row {
-    type Output = f64;
+    type Output = Scalar;
     fn index(&self, col: usize) -> &Self::Output {
         if col >= self.size { panic!("Index out-of-bounds") }
         &self.inner[col]
@@ -81,11 +93,11 @@ impl Default for Matrix {
 }
 
 impl Matrix {
-    const EMPTY_ROW:  [f64; 4] = [0., 0., 0., 0.];
-    const EMPTY_ROW3: [f64; 3] = [0., 0., 0.];
-    const EMPTY_ROW2: [f64; 2] = [0., 0.];
+    const EMPTY_ROW:  [Scalar; 4] = [0., 0., 0., 0.];
+    const EMPTY_ROW3: [Scalar; 3] = [0., 0., 0.];
+    const EMPTY_ROW2: [Scalar; 2] = [0., 0.];
 
-    pub fn new(row0: [f64; 4], row1: [f64; 4], row2 : [f64; 4], row3 : [f64; 4]) -> Self
+    pub fn new(row0: [Scalar; 4], row1: [Scalar; 4], row2 : [Scalar; 4], row3 : [Scalar; 4]) -> Self
     {
         Self {
             inner: [
@@ -96,7 +108,13 @@ impl Matrix {
             size: 4}
     }
 
-    pub fn new3(row0: [f64; 3], row1: [f64; 3], row2 : [f64; 3]) -> Self
+    // Like ==, but with a caller chosen tolerance
+    pub fn approx_eq(&self, other: &Matrix, eps: Scalar) -> bool {
+        self.size == other.size &&
+            (0..self.size).all(|row| (0..self.size).all(|col| super::approx_eq_eps(self[row][col], other[row][col], eps)))
+    }
+
+    pub fn new3(row0: [Scalar; 3], row1: [Scalar; 3], row2 : [Scalar; 3]) -> Self
     {
         Self {
             inner: [
@@ -107,7 +125,7 @@ impl Matrix {
             size: 3}
     }
 
-    pub fn new2(row0: [f64; 2], row1: [f64; 2]) -> Self
+    pub fn new2(row0: [Scalar; 2], row1: [Scalar; 2]) -> Self
     {
         Self {
             inner: [
@@ -130,11 +148,11 @@ impl Matrix {
         Matrix::new2(Matrix::EMPTY_ROW2, Matrix::EMPTY_ROW2)
     }
 
-    fn coerce_array2(arr: [f64; 2]) -> Row {
+    fn coerce_array2(arr: [Scalar; 2]) -> Row {
         Row { inner: [arr[0], arr[1], 0., 0.], size: 2 }
     }
 
-    fn coerce_array3(arr: [f64; 3]) -> Row {
+    fn coerce_array3(arr: [Scalar; 3]) -> Row {
         Row { inner: [arr[0], arr[1], arr[2], 0.], size: 3 }
     }
 
@@ -147,7 +165,7 @@ impl Matrix {
         }
     }
 
-    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+    pub fn set(&mut self, row: usize, col: usize, value: Scalar) {
         self.inner[row].inner[col] = value;
     }
 
@@ -167,7 +185,7 @@ impl Matrix {
         m
     }
 
-    fn determinant(&self) -> f64 {
+    fn determinant(&self) -> Scalar {
         let size = self.size;
         match size {
             2 => self[0][0] * self[1][1] - self[0][1] * self[1][0],
@@ -202,16 +220,37 @@ impl Matrix {
         m
     }
 
-    fn minor(&self, row: usize, col: usize) -> f64 {
+    fn minor(&self, row: usize, col: usize) -> Scalar {
         self.submatrix(row, col).determinant()
     }
 
-    fn cofactor(&self, row: usize, col: usize) -> f64 {
+    fn cofactor(&self, row: usize, col: usize) -> Scalar {
         let minor = self.minor(row, col);
         if (row + col) & 1 == 1 { -minor } else { minor }
     }
 
+    // Gram-Schmidt the columns of the upper-left 3x3, leaving translation untouched
+    pub fn orthonormalize(&self) -> Matrix {
+        let column = |col: usize| Tuple::vector(self[0][col], self[1][col], self[2][col]);
+        let x = column(0).normalize();
+        let y = column(1) - x * x.dot(&column(1));
+        let y = y.normalize();
+        let z = column(2) - x * x.dot(&column(2)) - y * y.dot(&column(2));
+        let z = z.normalize();
+
+        let mut m = *self;
+        for (col, axis) in [x, y, z].iter().enumerate() {
+            m.set(0, col, axis.x);
+            m.set(1, col, axis.y);
+            m.set(2, col, axis.z);
+        }
+        m
+    }
+
+    // None for singular matrices (determinant 0), e.g. a scaling with a zero factor
     pub fn inverse(&self) -> Option<Matrix> {
+        #[cfg(test)]
+        INVERSIONS.with(|n| n.set(n.get() + 1));
         let det = self.determinant();
         if det == 0.0 { return Option::None; }
         let size = self.size;
@@ -228,6 +267,7 @@ impl Matrix {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::EPSILON;
 
     #[test]
     fn construct_4x4_matrix()
@@ -566,4 +606,52 @@ mod tests {
         let c = a * b;
         assert_eq!(a, c * b.inverse().unwrap());
     }
-}
\ No newline at end of file
+
+    fn orthonormality_error(m: &Matrix) -> Scalar {
+        let mut error: Scalar = 0.;
+        for i in 0..3 {
+            for j in 0..3 {
+                let dot: Scalar = (0..3).map(|row| m[row][i] * m[row][j]).sum();
+                let expected = if i == j { 1. } else { 0. };
+                error = error.max((dot - expected).abs());
+            }
+        }
+        error
+    }
+
+    #[test]
+    fn orthonormalize_corrects_drift_from_many_rotations() {
+        let step = Matrix::rotation_x(0.001) * Matrix::rotation_y(0.002) * Matrix::rotation_z(0.003);
+        let mut m = Matrix::translation(1., 2., 3.);
+        for _ in 0..10_000 {
+            m = m * step;
+        }
+        let drift = orthonormality_error(&m);
+        assert!(drift > 0.);
+
+        let fixed = m.orthonormalize();
+        assert!(orthonormality_error(&fixed) < drift);
+        assert!(orthonormality_error(&fixed) < EPSILON);
+        assert_eq!(fixed[0][3], 1.);
+        assert_eq!(fixed[1][3], 2.);
+        assert_eq!(fixed[2][3], 3.);
+    }
+
+    #[test]
+    fn orthonormalize_keeps_rotation_unchanged() {
+        let m = Matrix::rotation_y(0.5) * Matrix::rotation_x(1.2);
+
+        assert_eq!(m, m.orthonormalize());
+    }
+
+    #[test]
+    fn comparing_matrices_with_custom_tolerance() {
+        let a = Matrix::scaling(0.5, 2., 1.);
+        let mut b = a;
+        b.set(0, 0, 0.500001);
+
+        assert!(a.approx_eq(&b, 1e-5));
+        assert!(!a.approx_eq(&b, 1e-7));
+        assert!(!a.approx_eq(&a.submatrix(3, 3), 1.));
+    }
+}
@@ -0,0 +1,90 @@
+use super::Scalar;
+
+// Ken Perlin's reference permutation; keeping it fixed makes the noise fully deterministic
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225,
+    140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148,
+    247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32,
+    57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122,
+    60, 211, 133, 230, 220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54,
+    65, 25, 63, 161, 1, 216, 80, 73, 209, 76, 132, 187, 208, 89, 18, 169,
+    200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173, 186, 3, 64,
+    52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212,
+    207, 206, 59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213,
+    119, 248, 152, 2, 44, 154, 163, 70, 221, 153, 101, 155, 167, 43, 172, 9,
+    129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232, 178, 185, 112, 104,
+    218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162, 241,
+    81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157,
+    184, 84, 204, 176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93,
+    222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn hash(i: usize) -> usize {
+    PERMUTATION[i & 255] as usize
+}
+
+fn fade(t: Scalar) -> Scalar {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: Scalar, a: Scalar, b: Scalar) -> Scalar {
+    a + t * (b - a)
+}
+
+// Dot product of the offset with one of twelve edge gradients picked by the hash
+fn grad(hash: usize, x: Scalar, y: Scalar, z: Scalar) -> Scalar {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+// Improved Perlin noise, roughly in -1..1 and zero at every integer lattice point
+pub fn perlin3(x: Scalar, y: Scalar, z: Scalar) -> Scalar {
+    let (xf, yf, zf) = (x.floor(), y.floor(), z.floor());
+    let (xi, yi, zi) = ((xf as i64 & 255) as usize, (yf as i64 & 255) as usize, (zf as i64 & 255) as usize);
+    let (x, y, z) = (x - xf, y - yf, z - zf);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let a = hash(xi) + yi;
+    let (aa, ab) = (hash(a) + zi, hash(a + 1) + zi);
+    let b = hash(xi + 1) + yi;
+    let (ba, bb) = (hash(b) + zi, hash(b + 1) + zi);
+
+    lerp(w,
+         lerp(v,
+              lerp(u, grad(hash(aa), x, y, z), grad(hash(ba), x - 1., y, z)),
+              lerp(u, grad(hash(ab), x, y - 1., z), grad(hash(bb), x - 1., y - 1., z))),
+         lerp(v,
+              lerp(u, grad(hash(aa + 1), x, y, z - 1.), grad(hash(ba + 1), x - 1., y, z - 1.)),
+              lerp(u, grad(hash(ab + 1), x, y - 1., z - 1.), grad(hash(bb + 1), x - 1., y - 1., z - 1.))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_vanishes_on_lattice_points() {
+        assert_eq!(perlin3(0., 0., 0.), 0.);
+        assert_eq!(perlin3(3., -7., 12.), 0.);
+    }
+
+    #[test]
+    fn noise_is_deterministic_and_bounded() {
+        let samples: Vec<Scalar> = (0..200).map(|i| i as Scalar * 0.173).map(|t| perlin3(t, t * 0.5, -t)).collect();
+        let again: Vec<Scalar> = (0..200).map(|i| i as Scalar * 0.173).map(|t| perlin3(t, t * 0.5, -t)).collect();
+
+        assert_eq!(samples, again);
+        assert!(samples.iter().all(|n| (-1. ..=1.).contains(n)));
+        assert!(samples.iter().any(|n| n.abs() > 0.1));
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let (a, b) = (perlin3(1.5, 2.25, 0.75), perlin3(1.5001, 2.25, 0.75));
+
+        assert!((a - b).abs() < 0.01);
+    }
+}
@@ -0,0 +1,356 @@
+use super::color::Color;
+use super::group::Group;
+use super::material::{Material, DEFAULT_AMBIENT};
+use super::shape::BoxShape;
+use super::triangle::{SmoothTriangle, Triangle};
+use super::tuple::Tuple;
+use super::Scalar;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjTriangle {
+    pub points: [Tuple; 3],
+    // Only present when every vertex of the face referenced a vertex normal
+    pub normals: Option<[Tuple; 3]>,
+    // Name from the last `usemtl` statement before the face
+    pub material: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ObjParseResult {
+    pub vertices: Vec<Tuple>,
+    pub normals: Vec<Tuple>,
+    // Faces seen before any `g` statement
+    pub default_group: Vec<ObjTriangle>,
+    pub named_groups: Vec<(String, Vec<ObjTriangle>)>,
+    // Files named by `mtllib`, for the caller to read and pass through parse_mtl
+    pub material_libraries: Vec<String>,
+    pub ignored_lines: usize,
+}
+
+impl ObjTriangle {
+    // Faces with vertex normals become smooth triangles
+    pub fn to_shape(&self) -> BoxShape {
+        self.to_shape_with_material(None)
+    }
+
+    pub fn to_shape_with_material(&self, material: Option<Material>) -> BoxShape {
+        match self.normals {
+            Some(normals) => SmoothTriangle::new_boxed(self.points, normals, material),
+            None => Triangle::new_boxed(self.points, material)
+        }
+    }
+}
+
+impl ObjParseResult {
+    // OBJ indices are 1-based
+    pub fn vertex(&self, index: usize) -> Option<Tuple> {
+        index.checked_sub(1).and_then(|i| self.vertices.get(i)).copied()
+    }
+
+    pub fn normal(&self, index: usize) -> Option<Tuple> {
+        index.checked_sub(1).and_then(|i| self.normals.get(i)).copied()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.default_group.len() + self.named_groups.iter().map(|(_, g)| g.len()).sum::<usize>()
+    }
+
+    // Default group faces are direct children; each named group becomes a nested group
+    pub fn to_group(&self) -> Group {
+        self.to_group_with_materials(&HashMap::new())
+    }
+
+    // Faces whose material is missing from the library keep the default material
+    pub fn to_group_with_materials(&self, materials: &HashMap<String, Material>) -> Group {
+        let to_shape = |triangle: &ObjTriangle| {
+            let material = triangle.material.as_ref().and_then(|name| materials.get(name));
+            triangle.to_shape_with_material(material.cloned())
+        };
+        let mut group = Group::default();
+        for triangle in self.default_group.iter() {
+            group.add_child(to_shape(triangle));
+        }
+        for (_, triangles) in self.named_groups.iter() {
+            group.add_child(Group::new_boxed(None, triangles.iter().map(to_shape).collect()));
+        }
+        group
+    }
+
+    fn current_group(&mut self) -> &mut Vec<ObjTriangle> {
+        match self.named_groups.last_mut() {
+            Some((_, triangles)) => triangles,
+            None => &mut self.default_group
+        }
+    }
+
+    // Polygons are fan-triangulated around their first vertex
+    fn parse_face(&self, args: &[&str], material: &Option<String>) -> Option<Vec<ObjTriangle>> {
+        if args.len() < 3 {
+            return None;
+        }
+        let mut points = vec![];
+        let mut normals = vec![];
+        for arg in args {
+            // v, v/vt, v//vn or v/vt/vn
+            let mut parts = arg.split('/');
+            points.push(self.vertex(parts.next()?.parse().ok()?)?);
+            match parts.nth(1) {
+                Some(n) if !n.is_empty() => normals.push(Some(self.normal(n.parse().ok()?)?)),
+                _ => normals.push(None)
+            }
+        }
+        let triangles = (1..points.len() - 1)
+            .map(|i| ObjTriangle {
+                points: [points[0], points[i], points[i + 1]],
+                normals: match (normals[0], normals[i], normals[i + 1]) {
+                    (Some(n1), Some(n2), Some(n3)) => Some([n1, n2, n3]),
+                    _ => None
+                },
+                material: material.clone()
+            })
+            .collect();
+        Some(triangles)
+    }
+}
+
+// Collects the named sub-groups into a parent group, see ObjParseResult::to_group
+pub fn obj_to_group(result: &ObjParseResult) -> BoxShape {
+    Box::new(result.to_group())
+}
+
+fn parse_xyz(args: &[&str]) -> Option<(Scalar, Scalar, Scalar)> {
+    match args {
+        [x, y, z] => Some((x.parse().ok()?, y.parse().ok()?, z.parse().ok()?)),
+        _ => None
+    }
+}
+
+// Vertices may carry a fourth weight component, which only matters for rational curves
+fn parse_vertex(args: &[&str]) -> Option<(Scalar, Scalar, Scalar)> {
+    match args {
+        [_, _, _, w] => w.parse::<Scalar>().ok().and_then(|_| parse_xyz(&args[..3])),
+        _ => parse_xyz(args)
+    }
+}
+
+// Lines that are malformed or use unsupported statements are skipped and counted
+pub fn parse_obj(contents: &str) -> ObjParseResult {
+    let mut result = ObjParseResult::default();
+    let mut material = None;
+    for line in contents.lines() {
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            None => continue,
+            Some(k) if k.starts_with('#') => continue,
+            Some(k) => k
+        };
+        let args: Vec<&str> = tokens.collect();
+        let parsed = match keyword {
+            "v" => parse_vertex(&args).map(|(x, y, z)| result.vertices.push(Tuple::point(x, y, z))),
+            "vn" => parse_xyz(&args).map(|(x, y, z)| result.normals.push(Tuple::vector(x, y, z))),
+            "f" => result.parse_face(&args, &material).map(|triangles| result.current_group().extend(triangles)),
+            "usemtl" if !args.is_empty() => {
+                material = Some(args.join(" "));
+                Some(())
+            }
+            "mtllib" if !args.is_empty() => {
+                result.material_libraries.extend(args.iter().map(|name| name.to_string()));
+                Some(())
+            }
+            "g" if !args.is_empty() => {
+                result.named_groups.push((args.join(" "), vec![]));
+                Some(())
+            }
+            _ => None
+        };
+        if parsed.is_none() {
+            result.ignored_lines += 1;
+        }
+    }
+    result
+}
+
+fn average(args: &[&str]) -> Option<Scalar> {
+    parse_xyz(args).map(|(r, g, b)| (r + g + b) / 3.)
+}
+
+// Reads the `newmtl` entries of an MTL library. Kd becomes the color; Ka and Ks are colors in
+// MTL but scalar weights here, so they're averaged. Exporters often write Ka 1 1 1, which as a
+// weight would wash the shape out, so Ka scales the default ambient instead. Ns is the shininess,
+// Ni the refractive index and d the opacity. Anything else is ignored.
+pub fn parse_mtl(contents: &str) -> HashMap<String, Material> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+    for line in contents.lines() {
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) if !k.starts_with('#') => k,
+            _ => continue
+        };
+        let args: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            materials.extend(current.take());
+            current = Some((args.join(" "), Material::default()));
+            continue;
+        }
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => continue
+        };
+        let value = args.first().and_then(|v| v.parse::<Scalar>().ok());
+        match keyword {
+            "Kd" => if let Some((r, g, b)) = parse_xyz(&args) { material.color = Color::new(r, g, b) },
+            "Ka" => if let Some(a) = average(&args) { material.ambient = a * DEFAULT_AMBIENT },
+            "Ks" => if let Some(s) = average(&args) { material.specular = s },
+            "Ns" => if let Some(n) = value { material.shininess = n },
+            "Ni" => if let Some(n) = value { material.refractive_index = n },
+            "d" => if let Some(d) = value { material.transparency = 1. - d },
+            _ => {}
+        }
+    }
+    materials.extend(current);
+    materials
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\nwho traveled much faster than light.\n\nv 1 2\nf 1 2 99\n";
+        let result = parse_obj(gibberish);
+
+        assert_eq!(result.ignored_lines, 4);
+        assert_eq!(result.triangle_count(), 0);
+    }
+
+    #[test]
+    fn vertex_records() {
+        let result = parse_obj("v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0\n");
+
+        assert_eq!(result.vertex(1), Some(Tuple::point(-1., 1., 0.)));
+        assert_eq!(result.vertex(2), Some(Tuple::point(-1., 0.5, 0.)));
+        assert_eq!(result.vertex(3), Some(Tuple::point(1., 0., 0.)));
+        assert_eq!(result.vertex(4), Some(Tuple::point(1., 1., 0.)));
+        assert_eq!(result.vertex(0), None);
+    }
+
+    #[test]
+    fn vertex_records_with_weight() {
+        let result = parse_obj("v 0 0 0 1
+v 1 0 0
+v 0 1 0 0.5
+v 0 0 1
+v 1 2 3 x
+f 2 3 4
+");
+
+        assert_eq!(result.vertices.len(), 4);
+        assert_eq!(result.ignored_lines, 1);
+        assert_eq!(result.vertex(3), Some(Tuple::point(0., 1., 0.)));
+        assert_eq!(result.default_group[0].points, [Tuple::point(1., 0., 0.), Tuple::point(0., 1., 0.), Tuple::point(0., 0., 1.)]);
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let result = parse_obj("v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\nf 1 2 3\nf 1 3 4\n");
+        let g = &result.default_group;
+
+        assert_eq!(g.len(), 2);
+        assert_eq!(g[0].points, [result.vertices[0], result.vertices[1], result.vertices[2]]);
+        assert_eq!(g[1].points, [result.vertices[0], result.vertices[2], result.vertices[3]]);
+        assert_eq!(g[0].normals, None);
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let result = parse_obj("v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\nf 1 2 3 4 5\n");
+        let g = &result.default_group;
+
+        assert_eq!(g.len(), 3);
+        assert_eq!(g[2].points, [result.vertices[0], result.vertices[3], result.vertices[4]]);
+    }
+
+    #[test]
+    fn triangles_in_named_groups() {
+        let cube = "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\nv -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+                    g Front\nf 1 2 3 4\ng Back\nf 5 8 7 6\ng Sides\nf 1 5 6 2\nf 2 6 7 3\nf 3 7 8 4\nf 4 8 5 1\n";
+        let result = parse_obj(cube);
+        let names: Vec<&str> = result.named_groups.iter().map(|(name, _)| name.as_str()).collect();
+
+        assert_eq!(names, vec!["Front", "Back", "Sides"]);
+        assert_eq!(result.named_groups[2].1.len(), 8);
+        assert_eq!(result.triangle_count(), 12);
+        assert_eq!(result.ignored_lines, 0);
+    }
+
+    #[test]
+    fn faces_with_vertex_normals() {
+        let result = parse_obj("v 0 1 0\nv -1 0 0\nv 1 0 0\n\nvn -1 0 0\nvn 1 0 0\nvn 0 1 0\n\nf 1//3 2//1 3//2\nf 1/0/3 2/102/1 3/14/2\n");
+        let g = &result.default_group;
+
+        assert_eq!(result.normal(1), Some(Tuple::vector(-1., 0., 0.)));
+        assert_eq!(g.len(), 2);
+        assert_eq!(g[0].normals, Some([result.normals[2], result.normals[0], result.normals[1]]));
+        assert_eq!(g[0], g[1]);
+    }
+
+    #[test]
+    fn converting_obj_to_group() {
+        let result = parse_obj("v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nvn 0 0 -1\n\nf 1 2 3\ng Smooth\nf 1//1 3//1 4//1\n");
+        let group = result.to_group();
+        let children = group.children();
+
+        assert_eq!(children.len(), 2);
+        assert!(children[0].as_any().downcast_ref::<Triangle>().is_some());
+        let nested = children[1].as_any().downcast_ref::<Group>().unwrap();
+        assert!(nested.children()[0].as_any().downcast_ref::<SmoothTriangle>().is_some());
+        assert_eq!(obj_to_group(&result).as_any().downcast_ref::<Group>(), Some(&group));
+    }
+
+    const MTL: &str = "# two materials\nnewmtl red plastic\nKd 1 0 0\nKa 0.2 0.1 0\nKs 0.5 0.5 0.5\nNs 50\n\n\
+                       newmtl glass\nKd 1 1 1\nd 0.1\nNi 1.5\nillum 4\n";
+
+    #[test]
+    fn parsing_material_library() {
+        let materials = parse_mtl(MTL);
+        let red = &materials["red plastic"];
+        let glass = &materials["glass"];
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(red.color, Color::new(1., 0., 0.));
+        assert!(crate::approx_eq(red.ambient, 0.1 * DEFAULT_AMBIENT));
+        assert_eq!(red.specular, 0.5);
+        assert_eq!(red.shininess, 50.);
+        assert_eq!(red.transparency, 0.);
+        assert!(crate::approx_eq(glass.transparency, 0.9));
+        assert_eq!(glass.refractive_index, 1.5);
+        assert_eq!(glass.diffuse, Material::default().diffuse);
+    }
+
+    #[test]
+    fn full_ambient_color_keeps_the_default_ambient() {
+        let materials = parse_mtl("newmtl white\nKa 1 1 1\n");
+
+        assert_eq!(materials["white"].ambient, DEFAULT_AMBIENT);
+    }
+
+    #[test]
+    fn faces_use_the_current_material() {
+        let result = parse_obj("mtllib scene.mtl\nv 0 1 0\nv -1 0 0\nv 1 0 0\nf 1 2 3\nusemtl glass\nf 1 2 3\nusemtl missing\nf 1 2 3\n");
+        let g = &result.default_group;
+
+        assert_eq!(result.material_libraries, vec!["scene.mtl".to_string()]);
+        assert_eq!(result.ignored_lines, 0);
+        assert_eq!(g[0].material, None);
+        assert_eq!(g[1].material, Some("glass".to_string()));
+
+        let group = result.to_group_with_materials(&parse_mtl(MTL));
+        let children = group.children();
+        assert_eq!(children[0].material(), &Material::default());
+        assert_eq!(children[1].material().refractive_index, 1.5);
+        assert_eq!(children[2].material(), &Material::default());
+    }
+}
@@ -1,23 +1,71 @@
 use std::fmt;
 use std::any::Any;
+use std::sync::Arc;
 
+use super::canvas::Canvas;
 use super::color::Color;
+use super::consts::PI;
+use super::noise::perlin3;
+use super::sphere::Sphere;
 use super::tuple::Tuple;
 use super::matrix::Matrix;
 use super::shape::{Shape, inverse_transform_parameter};
+use super::Scalar;
 
-pub trait Pattern: Any + fmt::Debug {
+pub trait Pattern: Any + fmt::Debug + Send + Sync {
     fn box_clone(&self) -> BoxPattern;
     fn box_eq(&self, other: &dyn Any) -> bool;
     fn as_any(&self) -> &dyn Any;
     fn transformation(&self) -> Matrix;
     fn inverse_transformation(&self) -> Matrix;
     fn inner_pattern_at(&self, pattern_point: Tuple) -> Color;
-    fn pattern_at_shape(&self, object: &dyn Shape, world_point: Tuple) -> Color {
-        let object_point = object.inverse_transformation() * world_point;
-        let pattern_point = self.inverse_transformation() * object_point;
+    // Patterns wrapping other patterns override this to pass the time on to them
+    fn inner_pattern_at_time(&self, pattern_point: Tuple, _time: Scalar) -> Color {
         self.inner_pattern_at(pattern_point)
     }
+    fn animation(&self) -> Option<&Animation> {
+        None
+    }
+    fn pattern_at_shape(&self, object: &dyn Shape, world_point: Tuple) -> Color {
+        self.pattern_at_shape_at_time(object, world_point, 0.)
+    }
+    fn pattern_at_shape_at_time(&self, object: &dyn Shape, world_point: Tuple, time: Scalar) -> Color {
+        self.pattern_at_time(object.to_object_space(world_point), time)
+    }
+    // Takes the point in the space the pattern is placed in, i.e. before its own transform
+    fn pattern_at_time(&self, point: Tuple, time: Scalar) -> Color {
+        let mut pattern_point = self.inverse_transformation() * point;
+        if let Some(animation) = self.animation() {
+            pattern_point = animation.inverse_at(time) * pattern_point;
+        }
+        self.inner_pattern_at_time(pattern_point, time)
+    }
+}
+
+// Extra pattern-space transform evaluated per frame time, e.g. to make stripes flow
+#[derive(Clone)]
+pub struct Animation(Arc<dyn Fn(Scalar) -> Matrix + Send + Sync>);
+
+impl Animation {
+    pub fn new(f: impl Fn(Scalar) -> Matrix + Send + Sync + 'static) -> Self {
+        Animation(Arc::new(f))
+    }
+
+    fn inverse_at(&self, time: Scalar) -> Matrix {
+        (self.0)(time).inverse().unwrap_or_else(Matrix::new_empty4)
+    }
+}
+
+impl fmt::Debug for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Animation")
+    }
+}
+
+impl PartialEq for Animation {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 pub type BoxPattern = Box<dyn Pattern>;
@@ -34,12 +82,13 @@ impl PartialEq for BoxPattern {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StripePattern {
     a: Color,
     b: Color,
     transform: Matrix,
-    inverse_transform: Matrix
+    inverse_transform: Matrix,
+    animation: Option<Animation>
 }
 
 impl StripePattern {
@@ -48,13 +97,18 @@ impl StripePattern {
             a,
             b,
             transform: transform.unwrap_or_default(),
-            inverse_transform: inverse_transform_parameter(transform)
+            inverse_transform: inverse_transform_parameter(transform),
+            animation: None
         }
     }
 
     pub fn new_boxed(a: Color, b: Color, transform: Option<Matrix>) -> BoxPattern {
         Box::new(Self::new(a, b, transform))
     }
+
+    pub fn with_animation(self, animation: Animation) -> Self {
+        Self { animation: Some(animation), ..self }
+    }
 }
 
 impl Pattern for StripePattern {
@@ -63,7 +117,7 @@ impl Pattern for StripePattern {
     }
 
     fn box_eq(&self, other: &dyn Any) -> bool {
-        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -78,6 +132,10 @@ impl Pattern for StripePattern {
         self.inverse_transform
     }
 
+    fn animation(&self) -> Option<&Animation> {
+        self.animation.as_ref()
+    }
+
     fn inner_pattern_at(&self, pattern_point: Tuple) -> Color {
         if pattern_point.x < 0. {
             if pattern_point.x.abs() % 2. <= 1. {
@@ -93,12 +151,13 @@ impl Pattern for StripePattern {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GradientPattern {
     a: Color,
     b: Color,
     transform: Matrix,
-    inverse_transform: Matrix
+    inverse_transform: Matrix,
+    animation: Option<Animation>
 }
 
 impl GradientPattern {
@@ -107,13 +166,18 @@ impl GradientPattern {
             a,
             b,
             transform: transform.unwrap_or_default(),
-            inverse_transform: inverse_transform_parameter(transform)
+            inverse_transform: inverse_transform_parameter(transform),
+            animation: None
         }
     }
 
     pub fn new_boxed(a: Color, b: Color, transform: Option<Matrix>) -> BoxPattern {
         Box::new(Self::new(a, b, transform))
     }
+
+    pub fn with_animation(self, animation: Animation) -> Self {
+        Self { animation: Some(animation), ..self }
+    }
 }
 
 impl Pattern for GradientPattern {
@@ -122,7 +186,7 @@ impl Pattern for GradientPattern {
     }
 
     fn box_eq(&self, other: &dyn Any) -> bool {
-        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -137,13 +201,357 @@ impl Pattern for GradientPattern {
         self.inverse_transform
     }
 
+    fn animation(&self) -> Option<&Animation> {
+        self.animation.as_ref()
+    }
+
     fn inner_pattern_at(&self, pattern_point: Tuple) -> Color {
         let distance = self.b - self.a;
-        let fraction = pattern_point.x.fract();
+        // fract() keeps the sign, which would extrapolate past `a` for negative x
+        let fraction = pattern_point.x - pattern_point.x.floor();
         self.a + distance * fraction
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingPattern {
+    a: Color,
+    b: Color,
+    transform: Matrix,
+    inverse_transform: Matrix,
+    animation: Option<Animation>
+}
+
+impl RingPattern {
+    pub fn new(a: Color, b: Color, transform: Option<Matrix>) -> Self {
+        Self {
+            a,
+            b,
+            transform: transform.unwrap_or_default(),
+            inverse_transform: inverse_transform_parameter(transform),
+            animation: None
+        }
+    }
+
+    pub fn new_boxed(a: Color, b: Color, transform: Option<Matrix>) -> BoxPattern {
+        Box::new(Self::new(a, b, transform))
+    }
+
+    pub fn with_animation(self, animation: Animation) -> Self {
+        Self { animation: Some(animation), ..self }
+    }
+}
+
+impl Pattern for RingPattern {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transform
+    }
+
+    fn inverse_transformation(&self) -> Matrix {
+        self.inverse_transform
+    }
+
+    fn animation(&self) -> Option<&Animation> {
+        self.animation.as_ref()
+    }
+
+    fn inner_pattern_at(&self, pattern_point: Tuple) -> Color {
+        let distance = (pattern_point.x * pattern_point.x + pattern_point.z * pattern_point.z).sqrt();
+        if (distance.floor() as i64).rem_euclid(2) == 0 { self.a } else { self.b }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckerPattern {
+    a: Color,
+    b: Color,
+    transform: Matrix,
+    inverse_transform: Matrix,
+    animation: Option<Animation>
+}
+
+impl CheckerPattern {
+    pub fn new(a: Color, b: Color, transform: Option<Matrix>) -> Self {
+        Self {
+            a,
+            b,
+            transform: transform.unwrap_or_default(),
+            inverse_transform: inverse_transform_parameter(transform),
+            animation: None
+        }
+    }
+
+    pub fn new_boxed(a: Color, b: Color, transform: Option<Matrix>) -> BoxPattern {
+        Box::new(Self::new(a, b, transform))
+    }
+
+    pub fn with_animation(self, animation: Animation) -> Self {
+        Self { animation: Some(animation), ..self }
+    }
+}
+
+impl Pattern for CheckerPattern {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transform
+    }
+
+    fn inverse_transformation(&self) -> Matrix {
+        self.inverse_transform
+    }
+
+    fn animation(&self) -> Option<&Animation> {
+        self.animation.as_ref()
+    }
+
+    fn inner_pattern_at(&self, pattern_point: Tuple) -> Color {
+        let sum = pattern_point.x.floor() + pattern_point.y.floor() + pattern_point.z.floor();
+        if (sum as i64).rem_euclid(2) == 0 { self.a } else { self.b }
+    }
+}
+
+// How a point in pattern space is flattened to (u, v) in 0..1 for texture lookup
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UvMapping {
+    // Latitude and longitude around the unit sphere
+    Spherical,
+    // Tiles the xz plane once per unit
+    Planar,
+    // Around the y axis, repeating once per unit of height
+    Cylindrical,
+    // Each face of the [-1, 1] cube shows the whole texture
+    CubeFace
+}
+
+impl UvMapping {
+    pub fn uv(&self, point: Tuple) -> (Scalar, Scalar) {
+        let wrap = |value: Scalar, period: Scalar| value.rem_euclid(period) / period;
+        match self {
+            UvMapping::Spherical => Sphere::uv_map(point),
+            UvMapping::Planar => (wrap(point.x, 1.), wrap(point.z, 1.)),
+            UvMapping::Cylindrical => {
+                let theta = point.x.atan2(point.z);
+                (1. - (theta / (2. * PI) + 0.5), wrap(point.y, 1.))
+            }
+            UvMapping::CubeFace => {
+                let (x, y, z) = (point.x, point.y, point.z);
+                let coord = x.abs().max(y.abs()).max(z.abs());
+                if coord == x {
+                    (wrap(1. - z, 2.), wrap(y + 1., 2.))
+                } else if coord == -x {
+                    (wrap(z + 1., 2.), wrap(y + 1., 2.))
+                } else if coord == y {
+                    (wrap(x + 1., 2.), wrap(1. - z, 2.))
+                } else if coord == -y {
+                    (wrap(x + 1., 2.), wrap(z + 1., 2.))
+                } else if coord == z {
+                    (wrap(x + 1., 2.), wrap(y + 1., 2.))
+                } else {
+                    (wrap(1. - x, 2.), wrap(y + 1., 2.))
+                }
+            }
+        }
+    }
+}
+
+// Shared, immutable image behind a texture; textures compare by identity like animations do
+#[derive(Clone)]
+pub struct Texture(Arc<Canvas>);
+
+impl Texture {
+    pub fn new(canvas: Canvas) -> Self {
+        Texture(Arc::new(canvas))
+    }
+
+    // Nearest neighbour lookup; v runs up the image, so rows are flipped
+    fn sample(&self, u: Scalar, v: Scalar) -> Color {
+        let canvas = &self.0;
+        let x = (u.clamp(0., 1.) * (canvas.width - 1) as Scalar).round() as usize;
+        let y = ((1. - v.clamp(0., 1.)) * (canvas.height - 1) as Scalar).round() as usize;
+        canvas.pixel_at(x, y)
+    }
+}
+
+impl fmt::Debug for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Texture({}x{})", self.0.width, self.0.height)
+    }
+}
+
+impl PartialEq for Texture {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureMap {
+    mapping: UvMapping,
+    texture: Texture,
+    transform: Matrix,
+    inverse_transform: Matrix,
+    animation: Option<Animation>
+}
+
+impl TextureMap {
+    // Sampling needs at least one pixel, so an empty texture is refused
+    pub fn new(mapping: UvMapping, texture: Canvas, transform: Option<Matrix>) -> Result<Self, String> {
+        if texture.width == 0 || texture.height == 0 {
+            return Err("A texture needs at least one pixel".to_string());
+        }
+        Ok(Self {
+            mapping,
+            texture: Texture::new(texture),
+            transform: transform.unwrap_or_default(),
+            inverse_transform: inverse_transform_parameter(transform),
+            animation: None
+        })
+    }
+
+    pub fn new_boxed(mapping: UvMapping, texture: Canvas, transform: Option<Matrix>) -> Result<BoxPattern, String> {
+        Ok(Box::new(Self::new(mapping, texture, transform)?))
+    }
+
+    pub fn from_ppm(mapping: UvMapping, ppm: &str, transform: Option<Matrix>) -> Result<Self, String> {
+        Self::new(mapping, Canvas::from_ppm(ppm)?, transform)
+    }
+
+    pub fn with_animation(self, animation: Animation) -> Self {
+        Self { animation: Some(animation), ..self }
+    }
+}
+
+impl Pattern for TextureMap {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transform
+    }
+
+    fn inverse_transformation(&self) -> Matrix {
+        self.inverse_transform
+    }
+
+    fn animation(&self) -> Option<&Animation> {
+        self.animation.as_ref()
+    }
+
+    fn inner_pattern_at(&self, pattern_point: Tuple) -> Color {
+        let (u, v) = self.mapping.uv(pattern_point);
+        self.texture.sample(u, v)
+    }
+}
+
+// Jitters the lookup point by Perlin noise before handing it to the wrapped pattern, which still
+// applies its own transform and animation; scale is the largest displacement along each axis
+#[derive(Debug, Clone)]
+pub struct PerturbedPattern {
+    pattern: BoxPattern,
+    scale: Scalar,
+    transform: Matrix,
+    inverse_transform: Matrix,
+    animation: Option<Animation>
+}
+
+impl PartialEq for PerturbedPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.box_eq(other.pattern.as_any()) &&
+            self.scale == other.scale &&
+            self.transform == other.transform &&
+            self.animation == other.animation
+    }
+}
+
+impl PerturbedPattern {
+    pub fn new(pattern: BoxPattern, scale: Scalar, transform: Option<Matrix>) -> Self {
+        Self {
+            pattern,
+            scale,
+            transform: transform.unwrap_or_default(),
+            inverse_transform: inverse_transform_parameter(transform),
+            animation: None
+        }
+    }
+
+    pub fn new_boxed(pattern: BoxPattern, scale: Scalar, transform: Option<Matrix>) -> BoxPattern {
+        Box::new(Self::new(pattern, scale, transform))
+    }
+
+    pub fn with_animation(self, animation: Animation) -> Self {
+        Self { animation: Some(animation), ..self }
+    }
+}
+
+impl Pattern for PerturbedPattern {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transform
+    }
+
+    fn inverse_transformation(&self) -> Matrix {
+        self.inverse_transform
+    }
+
+    fn animation(&self) -> Option<&Animation> {
+        self.animation.as_ref()
+    }
+
+    fn inner_pattern_at(&self, pattern_point: Tuple) -> Color {
+        self.inner_pattern_at_time(pattern_point, 0.)
+    }
+
+    fn inner_pattern_at_time(&self, pattern_point: Tuple, time: Scalar) -> Color {
+        let (x, y, z) = (pattern_point.x, pattern_point.y, pattern_point.z);
+        // Offset the lattice per axis so the three displacements are not identical
+        let offset = Tuple::vector(perlin3(x, y, z), perlin3(x, y, z + 1.7), perlin3(x, y, z + 3.4)) * self.scale;
+        self.pattern.pattern_at_time(pattern_point + offset, time)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,7 +585,7 @@ mod tests {
         }
 
         fn box_eq(&self, other: &dyn Any) -> bool {
-            other.downcast_ref::<Self>().map_or(false, |a| self == a)
+            other.downcast_ref::<Self>().is_some_and(|a| self == a)
         }
 
         fn as_any(&self) -> &dyn Any {
@@ -299,6 +707,216 @@ mod tests {
         assert_eq!(pattern.inner_pattern_at(Tuple::point(0.25, 0., 0.)), Color::new(0.75, 0.75, 0.75));
         assert_eq!(pattern.inner_pattern_at(Tuple::point(0.5, 0., 0.)), Color::new(0.5, 0.5, 0.5));
         assert_eq!(pattern.inner_pattern_at(Tuple::point(0.75, 0., 0.)), Color::new(0.25, 0.25, 0.25));
-        assert_eq!(pattern.inner_pattern_at(Tuple::point(1. - EPSILON, 0., 0.)), BLACK);
+        let near_end = pattern.inner_pattern_at(Tuple::point(1. - EPSILON, 0., 0.));
+        #[cfg(not(feature = "f32"))]
+        assert_eq!(near_end, BLACK);
+        // Exactly EPSILON away from black, so under f32 rounding alone decides whether approx_eq holds
+        #[cfg(feature = "f32")]
+        assert!([near_end.r, near_end.g, near_end.b].iter().all(|&c| crate::approx_eq_eps(c, 0., 2. * EPSILON)));
+    }
+
+    #[test]
+    fn gradient_repeats_for_negative_x() {
+        let pattern = GradientPattern::new(WHITE, BLACK, None);
+
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(-0.75, 0., 0.)), Color::new(0.75, 0.75, 0.75));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(-1., 0., 0.)), WHITE);
+    }
+
+    #[test]
+    fn gradient_with_object_and_pattern_transformation() {
+        let o = Sphere::new(None, Some(Matrix::scaling(2., 2., 2.)));
+        let pattern = GradientPattern::new_boxed(WHITE, BLACK, Some(Matrix::translation(0.5, 0., 0.)));
+
+        assert_eq!(pattern.pattern_at_shape(&o, Tuple::point(2.5, 3., 3.5)), Color::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn ring_extends_in_both_x_and_z() {
+        let pattern = RingPattern::new(WHITE, BLACK, None);
+
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 0.)), WHITE);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(1., 0., 0.)), BLACK);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 1.)), BLACK);
+        // 0.708 is just slightly more than sqrt(2) / 2
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0.708, 0., 0.708)), BLACK);
+    }
+
+    #[test]
+    fn checkers_repeat_in_x() {
+        let pattern = CheckerPattern::new(WHITE, BLACK, None);
+
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 0.)), WHITE);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0.99, 0., 0.)), WHITE);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(1.01, 0., 0.)), BLACK);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(-0.5, 0., 0.)), BLACK);
+    }
+
+    #[test]
+    fn checkers_repeat_in_y() {
+        let pattern = CheckerPattern::new(WHITE, BLACK, None);
+
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0.99, 0.)), WHITE);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 1.01, 0.)), BLACK);
+    }
+
+    #[test]
+    fn checkers_repeat_in_z() {
+        let pattern = CheckerPattern::new(WHITE, BLACK, None);
+
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 0.99)), WHITE);
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 1.01)), BLACK);
+    }
+
+    #[test]
+    fn checkers_with_object_and_pattern_transformation() {
+        let o = Sphere::new(None, Some(Matrix::scaling(2., 2., 2.)));
+        let pattern = CheckerPattern::new_boxed(WHITE, BLACK, Some(Matrix::translation(0.5, 0., 0.)));
+
+        assert_eq!(pattern.pattern_at_shape(&o, Tuple::point(1.5, 0., 0.)), WHITE);
+        assert_eq!(pattern.pattern_at_shape(&o, Tuple::point(3.5, 0., 0.)), BLACK);
+    }
+
+    #[test]
+    fn animated_stripes_move_with_time() {
+        let o = Sphere::new(None, None);
+        let pattern = StripePattern::new(WHITE, BLACK, None)
+            .with_animation(Animation::new(|time| Matrix::translation(time, 0., 0.)));
+        let p = Tuple::point(1.25, 0., 0.);
+
+        assert_eq!(pattern.pattern_at_shape_at_time(&o, p, 0.), BLACK);
+        assert_eq!(pattern.pattern_at_shape_at_time(&o, p, 0.5), WHITE);
+        assert_eq!(StripePattern::new(WHITE, BLACK, None).pattern_at_shape_at_time(&o, p, 0.5), BLACK);
+    }
+
+    // 3x3 texture with a distinct gray level per pixel, row 0 at the top
+    fn gray_texture() -> Canvas {
+        let mut c = Canvas::new(3, 3);
+        for y in 0..3 {
+            for x in 0..3 {
+                let level = (y * 3 + x) as Scalar / 10.;
+                c.write_pixel(x, y, Color::new(level, level, level));
+            }
+        }
+        c
+    }
+
+    fn gray(level: Scalar) -> Color {
+        Color::new(level, level, level)
+    }
+
+    #[test]
+    fn spherical_mapping_at_poles_and_equator() {
+        let pattern = TextureMap::new(UvMapping::Spherical, gray_texture(), None).unwrap();
+
+        assert_eq!(UvMapping::Spherical.uv(Tuple::point(0., 0., -1.)), (0., 0.5));
+        assert_eq!(UvMapping::Spherical.uv(Tuple::point(1., 0., 0.)), (0.25, 0.5));
+        assert_eq!(UvMapping::Spherical.uv(Tuple::point(0., 0., 1.)), (0.5, 0.5));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 1., 0.)), gray(0.));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., -1., 0.)), gray(0.6));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 1.)), gray(0.4));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn planar_mapping_repeats_every_unit() {
+        let pattern = TextureMap::new(UvMapping::Planar, gray_texture(), None).unwrap();
+
+        assert_eq!(UvMapping::Planar.uv(Tuple::point(0.25, 0., 0.5)), (0.25, 0.5));
+        assert_eq!(UvMapping::Planar.uv(Tuple::point(-0.25, 0.5, -0.75)), (0.75, 0.25));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0.9, 5., 0.9)), gray(0.2));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(1.9, 0., 0.9)), gray(0.2));
+    }
+
+    #[test]
+    fn cylindrical_mapping_wraps_around_y() {
+        assert_eq!(UvMapping::Cylindrical.uv(Tuple::point(0., 0., -1.)), (0., 0.));
+        assert_eq!(UvMapping::Cylindrical.uv(Tuple::point(0., 0.5, 1.)), (0.5, 0.5));
+        assert_eq!(UvMapping::Cylindrical.uv(Tuple::point(1., 1.25, 0.)), (0.25, 0.25));
+    }
+
+    #[test]
+    fn cube_face_mapping_spans_each_face() {
+        assert_eq!(UvMapping::CubeFace.uv(Tuple::point(-0.5, 0.5, 1.)), (0.25, 0.75));
+        assert_eq!(UvMapping::CubeFace.uv(Tuple::point(0.5, -0.5, -1.)), (0.25, 0.25));
+        assert_eq!(UvMapping::CubeFace.uv(Tuple::point(1., 0.5, -0.5)), (0.75, 0.75));
+        assert_eq!(UvMapping::CubeFace.uv(Tuple::point(-0.5, 1., -0.5)), (0.25, 0.75));
+    }
+
+    #[test]
+    fn texture_map_from_ppm() {
+        let ppm = "P3\n2 2\n255\n255 0 0 0 255 0\n0 0 255 255 255 255\n";
+        let pattern = TextureMap::from_ppm(UvMapping::Planar, ppm, None).unwrap();
+
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0., 0., 0.)), Color::new(0., 0., 1.));
+        assert_eq!(pattern.inner_pattern_at(Tuple::point(0.9, 0., 0.9)), Color::new(0., 1., 0.));
+        assert!(TextureMap::from_ppm(UvMapping::Planar, "P3\n0 0\n255\n", None).is_err());
+        assert!(TextureMap::new(UvMapping::Planar, Canvas::new(0, 4), None).is_err());
+        assert!(TextureMap::new_boxed(UvMapping::Planar, Canvas::new(3, 0), None).is_err());
+    }
+
+    #[test]
+    fn zero_scale_perturbation_matches_child() {
+        let child = CheckerPattern::new_boxed(WHITE, BLACK, Some(Matrix::scaling(0.5, 0.5, 0.5)));
+        let pattern = PerturbedPattern::new(child.clone(), 0., None);
+
+        for i in 0..50 {
+            let t = i as Scalar * 0.137;
+            let p = Tuple::point(t, -t * 0.7, t * 0.3);
+            assert_eq!(pattern.inner_pattern_at(p), child.inner_pattern_at(child.inverse_transformation() * p));
+        }
+    }
+
+    #[test]
+    fn perturbed_pattern_passes_time_to_animated_child() {
+        let o = Sphere::new(None, None);
+        let child = StripePattern::new(WHITE, BLACK, None)
+            .with_animation(Animation::new(|time| Matrix::translation(time, 0., 0.)));
+        let pattern = PerturbedPattern::new(Box::new(child), 0., None);
+        let p = Tuple::point(1.25, 0., 0.);
+
+        assert_eq!(pattern.pattern_at_shape_at_time(&o, p, 0.), BLACK);
+        assert_eq!(pattern.pattern_at_shape_at_time(&o, p, 0.5), WHITE);
+    }
+
+    #[test]
+    fn perturbation_moves_stripe_edges_reproducibly() {
+        let child = StripePattern::new_boxed(WHITE, BLACK, None);
+        let pattern = PerturbedPattern::new(child.clone(), 0.3, None);
+        let points: Vec<Tuple> = (0..100).map(|i| Tuple::point(i as Scalar * 0.05, 0.37, 0.61)).collect();
+        let differing = points.iter().filter(|&&p| pattern.inner_pattern_at(p) != child.inner_pattern_at(p)).count();
+
+        assert!(differing > 0);
+        assert!(points.iter().all(|&p| pattern.inner_pattern_at(p) == pattern.clone().inner_pattern_at(p)));
+    }
+
+    #[test]
+    fn cached_inverses_match_inverting_per_lookup() {
+        let shape_transform = Matrix::translation(0.3, 0., 0.) * Matrix::scaling(2., 1.5, 2.) * Matrix::rotation_y(0.4);
+        let pattern_transform = Matrix::scaling(0.25, 1., 1.) * Matrix::rotation_z(0.2);
+        let object = Sphere::new(None, Some(shape_transform));
+        let pattern = StripePattern::new(WHITE, BLACK, Some(pattern_transform));
+
+        for i in 0..200 {
+            let t = i as Scalar * 0.031;
+            let p = Tuple::point(t - 3., (t * 1.7).sin(), (t * 2.3).cos());
+            let uncached = shape_transform.inverse().unwrap() * p;
+            let uncached = pattern_transform.inverse().unwrap() * uncached;
+            assert_eq!(pattern.pattern_at_shape(&object, p), pattern.inner_pattern_at(uncached));
+        }
+    }
+
+    #[test]
+    fn pattern_lookups_do_not_invert_matrices() {
+        let object = Sphere::new(None, Some(Matrix::scaling(2., 2., 2.)));
+        let pattern = StripePattern::new(WHITE, BLACK, Some(Matrix::translation(0.5, 0., 0.)));
+        let before = crate::matrix::inversions_on_this_thread();
+
+        for i in 0..1000 {
+            pattern.pattern_at_shape(&object, Tuple::point(i as Scalar * 0.01, 0., 0.));
+        }
+
+        assert_eq!(crate::matrix::inversions_on_this_thread(), before);
+        Sphere::new(None, Some(Matrix::scaling(3., 3., 3.)));
+        assert_eq!(crate::matrix::inversions_on_this_thread(), before + 1);
+    }
+}
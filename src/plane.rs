@@ -1,15 +1,17 @@
-use super::intersection::{Intersection, Intersections};
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
 use super::material::Material;
 use super::matrix::Matrix;
 use super::ray::Ray;
-use super::shape::{inverse_transform_parameter, BoxShape, Shape};
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
 use super::tuple::{Tuple, VECTOR_Y_UP};
 use std::any::Any;
+use super::Scalar;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Plane {
-    inverse_transform: Matrix,
-    transform: Matrix,
+    id: ShapeId,
+    transforms: Transforms,
     material: Material,
 }
 
@@ -19,20 +21,20 @@ impl Shape for Plane {
     }
 
     fn box_eq(&self, other: &dyn Any) -> bool {
-        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
     }
 
     fn as_any(&self) -> &dyn Any {
         self
     }
 
-    fn inner_intersect(&self, object_ray: Ray) -> Intersections {
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
         if super::approx_eq(0., object_ray.direction.y) {
-            return Intersections::new(vec![]);
+            return vec![];
         }
         let t = -object_ray.origin.y / object_ray.direction.y;
 
-        Intersections::new(vec![Intersection::new(t, Box::new(self.clone()))])
+        vec![IntersectionRef::new(t, self)]
     }
 
     fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
@@ -43,24 +45,51 @@ impl Shape for Plane {
         &self.material
     }
 
-    fn transformation(&self) -> Matrix {
-        self.transform
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
     }
 
-    fn inverse_transformation(&self) -> Matrix {
-        self.inverse_transform
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Tuple::point(Scalar::NEG_INFINITY, 0., Scalar::NEG_INFINITY),
+            Tuple::point(Scalar::INFINITY, 0., Scalar::INFINITY))
     }
 }
 
 impl Plane {
     pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
         Self {
-            transform: transform.unwrap_or_default(),
-            inverse_transform: inverse_transform_parameter(transform),
+            transforms: Transforms::new(transform),
             material: material.unwrap_or_default(),
+            ..Default::default()
         }
     }
+
+    pub fn new_boxed(material: Option<Material>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Plane::new(material, transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Plane::default())
+    }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,4 +147,19 @@ mod tests {
         assert_eq!(xs[0].t, 1.);
         assert_eq!(&xs[0].object, &box_plane(p));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn boxed_constructors_match_plain_ones() {
+        assert_eq!(&Plane::default_boxed(), &box_plane(Plane::new(None, None)));
+        let tr = Matrix::translation(0., -1., 0.);
+        assert_eq!(Plane::new_boxed(None, Some(tr)).transformation(), tr);
+    }
+
+    #[test]
+    fn plane_is_unbounded_except_in_y() {
+        let bounds = Plane::default().bounds();
+
+        assert_eq!(bounds.min, Tuple::point(Scalar::NEG_INFINITY, 0., Scalar::NEG_INFINITY));
+        assert_eq!(bounds.max, Tuple::point(Scalar::INFINITY, 0., Scalar::INFINITY));
+    }
+}
@@ -1,18 +1,116 @@
 use super::tuple::Tuple;
 use super::shape::BoxShape;
+use super::intersection::ObjectKey;
+use super::Scalar;
 
+#[derive(Debug, Clone)]
 pub struct PrecomputedData {
-    pub t: f64,
+    pub t: Scalar,
     pub object: BoxShape,
+    // See Intersection::instance
+    pub instance: Option<usize>,
     pub point: Tuple,
     pub eyev: Tuple,
     pub normalv: Tuple,
     pub inside: bool,
-    pub over_point: Tuple
+    pub over_point: Tuple,
+    pub under_point: Tuple,
+    pub reflectv: Tuple,
+    pub n1: Scalar,
+    pub n2: Scalar
 }
 
 impl PrecomputedData {
-    pub fn new(t: f64, object: BoxShape, point: Tuple, eyev: Tuple, normalv: Tuple, inside: bool, over_point: Tuple) -> Self {
-        Self { t, object, point, eyev, normalv, inside, over_point }
+    // One line per field, for inspecting the shading state at a hit
+    pub fn debug_summary(&self) -> String {
+        // Adding 0 turns -0 into 0, which is just noise here
+        let xyz = |t: Tuple| format!("({}, {}, {})", t.x + 0., t.y + 0., t.z + 0.);
+        format!("t: {}\npoint: {}\neyev: {}\nnormalv: {}\ninside: {}\nover_point: {}\nunder_point: {}\nreflectv: {}\nn1: {}\nn2: {}",
+                self.t, xyz(self.point), xyz(self.eyev), xyz(self.normalv), self.inside,
+                xyz(self.over_point), xyz(self.under_point), xyz(self.reflectv), self.n1, self.n2)
     }
-}
\ No newline at end of file
+
+    pub fn key(&self) -> ObjectKey {
+        (self.object.id(), self.instance)
+    }
+
+    // Schlick's approximation of the Fresnel reflectance at the hit
+    pub fn schlick(&self) -> Scalar {
+        let mut cos = self.eyev.dot(&self.normalv);
+        if self.n1 > self.n2 {
+            let n = self.n1 / self.n2;
+            let sin2_t = n * n * (1. - cos * cos);
+            if sin2_t > 1. {
+                return 1.;
+            }
+            cos = (1. - sin2_t).sqrt();
+        }
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1. - r0) * (1. - cos).powi(5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intersection::sort_refs;
+    use crate::material::Material;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+
+    fn glass_sphere() -> BoxShape {
+        let m = Material { transparency: 1., refractive_index: 1.5, ..Default::default() };
+        Sphere::new_boxed(Some(m), None)
+    }
+
+    #[test]
+    fn schlick_under_total_internal_reflection() {
+        let shape = glass_sphere();
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0., 0., pv), Tuple::vector(0., 1., 0.));
+        let mut xs = shape.intersect_ref(r);
+        sort_refs(&mut xs);
+        let comps = xs[1].into_owned().prepare_computations_with(r, &xs);
+
+        assert!(crate::approx_eq(comps.t, pv));
+        assert_eq!(comps.schlick(), 1.);
+    }
+
+    #[test]
+    fn schlick_with_perpendicular_viewing_angle() {
+        let shape = glass_sphere();
+        let r = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.));
+        let mut xs = shape.intersect_ref(r);
+        sort_refs(&mut xs);
+        let comps = xs[1].into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!(comps.t, 1.);
+        assert!(crate::approx_eq(comps.schlick(), 0.04));
+    }
+
+    #[test]
+    fn schlick_with_small_angle_and_n2_greater_than_n1() {
+        let shape = glass_sphere();
+        let r = Ray::new(Tuple::point(0., 0.99, -2.), Tuple::vector(0., 0., 1.));
+        let mut xs = shape.intersect_ref(r);
+        sort_refs(&mut xs);
+        let comps = xs[0].into_owned().prepare_computations_with(r, &xs);
+
+        assert!((comps.schlick() - 0.48873).abs() < 0.0001);
+    }
+
+    #[test]
+    fn debug_summary_lists_shading_state() {
+        let shape = glass_sphere();
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let mut xs = shape.intersect_ref(r);
+        sort_refs(&mut xs);
+        let comps = xs[0].into_owned().prepare_computations_with(r, &xs);
+        let summary = comps.clone().debug_summary();
+
+        assert!(summary.starts_with("t: 4\npoint: (0, 0, -1)\neyev: (0, 0, -1)\nnormalv: (0, 0, -1)\ninside: false\n"));
+        assert!(summary.contains("\nreflectv: (0, 0, -1)\n"));
+        assert!(summary.ends_with("n1: 1\nn2: 1.5"));
+        assert_eq!(summary.lines().count(), 10);
+    }
+}
@@ -1,5 +1,6 @@
 use super::matrix::Matrix;
 use super::tuple::Tuple;
+use super::Scalar;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Ray {
@@ -14,7 +15,7 @@ impl Ray {
         Ray { origin, direction }
     }
 
-    pub fn position(&self, t: f64) -> Tuple {
+    pub fn position(&self, t: Scalar) -> Tuple {
         self.origin + self.direction * t
     }
 
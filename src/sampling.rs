@@ -0,0 +1,67 @@
+use super::consts::PI;
+use super::Scalar;
+
+// Small splitmix64 generator; stochastic effects derive their streams from the world seed so a
+// render is reproducible from that one number
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    // Independent stream for one pixel, so the result does not depend on render order
+    pub fn for_pixel(seed: u64, px: usize, py: usize) -> Self {
+        let mut mixer = Rng(seed ^ (px as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let pixel_seed = mixer.next_u64() ^ (py as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        Rng(Rng(pixel_seed).next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_scalar(&mut self) -> Scalar {
+        let bits = Scalar::MANTISSA_DIGITS;
+        (self.next_u64() >> (64 - bits)) as Scalar / (1u64 << bits) as Scalar
+    }
+
+    // Uniform point on the unit disk
+    pub fn unit_disk(&mut self) -> (Scalar, Scalar) {
+        let r = self.next_scalar().sqrt();
+        let theta = 2. * PI * self.next_scalar();
+        (r * theta.cos(), r * theta.sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let xs: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+
+        assert_eq!(xs, (0..4).map(|_| b.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(xs, (0..4).map(|_| c.next_u64()).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn samples_stay_in_range() {
+        let mut rng = Rng::for_pixel(7, 3, 5);
+        for _ in 0..1000 {
+            assert!((0. ..1.).contains(&rng.next_scalar()));
+            let (x, y) = rng.unit_disk();
+            assert!(x * x + y * y <= 1.);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use super::camera::Camera;
+use super::color::Color;
+use super::cone::Cone;
+use super::cube::Cube;
+use super::cylinder::Cylinder;
+use super::group::Group;
+use super::light::{BoxLight, DirectionalLight, PointLight};
+use super::material::Material;
+use super::matrix::{Matrix, IDENTITY_MATRIX};
+use super::plane::Plane;
+use super::shape::BoxShape;
+use super::sphere::Sphere;
+use super::tuple::Tuple;
+use super::world::World;
+use super::Scalar;
+use std::collections::HashMap;
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Yaml, YamlLoader};
+
+// Scene description format:
+//
+//   camera: { width: 100, height: 50, field-of-view: 1.047, from: [0, 1.5, -5], to: [0, 1, 0], up: [0, 1, 0] }
+//   lights:
+//     - { at: [-10, 10, -10], intensity: [1, 1, 1] }
+//     - { type: directional, direction: [0, -1, 0], intensity: [0.2, 0.2, 0.2] }
+//   shapes:
+//     - type: sphere
+//       transform: [[scale, 0.5, 0.5, 0.5], [translate, 0, 1, 0]]
+//       material: { color: [1, 0, 0], diffuse: 0.7 }
+//
+// Transforms are applied in the order they are listed. The list based format from The Ray Tracer
+// Challenge (`- add: camera`, `- add: sphere`, `- define: name`) is accepted as well. JSON is valid
+// YAML, so the same structure written as JSON loads too.
+pub fn load_scene(yaml: &str) -> Result<(Camera, World), String> {
+    let docs = YamlLoader::load_from_str(yaml).map_err(|e| format!("invalid YAML: {}", e))?;
+    let doc = docs.first().ok_or_else(|| "empty scene".to_string())?;
+    let converted;
+    let doc = match doc {
+        Yaml::Array(entries) => {
+            converted = from_book_format(entries)?;
+            &converted
+        }
+        _ => doc
+    };
+    let camera = parse_camera(&doc["camera"])?;
+    let lights = match &doc["lights"] {
+        Yaml::BadValue => vec![],
+        lights => list(lights, "lights")?.iter().enumerate()
+            .map(|(i, l)| parse_light(l).map_err(|e| format!("light {}: {}", i, e)))
+            .collect::<Result<_, _>>()?
+    };
+    let shapes = match &doc["shapes"] {
+        Yaml::BadValue => vec![],
+        shapes => parse_shapes(shapes)?
+    };
+    Ok((camera, World::with_lights(lights, shapes)))
+}
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.to_string())
+}
+
+// Rewrites the book format into the map format, substituting defined materials and transforms
+// wherever their names are used
+fn from_book_format(entries: &[Yaml]) -> Result<Yaml, String> {
+    let mut defines = HashMap::new();
+    let mut scene = Hash::new();
+    let (mut lights, mut shapes) = (vec![], vec![]);
+    for (i, entry) in entries.iter().enumerate() {
+        let at = |e: String| format!("entry {}: {}", i, e);
+        if let Some(name) = entry["define"].as_str() {
+            let value = resolve_define(entry, &defines).map_err(at)?;
+            defines.insert(name.to_string(), value);
+            continue;
+        }
+        match entry["add"].as_str() {
+            Some("camera") => { scene.insert(key("camera"), entry.clone()); }
+            Some("light") => lights.push(entry.clone()),
+            Some(_) => shapes.push(book_shape(entry, &defines).map_err(at)?),
+            None => return Err(at("expected add or define".to_string()))
+        }
+    }
+    scene.insert(key("lights"), Yaml::Array(lights));
+    scene.insert(key("shapes"), Yaml::Array(shapes));
+    Ok(Yaml::Hash(scene))
+}
+
+fn lookup<'a>(name: &str, defines: &'a HashMap<String, Yaml>) -> Result<&'a Yaml, String> {
+    defines.get(name).ok_or(format!("undefined name \"{}\"", name))
+}
+
+// Materials can extend an earlier definition, overriding some of its fields
+fn resolve_define(entry: &Yaml, defines: &HashMap<String, Yaml>) -> Result<Yaml, String> {
+    match (&entry["value"], entry["extend"].as_str()) {
+        (Yaml::Hash(fields), Some(base)) => match lookup(base, defines)? {
+            Yaml::Hash(base) => {
+                let mut merged = base.clone();
+                merged.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                Ok(Yaml::Hash(merged))
+            }
+            _ => Err(format!("\"{}\" is not a material", base))
+        },
+        (value @ Yaml::Hash(_), None) => Ok(value.clone()),
+        (Yaml::Array(steps), _) => expand_transforms(steps, defines),
+        _ => Err("define needs a map or list value".to_string())
+    }
+}
+
+// Defined transforms are stored expanded, so one level of substitution is enough
+fn expand_transforms(steps: &[Yaml], defines: &HashMap<String, Yaml>) -> Result<Yaml, String> {
+    let mut expanded = vec![];
+    for step in steps {
+        match step {
+            Yaml::String(name) => match lookup(name, defines)? {
+                Yaml::Array(steps) => expanded.extend(steps.iter().cloned()),
+                _ => return Err(format!("\"{}\" is not a transform", name))
+            },
+            step => expanded.push(step.clone())
+        }
+    }
+    Ok(Yaml::Array(expanded))
+}
+
+fn book_shape(entry: &Yaml, defines: &HashMap<String, Yaml>) -> Result<Yaml, String> {
+    let mut shape = Hash::new();
+    for (k, v) in entry.as_hash().ok_or("shape must be a map")? {
+        let value = match (k.as_str(), v) {
+            (Some("add"), _) => {
+                shape.insert(key("type"), v.clone());
+                continue;
+            }
+            (Some("material"), Yaml::String(name)) => lookup(name, defines)?.clone(),
+            (Some("transform"), Yaml::Array(steps)) => expand_transforms(steps, defines)?,
+            (Some("children"), Yaml::Array(children)) => Yaml::Array(
+                children.iter().map(|c| book_shape(c, defines)).collect::<Result<_, _>>()?),
+            _ => v.clone()
+        };
+        shape.insert(k.clone(), value);
+    }
+    Ok(Yaml::Hash(shape))
+}
+
+fn parse_camera(camera: &Yaml) -> Result<Camera, String> {
+    if camera.is_badvalue() {
+        return Err("missing camera".to_string());
+    }
+    let field = |key: &str| -> Result<&Yaml, String> {
+        match &camera[key] {
+            Yaml::BadValue => Err(format!("camera: missing {}", key)),
+            value => Ok(value)
+        }
+    };
+    let size = |key: &str| -> Result<usize, String> {
+        match field(key)?.as_i64() {
+            Some(n) if n > 0 => Ok(n as usize),
+            _ => Err(format!("camera: {} must be a positive integer", key))
+        }
+    };
+    let hsize = size("width")?;
+    let vsize = size("height")?;
+    let field_of_view = number(field("field-of-view")?).ok_or("camera: field-of-view must be a number")?;
+    let from = triple(field("from")?).map_err(|e| format!("camera: from {}", e))?;
+    let to = triple(field("to")?).map_err(|e| format!("camera: to {}", e))?;
+    let up = triple(field("up")?).map_err(|e| format!("camera: up {}", e))?;
+    let transform = Matrix::view_transform(
+        Tuple::point(from.0, from.1, from.2), Tuple::point(to.0, to.1, to.2), Tuple::vector(up.0, up.1, up.2));
+    Camera::try_new(hsize, vsize, field_of_view, Some(transform)).map_err(|e| format!("camera: {}", e))
+}
+
+fn parse_light(light: &Yaml) -> Result<BoxLight, String> {
+    let intensity = match &light["intensity"] {
+        Yaml::BadValue => Color::new(1., 1., 1.),
+        value => color(value).map_err(|e| format!("intensity {}", e))?
+    };
+    match light["type"].as_str().unwrap_or("point") {
+        "point" => {
+            let (x, y, z) = triple(&light["at"]).map_err(|e| format!("at {}", e))?;
+            Ok(PointLight::new_boxed(Tuple::point(x, y, z), intensity))
+        }
+        "directional" => {
+            let (x, y, z) = triple(&light["direction"]).map_err(|e| format!("direction {}", e))?;
+            Ok(Box::new(DirectionalLight::new(Tuple::vector(x, y, z), intensity)))
+        }
+        other => Err(format!("unknown type \"{}\"", other))
+    }
+}
+
+fn parse_shapes(shapes: &Yaml) -> Result<Vec<BoxShape>, String> {
+    list(shapes, "shapes")?.iter().enumerate()
+        .map(|(i, s)| parse_shape(s).map_err(|e| format!("shape {}: {}", i, e)))
+        .collect()
+}
+
+fn parse_shape(shape: &Yaml) -> Result<BoxShape, String> {
+    let transform = match &shape["transform"] {
+        Yaml::BadValue => None,
+        transforms => Some(parse_transform(transforms)?)
+    };
+    let kind = shape["type"].as_str().ok_or("missing type")?;
+    if kind == "group" {
+        let children = match &shape["children"] {
+            Yaml::BadValue => vec![],
+            children => parse_shapes(children)?
+        };
+        return Ok(Group::new_boxed(transform, children));
+    }
+    let material = match &shape["material"] {
+        Yaml::BadValue => None,
+        material => Some(parse_material(material)?)
+    };
+    match kind {
+        "sphere" => Ok(Sphere::new_boxed(material, transform)),
+        "plane" => Ok(Plane::new_boxed(material, transform)),
+        "cube" => Ok(Cube::new_boxed(material, transform)),
+        "cylinder" => {
+            let (min, max, closed) = truncation(shape)?;
+            Ok(Box::new(Cylinder::new(material, transform).truncated(min, max, closed)))
+        }
+        "cone" => {
+            let (min, max, closed) = truncation(shape)?;
+            Ok(Box::new(Cone::new(material, transform).truncated(min, max, closed)))
+        }
+        other => Err(format!("unknown type \"{}\"", other))
+    }
+}
+
+fn truncation(shape: &Yaml) -> Result<(Scalar, Scalar, bool), String> {
+    let bound = |key: &str, default: Scalar| match &shape[key] {
+        Yaml::BadValue => Ok(default),
+        value => number(value).ok_or(format!("{} must be a number", key))
+    };
+    let closed = match &shape["closed"] {
+        Yaml::BadValue => false,
+        value => value.as_bool().ok_or("closed must be true or false")?
+    };
+    Ok((bound("min", Scalar::NEG_INFINITY)?, bound("max", Scalar::INFINITY)?, closed))
+}
+
+fn parse_transform(transforms: &Yaml) -> Result<Matrix, String> {
+    list(transforms, "transform")?.iter().enumerate().try_fold(IDENTITY_MATRIX, |matrix, (i, step)| {
+        parse_transform_step(step)
+            .map(|t| t * matrix)
+            .map_err(|e| format!("transform {}: {}", i, e))
+    })
+}
+
+fn parse_transform_step(step: &Yaml) -> Result<Matrix, String> {
+    let items = step.as_vec().ok_or("expected a list like [translate, x, y, z]")?;
+    let (name, args) = items.split_first().ok_or("empty transform")?;
+    let name = name.as_str().ok_or("transform name must be a string")?;
+    let args = args.iter().map(number).collect::<Option<Vec<Scalar>>>()
+        .ok_or(format!("{} arguments must be numbers", name))?;
+    match (name, args.as_slice()) {
+        ("translate", &[x, y, z]) => Ok(Matrix::translation(x, y, z)),
+        ("scale", &[x, y, z]) => Ok(Matrix::scaling(x, y, z)),
+        ("rotate-x", &[r]) => Ok(Matrix::rotation_x(r)),
+        ("rotate-y", &[r]) => Ok(Matrix::rotation_y(r)),
+        ("rotate-z", &[r]) => Ok(Matrix::rotation_z(r)),
+        ("shear", &[xy, xz, yx, yz, zx, zy]) => Ok(Matrix::shearing(xy, xz, yx, yz, zx, zy)),
+        ("translate", _) | ("scale", _) => Err(format!("{} takes 3 arguments, got {}", name, args.len())),
+        ("rotate-x", _) | ("rotate-y", _) | ("rotate-z", _) => Err(format!("{} takes 1 argument, got {}", name, args.len())),
+        ("shear", _) => Err(format!("shear takes 6 arguments, got {}", args.len())),
+        _ => Err(format!("unknown transform \"{}\"", name))
+    }
+}
+
+fn parse_material(material: &Yaml) -> Result<Material, String> {
+    if material.as_hash().is_none() {
+        return Err("material must be a map".to_string());
+    }
+    let mut m = Material::default();
+    if !material["color"].is_badvalue() {
+        m.color = color(&material["color"]).map_err(|e| format!("material color {}", e))?;
+    }
+    let fields: [(&str, &mut Scalar); 7] = [
+        ("ambient", &mut m.ambient),
+        ("diffuse", &mut m.diffuse),
+        ("specular", &mut m.specular),
+        ("shininess", &mut m.shininess),
+        ("reflective", &mut m.reflective),
+        ("transparency", &mut m.transparency),
+        ("refractive-index", &mut m.refractive_index)
+    ];
+    for (key, field) in fields {
+        match &material[key] {
+            Yaml::BadValue => {}
+            value => *field = number(value).ok_or(format!("material {} must be a number", key))?
+        }
+    }
+    match &material["casts-shadow"] {
+        Yaml::BadValue => {}
+        value => m.casts_shadow = value.as_bool().ok_or("material casts-shadow must be true or false")?
+    }
+    Ok(m)
+}
+
+fn list<'a>(value: &'a Yaml, what: &str) -> Result<&'a Vec<Yaml>, String> {
+    value.as_vec().ok_or(format!("{} must be a list", what))
+}
+
+// YAML distinguishes integers from reals, scenes shouldn't have to
+fn number(value: &Yaml) -> Option<Scalar> {
+    match value {
+        Yaml::Real(_) => value.as_f64().map(|n| n as Scalar),
+        Yaml::Integer(n) => Some(*n as Scalar),
+        _ => None
+    }
+}
+
+fn triple(value: &Yaml) -> Result<(Scalar, Scalar, Scalar), String> {
+    match value.as_vec().map(|v| v.iter().map(number).collect::<Option<Vec<Scalar>>>()) {
+        Some(Some(v)) if v.len() == 3 => Ok((v[0], v[1], v[2])),
+        _ => Err("must be a list of 3 numbers".to_string())
+    }
+}
+
+fn color(value: &Yaml) -> Result<Color, String> {
+    triple(value).map(|(r, g, b)| Color::new(r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::WHITE;
+    use crate::ray::Ray;
+    use crate::world::MAX_REFLECTION_DEPTH;
+
+    const TWO_SPHERES: &str = "
+camera:
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+lights:
+  - at: [-10, 10, -10]
+    intensity: [1, 1, 1]
+shapes:
+  - type: sphere
+    material:
+      color: [0.8, 1.0, 0.6]
+      diffuse: 0.7
+      specular: 0.2
+  - type: sphere
+    transform:
+      - [scale, 0.5, 0.5, 0.5]
+";
+
+    #[test]
+    fn loading_two_sphere_scene() {
+        let (camera, world) = load_scene(TWO_SPHERES).unwrap();
+        let material = Material { color: Color::new(0.8, 1.0, 0.6), diffuse: 0.7, specular: 0.2, ..Default::default() };
+        let expected = World::new(
+            Some(PointLight::new(Tuple::point(-10., 10., -10.), WHITE)),
+            vec![Sphere::new_boxed(Some(material), None),
+                 Sphere::new_boxed(None, Some(Matrix::scaling(0.5, 0.5, 0.5)))]);
+
+        assert_eq!(camera.hsize, 100);
+        assert_eq!(camera.vsize, 50);
+        assert_eq!(camera.transformation(), Matrix::view_transform(Tuple::point(0., 0., -5.), Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.)));
+        assert_eq!(world.lights(), expected.lights());
+        assert_eq!(world.objects(), expected.objects());
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        assert_eq!(world.color_at(r, MAX_REFLECTION_DEPTH), expected.color_at(r, MAX_REFLECTION_DEPTH));
+    }
+
+    #[test]
+    fn transforms_are_applied_in_listed_order() {
+        let scene = "camera: { width: 1, height: 1, field-of-view: 1, from: [0, 0, -1], to: [0, 0, 0], up: [0, 1, 0] }\n\
+                     shapes:\n  - { type: cube, transform: [[scale, 2, 2, 2], [translate, 1, 0, 0]] }\n";
+        let (_, world) = load_scene(scene).unwrap();
+
+        assert_eq!(world.objects()[0].transformation(), Matrix::translation(1., 0., 0.) * Matrix::scaling(2., 2., 2.));
+    }
+
+    #[test]
+    fn unknown_shape_type_is_an_error() {
+        let scene = "camera: { width: 1, height: 1, field-of-view: 1, from: [0, 0, -1], to: [0, 0, 0], up: [0, 1, 0] }\n\
+                     shapes:\n  - { type: sphere }\n  - { type: torus }\n";
+
+        assert_eq!(load_scene(scene).err(), Some("shape 1: unknown type \"torus\"".to_string()));
+    }
+
+    #[test]
+    fn malformed_transform_is_an_error() {
+        let scene = "camera: { width: 1, height: 1, field-of-view: 1, from: [0, 0, -1], to: [0, 0, 0], up: [0, 1, 0] }\n\
+                     shapes:\n  - { type: sphere, transform: [[translate, 1, 2]] }\n";
+
+        assert_eq!(load_scene(scene).err(), Some("shape 0: transform 0: translate takes 3 arguments, got 2".to_string()));
+    }
+
+    #[test]
+    fn loading_json_scene() {
+        let json = r#"{
+            "camera": {"width": 100, "height": 50, "field-of-view": 0.785, "from": [0, 0, -5], "to": [0, 0, 0], "up": [0, 1, 0]},
+            "lights": [{"at": [-10, 10, -10], "intensity": [1, 1, 1]}],
+            "shapes": [
+                {"type": "sphere", "material": {"color": [0.8, 1.0, 0.6], "diffuse": 0.7, "specular": 0.2}},
+                {"type": "sphere", "transform": [["scale", 0.5, 0.5, 0.5]]}
+            ]
+        }"#;
+        let (json_camera, json_world) = load_scene(json).unwrap();
+        let (yaml_camera, yaml_world) = load_scene(TWO_SPHERES).unwrap();
+
+        assert_eq!(json_camera.transformation(), yaml_camera.transformation());
+        assert_eq!(json_world.lights(), yaml_world.lights());
+        assert_eq!(json_world.objects(), yaml_world.objects());
+    }
+
+    const BOOK_SCENE: &str = "
+- add: camera
+  width: 80
+  height: 40
+  field-of-view: 0.785
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+
+- define: white-material
+  value:
+    color: [1, 1, 1]
+    diffuse: 0.7
+    reflective: 0.1
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [0.537, 0.831, 0.914]
+
+- define: standard-transform
+  value:
+    - [translate, 1, -1, 1]
+    - [scale, 0.5, 0.5, 0.5]
+
+- define: large-object
+  value:
+    - standard-transform
+    - [scale, 3.5, 3.5, 3.5]
+
+- add: cube
+  material: blue-material
+  transform:
+    - large-object
+    - [translate, 0, 0, 4]
+
+- add: group
+  children:
+    - add: sphere
+      material: white-material
+";
+
+    #[test]
+    fn loading_book_format_scene() {
+        let (camera, world) = load_scene(BOOK_SCENE).unwrap();
+        let white = Material { diffuse: 0.7, reflective: 0.1, ..Default::default() };
+        let blue = Material { color: Color::new(0.537, 0.831, 0.914), ..white.clone() };
+        let transform = Matrix::translation(0., 0., 4.) * Matrix::scaling(3.5, 3.5, 3.5)
+            * Matrix::scaling(0.5, 0.5, 0.5) * Matrix::translation(1., -1., 1.);
+        let expected = World::new(
+            Some(PointLight::new(Tuple::point(-10., 10., -10.), WHITE)),
+            vec![Cube::new_boxed(Some(blue), Some(transform)),
+                 Group::new_boxed(None, vec![Sphere::new_boxed(Some(white), None)])]);
+
+        assert_eq!((camera.hsize, camera.vsize), (80, 40));
+        assert_eq!(world.lights(), expected.lights());
+        assert_eq!(world.objects(), expected.objects());
+    }
+
+    #[test]
+    fn book_format_with_undefined_name_is_an_error() {
+        let scene = "- add: camera\n  width: 1\n  height: 1\n  field-of-view: 1\n  from: [0, 0, -1]\n  to: [0, 0, 0]\n  up: [0, 1, 0]\n\
+                     - add: sphere\n  material: shiny\n";
+
+        assert_eq!(load_scene(scene).err(), Some("entry 1: undefined name \"shiny\"".to_string()));
+    }
+}
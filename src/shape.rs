@@ -1,29 +1,156 @@
 use std::any::Any;
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::tuple::Tuple;
+use super::bounds::BoundingBox;
 use super::ray::Ray;
-use super::intersection::Intersections;
+use super::intersection::{Intersection, Intersections, IntersectionRef};
 use super::material::Material;
 use super::matrix::{Matrix, IDENTITY_MATRIX};
 
-pub trait Shape: Any + fmt::Debug {
+pub trait Shape: Any + fmt::Debug + Send + Sync {
     fn box_clone(&self) -> BoxShape;
     fn box_eq(&self, other: &dyn Any) -> bool;
     fn as_any(&self) -> &dyn Any;
-    fn inner_intersect(&self, object_ray: Ray) -> Intersections;
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>>;
+    // Containers such as groups and CSG shapes report every hit on one of their children and
+    // so never own one; they panic here instead of making up a normal
     fn inner_normal_at(&self, object_point: Tuple) -> Tuple;
     fn material(&self) -> &Material;
-    fn transformation(&self) -> Matrix;
-    fn inverse_transformation(&self) -> Matrix;
+    fn material_mut(&mut self) -> &mut Material;
+    fn transforms(&self) -> &Transforms;
+    fn transforms_mut(&mut self) -> &mut Transforms;
+
+    // Which shape instance this is, see ShapeId
+    fn id(&self) -> usize;
+
+    // Gives this shape, and every shape it contains, a fresh id
+    fn renew_ids(&mut self);
+
+    // A copy that counts as a separate instance, e.g. one of many shapes built from a template.
+    // box_clone keeps the ids, so a clone still stands for the shape it was taken from.
+    fn duplicate(&self) -> BoxShape {
+        let mut copy = self.box_clone();
+        copy.renew_ids();
+        copy
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transforms().transform
+    }
+
+    fn inverse_transformation(&self) -> Matrix {
+        self.transforms().inverse
+    }
+
+    // Combined transform of every enclosing group, set by the group when the shape is added to it
+    fn parent_transformation(&self) -> Matrix {
+        self.transforms().parent
+    }
+
+    fn parent_inverse_transformation(&self) -> Matrix {
+        self.transforms().parent_inverse
+    }
+
+    // Shapes containing other shapes override this and set_transform to pass the combined
+    // transform on to their children
+    fn set_parent_transformation(&mut self, parent: Matrix) {
+        self.transforms_mut().set_parent(parent);
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transforms_mut().set_transform(transform);
+    }
+
+    fn set_material(&mut self, material: Material) {
+        *self.material_mut() = material;
+    }
+
+    fn with_transform(mut self, transform: Matrix) -> Self where Self: Sized {
+        self.set_transform(transform);
+        self
+    }
+
+    fn with_material(mut self, material: Material) -> Self where Self: Sized {
+        self.set_material(material);
+        self
+    }
+
+    // Object space bounds; unbounded unless a shape knows better, so it is never culled
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::infinite()
+    }
+
+    // Bounds in the space of the enclosing group
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.transformation())
+    }
+
+    // Groups split their children into a bounding volume hierarchy; primitives have nothing to do
+    fn divide(&mut self, _threshold: usize) {}
+
+    fn inner_intersect(&self, object_ray: Ray) -> Intersections {
+        Intersections::from_refs(self.inner_intersect_ref(object_ray))
+    }
 
     fn intersect(&self, world_ray: Ray) -> Intersections {
-        self.inner_intersect(world_ray.transform(self.inverse_transformation()))
+        match self.to_object_ray(world_ray) {
+            Some(object_ray) => self.inner_intersect(object_ray),
+            None => Intersections::new(vec![])
+        }
+    }
+
+    fn intersect_ref(&self, world_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        match self.to_object_ray(world_ray) {
+            Some(object_ray) => self.inner_intersect_ref(object_ray),
+            None => vec![]
+        }
+    }
+
+    // None when the shape has a singular transform: the zero matrix stored in place of its
+    // inverse maps every point to w = 0, and such a shape can't be hit
+    fn to_object_ray(&self, world_ray: Ray) -> Option<Ray> {
+        let inverse = self.inverse_transformation();
+        let origin = inverse * world_ray.origin;
+        if origin.is_point() { Some(Ray::new(origin, inverse * world_ray.direction)) } else { None }
     }
 
     fn normal_at(&self, world_point: Tuple) -> Tuple {
-        let object_normal = self.inner_normal_at(self.inverse_transformation() * world_point);
-        let mut world_normal = self.inverse_transformation().transpose() * object_normal;
+        let object_normal = self.inner_normal_at(self.to_object_space(world_point));
+        self.normal_to_world_space(object_normal)
+    }
+
+    // Shapes whose normal depends on where exactly they were hit (e.g. smooth triangles) override this
+    fn inner_normal_at_hit(&self, object_point: Tuple, _hit: &Intersection) -> Tuple {
+        self.inner_normal_at(object_point)
+    }
+
+    fn normal_at_hit(&self, world_point: Tuple, hit: &Intersection) -> Tuple {
+        let object_normal = self.inner_normal_at_hit(self.to_object_space(world_point), hit);
+        self.normal_to_world_space(object_normal)
+    }
+
+    fn to_object_space(&self, world_point: Tuple) -> Tuple {
+        self.inverse_transformation() * (self.parent_inverse_transformation() * world_point)
+    }
+
+    fn to_world_space(&self, object_point: Tuple) -> Tuple {
+        self.parent_transformation() * (self.transformation() * object_point)
+    }
+
+    fn vector_to_object_space(&self, world_vector: Tuple) -> Tuple {
+        self.inverse_transformation() * (self.parent_inverse_transformation() * world_vector)
+    }
+
+    fn vector_to_world_space(&self, object_vector: Tuple) -> Tuple {
+        self.parent_transformation() * (self.transformation() * object_vector)
+    }
+
+    // Normals are not carried by the transform itself but by its inverse transpose
+    fn normal_to_world_space(&self, object_normal: Tuple) -> Tuple {
+        let world_inverse = self.inverse_transformation() * self.parent_inverse_transformation();
+        let mut world_normal = world_inverse.transpose() * object_normal;
         world_normal.w = 0.;
 
         world_normal.normalize()
@@ -32,10 +159,79 @@ pub trait Shape: Any + fmt::Debug {
 
 pub type BoxShape = Box<dyn Shape>;
 
+// A shape's own transform and the combined transform of its enclosing groups, each kept with its
+// inverse so rays and normals don't invert a matrix per hit
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transforms {
+    transform: Matrix,
+    inverse: Matrix,
+    parent: Matrix,
+    parent_inverse: Matrix
+}
+
+impl Default for Transforms {
+    fn default() -> Self {
+        Transforms { transform: IDENTITY_MATRIX, inverse: IDENTITY_MATRIX, parent: IDENTITY_MATRIX, parent_inverse: IDENTITY_MATRIX }
+    }
+}
+
+impl Transforms {
+    pub fn new(transform: Option<Matrix>) -> Self {
+        Transforms { transform: transform.unwrap_or_default(), inverse: inverse_transform_parameter(transform), ..Default::default() }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = inverse_transform_parameter(Some(transform));
+    }
+
+    pub fn set_parent(&mut self, parent: Matrix) {
+        self.parent = parent;
+        self.parent_inverse = inverse_transform_parameter(Some(parent));
+    }
+
+    // What the shape's children see as their parent transform
+    pub fn chain(&self) -> Matrix {
+        self.parent * self.transform
+    }
+}
+
+static NEXT_SHAPE_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Identity of a shape instance, assigned at construction. Clones keep it, so a cloned shape still
+// stands for the one it was taken from; Shape::duplicate makes a new instance instead. Shapes
+// built alike are still equal as values, so the id takes no part in PartialEq; compare
+// Shape::id() to tell instances apart.
+#[derive(Debug, Copy, Clone)]
+pub struct ShapeId(usize);
+
+impl ShapeId {
+    pub fn next() -> Self {
+        ShapeId(NEXT_SHAPE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+impl Default for ShapeId {
+    fn default() -> Self {
+        ShapeId::next()
+    }
+}
+
+impl PartialEq for ShapeId {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+// A singular transform has no inverse; it is stored as the zero matrix and reported by World::validate
 pub fn inverse_transform_parameter(transform: Option<Matrix>) -> Matrix {
     match transform {
         None => IDENTITY_MATRIX,
-        Some(t) => t.inverse().unwrap()
+        Some(t) => t.inverse().unwrap_or_else(Matrix::new_empty4)
     }
 }
 
@@ -54,18 +250,27 @@ impl PartialEq for BoxShape {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f64::consts::{PI, SQRT_2};
+    use std::cell::Cell;
+    use crate::consts::{PI, SQRT_2, FRAC_1_SQRT_2};
     use crate::color::GREEN;
     use crate::tuple::{ORIGO, VECTOR_Y_UP};
     use crate::material::DEFAULT_MATERIAL;
 
-    static mut SAVED_RAY: Ray = Ray { origin: ORIGO, direction: VECTOR_Y_UP };
+    thread_local! {
+        static SAVED_RAY: Cell<Ray> = const { Cell::new(Ray { origin: ORIGO, direction: VECTOR_Y_UP }) };
+    }
 
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug)]
     struct TestShape {
+        id: ShapeId,
         material: Material,
-        inverse_transform: Matrix,
-        transform: Matrix
+        transforms: Transforms
+    }
+
+    impl PartialEq for TestShape {
+        fn eq(&self, other: &Self) -> bool {
+            self.transformation() == other.transformation() && self.material == other.material
+        }
     }
 
     impl Shape for TestShape {
@@ -74,18 +279,16 @@ mod tests {
         }
 
         fn box_eq(&self, other: &dyn Any) -> bool {
-            other.downcast_ref::<Self>().map_or(false, |a| self == a)
+            other.downcast_ref::<Self>().is_some_and(|a| self == a)
         }
 
         fn as_any(&self) -> &dyn Any {
             self
         }
 
-        fn inner_intersect(&self, object_ray: Ray) -> Intersections {
-            unsafe {
-                SAVED_RAY = object_ray;
-            }
-            Intersections::new(vec![])
+        fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+            SAVED_RAY.with(|r| r.set(object_ray));
+            vec![]
         }
 
         fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
@@ -96,21 +299,33 @@ mod tests {
             &self.material
         }
 
-        fn transformation(&self) -> Matrix {
-            self.transform
+        fn material_mut(&mut self) -> &mut Material {
+            &mut self.material
+        }
+
+        fn transforms(&self) -> &Transforms {
+            &self.transforms
+        }
+
+        fn transforms_mut(&mut self) -> &mut Transforms {
+            &mut self.transforms
+        }
+
+        fn id(&self) -> usize {
+            self.id.get()
         }
 
-        fn inverse_transformation(&self) -> Matrix {
-            self.inverse_transform
+        fn renew_ids(&mut self) {
+            self.id = ShapeId::next();
         }
     }
 
     impl TestShape {
         fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
             Self {
+                id: ShapeId::default(),
                 material: material.unwrap_or_default(),
-                transform: transform.unwrap_or_default(),
-                inverse_transform: inverse_transform_parameter(transform)
+                transforms: Transforms::new(transform)
             }
         }
     }
@@ -140,7 +355,7 @@ mod tests {
 
     #[test]
     fn assign_material() {
-        let m = Material::new(GREEN, 0.1, 0.2, 0.3, 0.4, None);
+        let m = Material::new(GREEN, 0.1, 0.2, 0.3, 0.4, 0.5, None);
         let s = TestShape::new(Some(m.clone()), None);
 
         assert_eq!(*s.material(), m);
@@ -153,10 +368,9 @@ mod tests {
         let s = TestShape::new(None, Some(tr));
         s.intersect(r);
 
-        unsafe {
-            assert_eq!(SAVED_RAY.origin, Tuple::point(0., 0., -2.5));
-            assert_eq!(SAVED_RAY.direction, Tuple::vector(0., 0., 0.5));
-        }
+        let saved_ray = SAVED_RAY.with(|r| r.get());
+        assert_eq!(saved_ray.origin, Tuple::point(0., 0., -2.5));
+        assert_eq!(saved_ray.direction, Tuple::vector(0., 0., 0.5));
     }
 
     #[test]
@@ -166,19 +380,60 @@ mod tests {
         let s = TestShape::new(None, Some(tr));
         s.intersect(r);
 
-        unsafe {
-            assert_eq!(SAVED_RAY.origin, Tuple::point(-5., 0., -5.));
-            assert_eq!(SAVED_RAY.direction, Tuple::vector(0., 0., 1.));
-        }
+        let saved_ray = SAVED_RAY.with(|r| r.get());
+        assert_eq!(saved_ray.origin, Tuple::point(-5., 0., -5.));
+        assert_eq!(saved_ray.direction, Tuple::vector(0., 0., 1.));
+    }
+
+    #[test]
+    fn clones_keep_the_id_and_duplicates_get_new_ones() {
+        use crate::sphere::Sphere;
+
+        let a = Sphere::default_boxed();
+        let b = Sphere::default_boxed();
+
+        assert_ne!(a.id(), b.id());
+        assert_eq!(a.clone().id(), a.id());
+        assert_ne!(a.duplicate().id(), a.id());
+        assert_eq!(&a.duplicate(), &a);
+        assert_eq!(&a, &b);
+    }
+
+    #[test]
+    fn duplicating_a_group_renews_the_ids_of_its_children() {
+        use crate::group::Group;
+        use crate::sphere::Sphere;
+
+        let g = Group::new_boxed(None, vec![Sphere::default_boxed()]);
+        let copy = g.duplicate();
+        let child_id = |g: &BoxShape| g.as_any().downcast_ref::<Group>().unwrap().children()[0].id();
+
+        assert_ne!(copy.id(), g.id());
+        assert_ne!(child_id(&copy), child_id(&g));
+    }
+
+    #[test]
+    fn shape_with_singular_transform_is_never_hit() {
+        use crate::sphere::Sphere;
+
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let s = Sphere::new(None, Some(Matrix::scaling(0., 0., 0.)));
+
+        assert!(s.intersect(r).is_empty());
+        assert!(s.intersect_ref(r).is_empty());
+        let probe = Ray::new(Tuple::point(7., 7., 7.), Tuple::vector(0., 1., 0.));
+        TestShape::new(None, Some(Matrix::scaling(1., 0., 1.))).intersect(probe);
+        // inner_intersect_ref was never reached, so the saved ray is still the initial one
+        assert_eq!(SAVED_RAY.with(|r| r.get()).origin, ORIGO);
     }
 
     #[test]
     fn compute_normal_on_translated_shape() {
         let tr = Matrix::translation(0., 1., 0.);
         let s = TestShape::new(None, Some(tr));
-        let n = s.normal_at(Tuple::point(0., 1.70711, -0.70711));
+        let n = s.normal_at(Tuple::point(0., 1. + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
 
-        assert_eq!(n, Tuple::vector(0., 0.70711, -0.70711));
+        assert_eq!(n, Tuple::vector(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
     }
 
     #[test]
@@ -189,4 +444,26 @@ mod tests {
 
         assert_eq!(n, Tuple::vector(0., 0.97014, -0.24254));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn point_round_trips_through_object_space() {
+        let tr = Matrix::translation(1., 2., 3.) * Matrix::scaling(2., 4., 0.5);
+        let s = TestShape::new(None, Some(tr));
+        let world_point = Tuple::point(3., -2., 5.);
+        let object_point = s.to_object_space(world_point);
+
+        assert_eq!(object_point, Tuple::point(1., -1., 4.));
+        assert_eq!(s.to_world_space(object_point), world_point);
+    }
+
+    #[test]
+    fn vectors_ignore_translation_and_normals_use_inverse_transpose() {
+        let tr = Matrix::translation(1., 2., 3.) * Matrix::scaling(2., 4., 0.5);
+        let s = TestShape::new(None, Some(tr));
+
+        assert_eq!(s.vector_to_object_space(Tuple::vector(2., 4., 1.)), Tuple::vector(1., 1., 2.));
+        assert_eq!(s.vector_to_world_space(Tuple::vector(1., 1., 2.)), Tuple::vector(2., 4., 1.));
+        assert_eq!(s.normal_to_world_space(Tuple::vector(1., 1., 0.)),
+                   Tuple::vector(0.5, 0.25, 0.).normalize());
+    }
+}
@@ -0,0 +1,103 @@
+use super::Scalar;
+
+fn evaluate(coefficients: &[Scalar], x: Scalar) -> Scalar {
+    coefficients.iter().fold(0., |acc, c| acc * x + c)
+}
+
+// Shrinks [lo, hi] around the sign change of p until the floats run out
+fn bisect(coefficients: &[Scalar], mut lo: Scalar, mut hi: Scalar) -> Scalar {
+    let lo_sign = evaluate(coefficients, lo) < 0.;
+    loop {
+        let mid = lo + (hi - lo) / 2.;
+        if mid <= lo || mid >= hi {
+            return mid;
+        }
+        let value = evaluate(coefficients, mid);
+        if value == 0. {
+            return mid;
+        }
+        if (value < 0.) == lo_sign { lo = mid } else { hi = mid }
+    }
+}
+
+// Real roots in ascending order, coefficients from the highest power down. The roots of the
+// derivative split the line into monotonic pieces, each holding at most one root to bisect for.
+// Roots of even multiplicity don't change sign and are only found when p hits exactly 0 there.
+pub fn real_roots(coefficients: &[Scalar]) -> Vec<Scalar> {
+    let first = coefficients.iter().position(|&c| c != 0.).unwrap_or(coefficients.len());
+    let coefficients = &coefficients[first..];
+    let degree = match coefficients.len() {
+        0 | 1 => return vec![],
+        2 => return vec![-coefficients[1] / coefficients[0]],
+        n => n - 1
+    };
+    // Cauchy's bound, every root lies within [-bound, bound]
+    let bound = 1. + coefficients[1..].iter().map(|c| (c / coefficients[0]).abs()).fold(0., Scalar::max);
+    let derivative: Vec<Scalar> = coefficients[..degree].iter().enumerate()
+        .map(|(i, c)| c * (degree - i) as Scalar)
+        .collect();
+    let mut splits = vec![-bound];
+    splits.extend(real_roots(&derivative).into_iter().filter(|x| x.abs() < bound));
+    splits.push(bound);
+
+    let mut roots: Vec<Scalar> = vec![];
+    for pair in splits.windows(2) {
+        let (lo, hi) = (pair[0], pair[1]);
+        let (p_lo, p_hi) = (evaluate(coefficients, lo), evaluate(coefficients, hi));
+        let root = if p_lo == 0. {
+            lo
+        } else if p_hi == 0. {
+            hi
+        } else if (p_lo < 0.) != (p_hi < 0.) {
+            bisect(coefficients, lo, hi)
+        } else {
+            continue;
+        };
+        if roots.last() != Some(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+// Real solutions of a x^4 + b x^3 + c x^2 + d x + e = 0, ascending
+pub fn solve_quartic(a: Scalar, b: Scalar, c: Scalar, d: Scalar, e: Scalar) -> Vec<Scalar> {
+    real_roots(&[a, b, c, d, e])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(actual: Vec<Scalar>, expected: &[Scalar]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(crate::approx_eq(*a, *e), "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn quartic_with_four_real_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(solve_quartic(1., -10., 35., -50., 24.), &[1., 2., 3., 4.]);
+        // (x + 0.5)(x - 0.25)(x - 2)(x - 6), scaled by 2
+        assert_roots(solve_quartic(2., -15.5, 19.75, 8., -3.), &[-0.5, 0.25, 2., 6.]);
+    }
+
+    #[test]
+    fn quartic_with_fewer_real_roots() {
+        // (x^2 + 1)(x - 1)(x + 3)
+        assert_roots(solve_quartic(1., 2., -2., 2., -3.), &[-3., 1.]);
+        // x^4 + 1
+        assert_roots(solve_quartic(1., 0., 0., 0., 1.), &[]);
+    }
+
+    #[test]
+    fn lower_degree_polynomials() {
+        assert_roots(real_roots(&[0., 0., 2., -3.]), &[1.5]);
+        assert_roots(real_roots(&[1., 0., -4.]), &[-2., 2.]);
+        assert_roots(real_roots(&[1., 0., 4.]), &[]);
+        assert_roots(real_roots(&[1., -6., 11., -6.]), &[1., 2., 3.]);
+        assert_roots(real_roots(&[5.]), &[]);
+    }
+}
@@ -1,31 +1,24 @@
-use super::intersection::{Intersection, Intersections};
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
 use super::material::Material;
-use super::matrix::{Matrix, IDENTITY_MATRIX};
+use super::matrix::Matrix;
 use super::ray::Ray;
-use super::shape::{inverse_transform_parameter, Shape, BoxShape};
+use super::shape::{Shape, BoxShape, ShapeId, Transforms};
 use super::tuple::{Tuple, ORIGO};
 use std::any::Any;
+use super::consts::PI;
+use super::Scalar;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Sphere {
-    inverse_transform: Matrix,
-    transform: Matrix,
+    id: ShapeId,
+    transforms: Transforms,
     material: Material,
 }
 
 impl PartialEq for Sphere {
     fn eq(&self, other: &Self) -> bool {
-        self.transform == other.transform && self.material == other.material
-    }
-}
-
-impl Default for Sphere {
-    fn default() -> Self {
-        Self {
-            transform: IDENTITY_MATRIX,
-            inverse_transform: IDENTITY_MATRIX,
-            material: Material::default(),
-        }
+        self.transformation() == other.transformation() && self.material == other.material
     }
 }
 
@@ -35,14 +28,14 @@ impl Shape for Sphere {
     }
 
     fn box_eq(&self, other: &dyn Any) -> bool {
-        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
     }
 
     fn box_clone(&self) -> BoxShape {
         Box::new((*self).clone())
     }
 
-    fn inner_intersect(&self, object_ray: Ray) -> Intersections {
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
         let sphere_to_ray = object_ray.origin - ORIGO;
         let a = object_ray.direction.dot(&object_ray.direction);
         let b = 2.0 * object_ray.direction.dot(&sphere_to_ray);
@@ -50,18 +43,12 @@ impl Shape for Sphere {
         let discriminant = b * b - 4. * a * c;
 
         if discriminant < 0. {
-            return Intersections::new(vec![]);
+            return vec![];
         }
 
-        let i1 = Intersection::new(
-            (-b - discriminant.sqrt()) / (2. * a),
-            Box::new(self.clone()),
-        );
-        let i2 = Intersection::new(
-            (-b + discriminant.sqrt()) / (2. * a),
-            Box::new(self.clone()),
-        );
-        Intersections::new(vec![i2, i1])
+        let i1 = IntersectionRef::new((-b - discriminant.sqrt()) / (2. * a), self);
+        let i2 = IntersectionRef::new((-b + discriminant.sqrt()) / (2. * a), self);
+        vec![i2, i1]
     }
 
     fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
@@ -72,21 +59,37 @@ impl Shape for Sphere {
         &self.material
     }
 
-    fn transformation(&self) -> Matrix {
-        self.transform
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
     }
 
-    fn inverse_transformation(&self) -> Matrix {
-        self.inverse_transform
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1., -1., -1.), Tuple::point(1., 1., 1.))
     }
 }
 
 impl Sphere {
     pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
         Self {
-            transform: transform.unwrap_or_default(),
-            inverse_transform: inverse_transform_parameter(transform),
+            transforms: Transforms::new(transform),
             material: material.unwrap_or_default(),
+            ..Default::default()
         }
     }
 
@@ -97,11 +100,26 @@ impl Sphere {
     pub fn default_boxed() -> BoxShape {
         Box::new(Sphere::default())
     }
+
+    // Maps an object space point to (u, v) in [0, 1]. The seam runs along the -z meridian, with u
+    // approaching 0 from the +x side and 1 from the -x side. At the poles the longitude is undefined,
+    // so u is fixed to 0 there.
+    pub fn uv_map(point: Tuple) -> (Scalar, Scalar) {
+        let radius = Tuple::vector(point.x, point.y, point.z).magnitude();
+        let v = 1. - (point.y / radius).clamp(-1., 1.).acos() / PI;
+        if super::approx_eq(0., point.x) && super::approx_eq(0., point.z) {
+            return (0., v);
+        }
+        let theta = point.x.atan2(point.z);
+        let u = 1. - (theta / (2. * PI) + 0.5);
+        (u, v)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Scalar;
 
     #[test]
     fn ray_intersect_sphere_at_two_points() {
@@ -183,7 +201,7 @@ mod tests {
     #[test]
     fn normal_on_sphere_on_non_axial_point() {
         let s = Sphere::default();
-        let pv = 3.0f64.sqrt() / 3.0;
+        let pv = Scalar::sqrt(3.0) / 3.0;
         let n = s.inner_normal_at(Tuple::point(pv, pv, pv));
 
         assert_eq!(n, Tuple::vector(pv, pv, pv));
@@ -192,9 +210,75 @@ mod tests {
     #[test]
     fn normal_is_normalized_vector() {
         let s = Sphere::default();
-        let pv = 3.0f64.sqrt() / 3.0;
+        let pv = Scalar::sqrt(3.0) / 3.0;
         let n = s.inner_normal_at(Tuple::point(pv, pv, pv));
 
         assert_eq!(n, n.normalize());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn normal_on_mirrored_sphere_points_outward() {
+        let s = Sphere::new(None, Some(Matrix::scaling(-1., 1., 1.)));
+
+        assert_eq!(s.normal_at(Tuple::point(1., 0., 0.)), Tuple::vector(1., 0., 0.));
+        assert_eq!(s.normal_at(Tuple::point(-1., 0., 0.)), Tuple::vector(-1., 0., 0.));
+    }
+
+    #[test]
+    fn normal_on_mirrored_and_translated_sphere_points_outward() {
+        let tr = Matrix::translation(2., 0., 0.) * Matrix::scaling(-1., 2., 1.);
+        let s = Sphere::new(None, Some(tr));
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let n = s.normal_at(Tuple::point(2. + pv, 0., pv));
+
+        assert_eq!(n, Tuple::vector(pv, 0., pv));
+    }
+
+    #[test]
+    fn uv_map_is_stable_at_the_poles() {
+        assert_eq!(Sphere::uv_map(Tuple::point(0., 1., 0.)), (0., 1.));
+        assert_eq!(Sphere::uv_map(Tuple::point(0., -1., 0.)), (0., 0.));
+        assert_eq!(Sphere::uv_map(Tuple::point(0., 2., 0.)), (0., 1.));
+    }
+
+    #[test]
+    fn uv_map_on_either_side_of_the_seam() {
+        let (u_east, v_east) = Sphere::uv_map(Tuple::point(0.001, 0., -1.));
+        let (u_west, v_west) = Sphere::uv_map(Tuple::point(-0.001, 0., -1.));
+
+        assert!((0. ..0.01).contains(&u_east));
+        assert!((0.99..=1.).contains(&u_west));
+        assert!(crate::approx_eq(v_east, 0.5) && crate::approx_eq(v_west, 0.5));
+    }
+
+    #[test]
+    fn uv_map_around_the_equator() {
+        assert_eq!(Sphere::uv_map(Tuple::point(0., 0., 1.)), (0.5, 0.5));
+        assert_eq!(Sphere::uv_map(Tuple::point(1., 0., 0.)), (0.25, 0.5));
+        assert_eq!(Sphere::uv_map(Tuple::point(-1., 0., 0.)), (0.75, 0.5));
+    }
+
+    #[test]
+    fn chained_builders_override_transform_and_material() {
+        let m = Material { ambient: 1., ..Default::default() };
+        let s = Sphere::default()
+            .with_transform(Matrix::translation(0., 0., 1.) * Matrix::scaling(2., 2., 2.))
+            .with_material(m.clone());
+        let xs = s.intersect(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.)));
+
+        assert_eq!(s.material(), &m);
+        assert_eq!(xs.len(), 2);
+        assert_eq!((xs[0].t, xs[1].t), (4., 8.));
+        assert_eq!(s.normal_at(Tuple::point(0., 2., 1.)), Tuple::vector(0., 1., 0.));
+        assert_eq!(&Sphere::new_boxed(Some(m), Some(s.transformation())), &s.box_clone());
+    }
+
+    #[test]
+    fn setting_transform_replaces_cached_inverse() {
+        let mut s = Sphere::new(None, Some(Matrix::scaling(2., 2., 2.)));
+        s.set_transform(Matrix::translation(5., 0., 0.));
+
+        assert_eq!(s.inverse_transformation(), Matrix::translation(-5., 0., 0.));
+        assert!(s.intersect(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.))).is_empty());
+    }
+}
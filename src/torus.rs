@@ -0,0 +1,188 @@
+use super::bounds::BoundingBox;
+use super::intersection::IntersectionRef;
+use super::material::Material;
+use super::matrix::Matrix;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::solver::solve_quartic;
+use super::tuple::{Tuple, ORIGO};
+use super::Scalar;
+use std::any::Any;
+
+// Ring around the y axis: the tube of radius minor_radius follows a circle of radius major_radius in the xz plane
+#[derive(Debug, Clone, PartialEq)]
+pub struct Torus {
+    id: ShapeId,
+    transforms: Transforms,
+    material: Material,
+    major_radius: Scalar,
+    minor_radius: Scalar,
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self {
+            id: ShapeId::default(),
+            transforms: Transforms::default(),
+            material: Material::default(),
+            major_radius: 1.,
+            minor_radius: 0.25,
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    // Substitutes the ray into (x² + y² + z² + R² - r²)² = 4R²(x² + z²). Solving from the ray's
+    // closest approach to the center keeps the coefficients small; far away origins otherwise
+    // lose most of the precision to cancellation
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let d = object_ray.direction;
+        let d_dot_d = d.x * d.x + d.y * d.y + d.z * d.z;
+        let shift = -(object_ray.origin - ORIGO).dot(&d) / d_dot_d;
+        let o = object_ray.position(shift);
+        let (major2, minor2) = (self.major_radius * self.major_radius, self.minor_radius * self.minor_radius);
+        let four_major2 = 4. * major2;
+        let o_dot_d = o.x * d.x + o.y * d.y + o.z * d.z;
+        let e = o.x * o.x + o.y * o.y + o.z * o.z + major2 - minor2;
+        solve_quartic(
+            d_dot_d * d_dot_d,
+            4. * d_dot_d * o_dot_d,
+            2. * d_dot_d * e + 4. * o_dot_d * o_dot_d - four_major2 * (d.x * d.x + d.z * d.z),
+            4. * o_dot_d * e - 2. * four_major2 * (o.x * d.x + o.z * d.z),
+            e * e - four_major2 * (o.x * o.x + o.z * o.z))
+            .into_iter()
+            .map(|t| IntersectionRef::new(t + shift, self))
+            .collect()
+    }
+
+    // Gradient of the implicit equation, with the common factor 4 dropped
+    fn inner_normal_at(&self, object_point: Tuple) -> Tuple {
+        let (x, y, z) = (object_point.x, object_point.y, object_point.z);
+        let (major2, minor2) = (self.major_radius * self.major_radius, self.minor_radius * self.minor_radius);
+        let sum = x * x + y * y + z * z;
+        Tuple::vector(x * (sum - minor2 - major2), y * (sum - minor2 + major2), z * (sum - minor2 - major2))
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let outer = self.major_radius + self.minor_radius;
+        BoundingBox::new(Tuple::point(-outer, -self.minor_radius, -outer), Tuple::point(outer, self.minor_radius, outer))
+    }
+}
+
+impl Torus {
+    pub fn new(material: Option<Material>, transform: Option<Matrix>) -> Self {
+        Self {
+            transforms: Transforms::new(transform),
+            material: material.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_boxed(material: Option<Material>, transform: Option<Matrix>) -> BoxShape {
+        Box::new(Torus::new(material, transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Torus::default())
+    }
+
+    pub fn with_radii(self, major_radius: Scalar, minor_radius: Scalar) -> Self {
+        Self { major_radius, minor_radius, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(torus: &Torus, origin: Tuple, direction: Tuple) -> Vec<Scalar> {
+        torus.inner_intersect(Ray::new(origin, direction)).iter().map(|i| i.t).collect()
+    }
+
+    #[test]
+    fn ray_through_both_sides_of_the_ring() {
+        let torus = Torus::default();
+        let actual = ts(&torus, Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let expected = [3.75, 4.25, 5.75, 6.25];
+
+        assert_eq!(actual.len(), 4);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!(crate::approx_eq(*a, *e), "{:?}", actual);
+        }
+    }
+
+    #[test]
+    fn rays_missing_the_torus() {
+        let torus = Torus::default();
+
+        assert!(ts(&torus, Tuple::point(0., 5., 0.), Tuple::vector(0., -1., 0.)).is_empty());
+        assert!(ts(&torus, Tuple::point(0., 0.5, -5.), Tuple::vector(0., 0., 1.)).is_empty());
+    }
+
+    #[test]
+    fn ray_through_the_tube_from_above() {
+        let torus = Torus::default().with_radii(2., 0.5);
+        let actual = ts(&torus, Tuple::point(2., 3., 0.), Tuple::vector(0., -1., 0.));
+
+        assert_eq!(actual.len(), 2);
+        assert!(crate::approx_eq(actual[0], 2.5) && crate::approx_eq(actual[1], 3.5), "{:?}", actual);
+    }
+
+    #[test]
+    fn normals_on_a_torus() {
+        let torus = Torus::default();
+        let cases = [
+            (Tuple::point(1.25, 0., 0.), Tuple::vector(1., 0., 0.)),
+            (Tuple::point(0.75, 0., 0.), Tuple::vector(-1., 0., 0.)),
+            (Tuple::point(1., 0.25, 0.), Tuple::vector(0., 1., 0.)),
+            (Tuple::point(0., -0.25, -1.), Tuple::vector(0., -1., 0.))];
+
+        for (point, normal) in cases.iter() {
+            assert_eq!(torus.normal_at(*point), *normal);
+        }
+    }
+
+    #[test]
+    fn bounds_of_a_torus() {
+        let bounds = Torus::default().with_radii(2., 0.5).bounds();
+
+        assert_eq!(bounds.min, Tuple::point(-2.5, -0.5, -2.5));
+        assert_eq!(bounds.max, Tuple::point(2.5, 0.5, 2.5));
+    }
+}
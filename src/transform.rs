@@ -1,8 +1,9 @@
 use super::matrix::{Matrix, IDENTITY_MATRIX};
 use super::tuple::Tuple;
+use super::Scalar;
 
 impl Matrix {
-    pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
+    pub fn translation(x: Scalar, y: Scalar, z: Scalar) -> Matrix {
         let mut m = IDENTITY_MATRIX;
         m.set(0, 3, x);
         m.set(1, 3, y);
@@ -10,7 +11,7 @@ impl Matrix {
         m
     }
 
-    pub fn scaling(x: f64, y: f64, z: f64) -> Matrix {
+    pub fn scaling(x: Scalar, y: Scalar, z: Scalar) -> Matrix {
         let mut m = IDENTITY_MATRIX;
         m.set(0, 0, x);
         m.set(1, 1, y);
@@ -18,7 +19,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotation_x(rad: f64) -> Matrix {
+    pub fn rotation_x(rad: Scalar) -> Matrix {
         let mut m = IDENTITY_MATRIX;
         m.set(1, 1, rad.cos());
         m.set(1, 2, -rad.sin());
@@ -27,7 +28,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotation_y(rad: f64) -> Matrix {
+    pub fn rotation_y(rad: Scalar) -> Matrix {
         let mut m = IDENTITY_MATRIX;
         m.set(0, 0, rad.cos());
         m.set(0, 2, rad.sin());
@@ -36,7 +37,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotation_z(rad: f64) -> Matrix {
+    pub fn rotation_z(rad: Scalar) -> Matrix {
         let mut m = IDENTITY_MATRIX;
         m.set(0, 0, rad.cos());
         m.set(0, 1, -rad.sin());
@@ -45,7 +46,7 @@ impl Matrix {
         m
     }
 
-    pub fn shearing(x_to_y: f64, x_to_z: f64, y_to_x: f64, y_to_z: f64, z_to_x: f64, z_to_y: f64) -> Matrix {
+    pub fn shearing(x_to_y: Scalar, x_to_z: Scalar, y_to_x: Scalar, y_to_z: Scalar, z_to_x: Scalar, z_to_y: Scalar) -> Matrix {
         let mut m = IDENTITY_MATRIX;
         m.set(0, 1, x_to_y);
         m.set(0, 2, x_to_z);
@@ -72,7 +73,7 @@ impl Matrix {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f64::consts::*;
+    use crate::consts::*;
     use crate::tuple::ORIGO;
 
     #[test]
@@ -154,7 +155,7 @@ mod tests {
         let actual_half_quarter = half_quarter * p;
         let actual_full_quarter = full_quarter * p;
 
-        assert_eq!(actual_half_quarter, Tuple::point(0., 2.0_f64.sqrt()/2., 2.0_f64.sqrt()/2.));
+        assert_eq!(actual_half_quarter, Tuple::point(0., Scalar::sqrt(2.0)/2., Scalar::sqrt(2.0)/2.));
         assert_eq!(actual_full_quarter, Tuple::point(0., 0., 1.));
     }
 
@@ -164,7 +165,7 @@ mod tests {
         let half_quarter = Matrix::rotation_x(FRAC_PI_4);
         let inv = half_quarter.inverse().unwrap();
         let actual = inv * p;
-        let expected = Tuple::point(0., 2.0_f64.sqrt()/2., -2.0_f64.sqrt()/2.);
+        let expected = Tuple::point(0., Scalar::sqrt(2.0)/2., -Scalar::sqrt(2.0)/2.);
 
         assert_eq!(actual, expected);
     }
@@ -177,7 +178,7 @@ mod tests {
         let actual_half_quarter = half_quarter * p;
         let actual_full_quarter = full_quarter * p;
 
-        assert_eq!(actual_half_quarter, Tuple::point(2.0_f64.sqrt()/2., 0., 2.0_f64.sqrt()/2.));
+        assert_eq!(actual_half_quarter, Tuple::point(Scalar::sqrt(2.0)/2., 0., Scalar::sqrt(2.0)/2.));
         assert_eq!(actual_full_quarter, Tuple::point(1., 0., 0.));
     }
 
@@ -189,7 +190,7 @@ mod tests {
         let actual_half_quarter = half_quarter * p;
         let actual_full_quarter = full_quarter * p;
 
-        assert_eq!(actual_half_quarter, Tuple::point(-2.0_f64.sqrt()/2., 2.0_f64.sqrt()/2., 0.));
+        assert_eq!(actual_half_quarter, Tuple::point(-Scalar::sqrt(2.0)/2., Scalar::sqrt(2.0)/2., 0.));
         assert_eq!(actual_full_quarter, Tuple::point(-1., 0., 0.));
     }
 
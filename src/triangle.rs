@@ -0,0 +1,316 @@
+use super::bounds::BoundingBox;
+use super::intersection::{Intersection, IntersectionRef};
+use super::material::Material;
+use super::ray::Ray;
+use super::shape::{BoxShape, Shape, ShapeId, Transforms};
+use super::tuple::Tuple;
+use super::{Scalar, EPSILON};
+use std::any::Any;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangle {
+    id: ShapeId,
+    p1: Tuple,
+    p2: Tuple,
+    p3: Tuple,
+    e1: Tuple,
+    e2: Tuple,
+    normal: Tuple,
+    material: Material,
+    transforms: Transforms,
+}
+
+impl Triangle {
+    pub fn new(points: [Tuple; 3], material: Option<Material>) -> Self {
+        let [p1, p2, p3] = points;
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Self {
+            id: ShapeId::default(),
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(&e1).normalize(),
+            material: material.unwrap_or_default(),
+            transforms: Transforms::default(),
+        }
+    }
+
+    pub fn new_boxed(points: [Tuple; 3], material: Option<Material>) -> BoxShape {
+        Box::new(Triangle::new(points, material))
+    }
+
+    pub fn points(&self) -> [Tuple; 3] {
+        [self.p1, self.p2, self.p3]
+    }
+
+    // Möller–Trumbore: returns t and the barycentric u, v of the hit
+    fn hit(&self, ray: Ray) -> Option<(Scalar, Scalar, Scalar)> {
+        let dir_cross_e2 = ray.direction.cross(&self.e2);
+        let det = self.e1.dot(&dir_cross_e2);
+        if det.abs() < EPSILON {
+            return None;
+        }
+        let f = 1. / det;
+        let p1_to_origin = ray.origin - self.p1;
+        let u = f * p1_to_origin.dot(&dir_cross_e2);
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+        let origin_cross_e1 = p1_to_origin.cross(&self.e1);
+        let v = f * ray.direction.dot(&origin_cross_e1);
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+        Some((f * self.e2.dot(&origin_cross_e1), u, v))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+        for point in [self.p1, self.p2, self.p3].iter() {
+            bounds.add_point(*point);
+        }
+        bounds
+    }
+}
+
+impl Shape for Triangle {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        match self.hit(object_ray) {
+            Some((t, u, v)) => vec![IntersectionRef::new_with_uv(t, self, u, v)],
+            None => vec![]
+        }
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        self.normal
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        Triangle::bounds(self)
+    }
+}
+
+// A triangle with per-vertex normals, interpolated across the face with the hit's u and v
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmoothTriangle {
+    triangle: Triangle,
+    n1: Tuple,
+    n2: Tuple,
+    n3: Tuple,
+}
+
+impl SmoothTriangle {
+    pub fn new(points: [Tuple; 3], normals: [Tuple; 3], material: Option<Material>) -> Self {
+        let [n1, n2, n3] = normals;
+        Self { triangle: Triangle::new(points, material), n1, n2, n3 }
+    }
+
+    pub fn new_boxed(points: [Tuple; 3], normals: [Tuple; 3], material: Option<Material>) -> BoxShape {
+        Box::new(SmoothTriangle::new(points, normals, material))
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inner_intersect_ref(&self, object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+        match self.triangle.hit(object_ray) {
+            Some((t, u, v)) => vec![IntersectionRef::new_with_uv(t, self, u, v)],
+            None => vec![]
+        }
+    }
+
+    fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+        self.triangle.normal
+    }
+
+    fn inner_normal_at_hit(&self, object_point: Tuple, hit: &Intersection) -> Tuple {
+        match (hit.u, hit.v) {
+            (Some(u), Some(v)) => self.n2 * u + self.n3 * v + self.n1 * (1. - u - v),
+            _ => self.inner_normal_at(object_point)
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.triangle.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.triangle.material
+    }
+
+    fn transforms(&self) -> &Transforms {
+        &self.triangle.transforms
+    }
+
+    fn transforms_mut(&mut self) -> &mut Transforms {
+        &mut self.triangle.transforms
+    }
+
+    fn id(&self) -> usize {
+        self.triangle.id.get()
+    }
+
+    fn renew_ids(&mut self) {
+        self.triangle.id = ShapeId::next();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.triangle.bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Matrix;
+
+    fn test_triangle() -> Triangle {
+        Triangle::new([Tuple::point(0., 1., 0.), Tuple::point(-1., 0., 0.), Tuple::point(1., 0., 0.)], None)
+    }
+
+    fn test_smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            [Tuple::point(0., 1., 0.), Tuple::point(-1., 0., 0.), Tuple::point(1., 0., 0.)],
+            [Tuple::vector(0., 1., 0.), Tuple::vector(-1., 0., 0.), Tuple::vector(1., 0., 0.)],
+            None)
+    }
+
+    #[test]
+    fn constructing_triangle() {
+        let t = test_triangle();
+
+        assert_eq!(t.e1, Tuple::vector(-1., -1., 0.));
+        assert_eq!(t.e2, Tuple::vector(1., -1., 0.));
+        assert_eq!(t.normal, Tuple::vector(0., 0., -1.));
+    }
+
+    #[test]
+    fn normal_on_triangle_is_constant() {
+        let t = test_triangle();
+
+        assert_eq!(t.inner_normal_at(Tuple::point(0., 0.5, 0.)), t.normal);
+        assert_eq!(t.inner_normal_at(Tuple::point(-0.5, 0.75, 0.)), t.normal);
+        assert_eq!(t.inner_normal_at(Tuple::point(0.5, 0.25, 0.)), t.normal);
+    }
+
+    #[test]
+    fn triangle_bounds_enclose_its_points() {
+        let t = Triangle::new([Tuple::point(-3., 7., 2.), Tuple::point(6., 2., -4.), Tuple::point(2., -1., -1.)], None);
+        let bounds = Shape::bounds(&t);
+
+        assert_eq!(bounds.min, Tuple::point(-3., -1., -4.));
+        assert_eq!(bounds.max, Tuple::point(6., 7., 2.));
+    }
+
+    #[test]
+    fn ray_parallel_to_triangle_misses() {
+        let r = Ray::new(Tuple::point(0., -1., -2.), Tuple::vector(0., 1., 0.));
+
+        assert!(test_triangle().inner_intersect_ref(r).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_each_triangle_edge() {
+        let t = test_triangle();
+        for origin in [Tuple::point(1., 1., -2.), Tuple::point(-1., 1., -2.), Tuple::point(0., -1., -2.)].iter() {
+            let r = Ray::new(*origin, Tuple::vector(0., 0., 1.));
+            assert!(t.inner_intersect_ref(r).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_triangle() {
+        let r = Ray::new(Tuple::point(0., 0.5, -2.), Tuple::vector(0., 0., 1.));
+        let xs = test_triangle().inner_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.);
+    }
+
+    #[test]
+    fn intersection_with_smooth_triangle_stores_u_and_v() {
+        let r = Ray::new(Tuple::point(-0.2, 0.3, -2.), Tuple::vector(0., 0., 1.));
+        let xs = test_smooth_triangle().inner_intersect(r);
+
+        assert!(crate::approx_eq(xs[0].u.unwrap(), 0.45));
+        assert!(crate::approx_eq(xs[0].v.unwrap(), 0.25));
+    }
+
+    #[test]
+    fn smooth_triangle_interpolates_normal() {
+        let tri = test_smooth_triangle();
+        let i = Intersection::new_with_uv(1., Box::new(tri.clone()), 0.45, 0.25);
+
+        assert_eq!(tri.normal_at_hit(Tuple::point(0., 0., 0.), &i), Tuple::vector(-0.5547, 0.83205, 0.));
+    }
+
+    #[test]
+    fn preparing_normal_on_smooth_triangle() {
+        let tri = test_smooth_triangle();
+        let i = Intersection::new_with_uv(1., Box::new(tri), 0.45, 0.25);
+        let r = Ray::new(Tuple::point(-0.2, 0.3, -2.), Tuple::vector(0., 0., 1.));
+        let comps = i.prepare_computations(r);
+
+        assert_eq!(comps.normalv, Tuple::vector(-0.5547, 0.83205, 0.));
+    }
+
+    #[test]
+    fn triangle_can_be_transformed_and_given_a_material() {
+        let m = Material { ambient: 1., ..Default::default() };
+        let t = test_triangle().with_transform(Matrix::translation(0., 0., 3.)).with_material(m.clone());
+        let r = Ray::new(Tuple::point(0., 0.5, -2.), Tuple::vector(0., 0., 1.));
+
+        assert_eq!(t.intersect_ref(r)[0].t, 5.);
+        assert_eq!(t.material(), &m);
+    }
+}
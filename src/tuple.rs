@@ -1,11 +1,12 @@
 use core::ops;
+use super::Scalar;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Tuple {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64
+    pub x: Scalar,
+    pub y: Scalar,
+    pub z: Scalar,
+    pub w: Scalar
 }
 
 pub const ORIGO: Tuple = Tuple { x: 0., y: 0., z: 0., w: 1. };
@@ -56,9 +57,9 @@ impl ops::Neg for Tuple {
     }
 }
 
-impl ops::Mul<f64> for Tuple {
+impl ops::Mul<Scalar> for Tuple {
     type Output = Tuple;
-    fn mul(self, rhs: f64) -> Tuple {
+    fn mul(self, rhs: Scalar) -> Tuple {
         Tuple {
             x: self.x * rhs,
             y: self.y * rhs,
@@ -68,9 +69,9 @@ impl ops::Mul<f64> for Tuple {
     }
 }
 
-impl ops::Div<f64> for Tuple {
+impl ops::Div<Scalar> for Tuple {
     type Output = Tuple;
-    fn div(self, rhs: f64) -> Tuple {
+    fn div(self, rhs: Scalar) -> Tuple {
         Tuple {
             x: self.x / rhs,
             y: self.y / rhs,
@@ -80,25 +81,33 @@ impl ops::Div<f64> for Tuple {
     }
 }
 
-impl From<[f64; 4]> for Tuple {
-    fn from(item: [f64; 4]) -> Self {
+impl From<[Scalar; 4]> for Tuple {
+    fn from(item: [Scalar; 4]) -> Self {
         Tuple { x: item[0], y: item[1], z: item[2], w: item[3] }
     }
 }
 
 impl Tuple {
-    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+    pub fn new(x: Scalar, y: Scalar, z: Scalar, w: Scalar) -> Self {
         Self {x, y, z, w}
     }
 
-    pub fn point(x: f64, y: f64, z: f64) -> Self {
+    pub fn point(x: Scalar, y: Scalar, z: Scalar) -> Self {
         Tuple::new(x, y, z, 1.)
     }
 
-    pub fn vector(x: f64, y: f64, z: f64) -> Self {
+    pub fn vector(x: Scalar, y: Scalar, z: Scalar) -> Self {
         Tuple::new(x, y, z, 0.)
     }
 
+    // Like ==, but with a caller chosen tolerance; w must still match exactly
+    pub fn approx_eq(&self, other: &Tuple, eps: Scalar) -> bool {
+        super::approx_eq_eps(self.x, other.x, eps) &&
+            super::approx_eq_eps(self.y, other.y, eps) &&
+            super::approx_eq_eps(self.z, other.z, eps) &&
+            self.w == other.w
+    }
+
     pub fn is_point(&self) -> bool {
         self.w == 1.
     }
@@ -107,7 +116,8 @@ impl Tuple {
         self.w == 0.
     }
 
-    pub fn magnitude(&self) -> f64 {
+    pub fn magnitude(&self) -> Scalar {
+        debug_assert!(self.is_vector(), "magnitude of a point: {:?}", self);
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
     }
 
@@ -116,14 +126,16 @@ impl Tuple {
         Tuple::vector(self.x / m, self.y / m, self.z / m)
     }
 
-    pub fn dot(&self, t: &Tuple) -> f64 {
+    pub fn dot(&self, t: &Tuple) -> Scalar {
         self.x * t.x +
             self.y * t.y +
             self.z * t.z +
             self.w * t.w
     }
 
+    // Only defined for vectors; the w components are ignored
     pub fn cross(&self, t: &Tuple) -> Self {
+        debug_assert!(self.is_vector() && t.is_vector(), "cross product of non-vectors: {:?} x {:?}", self, t);
         Tuple::vector(
             self.y * t.z - self.z * t.y,
             self.z * t.x - self.x * t.z,
@@ -131,6 +143,7 @@ impl Tuple {
     }
 
     pub fn reflect(&self, normal: Tuple) -> Self {
+        debug_assert!(self.is_vector() && normal.is_vector(), "reflecting non-vectors: {:?} off {:?}", self, normal);
         *self - normal * 2. * self.dot(&normal)
     }
 }
@@ -303,14 +316,14 @@ mod tests {
     #[test]
     fn computing_magnitude_of_vector_1_2_3() {
         let v = Tuple::vector(1., 2., 3.);
-        let expected = 14_f64.sqrt();
+        let expected = Scalar::sqrt(14.0);
         assert_eq!(expected, v.magnitude());
     }
 
     #[test]
     fn computing_magnitude_of_vector_1_2_3_neg() {
         let v = Tuple::vector(-1., -2., -3.);
-        let expected = 14_f64.sqrt();
+        let expected = Scalar::sqrt(14.0);
         assert_eq!(expected, v.magnitude());
     }
 
@@ -332,7 +345,11 @@ mod tests {
     fn magnitude_of_normalized_vector_is_1() {
         let v = Tuple::vector(1., 2., 3.);
         let norm = v.normalize();
+        #[cfg(not(feature = "f32"))]
         assert_eq!(1., norm.magnitude());
+        // f32 rounding leaves the magnitude a few ulps off 1
+        #[cfg(feature = "f32")]
+        assert!(crate::approx_eq(1., norm.magnitude()));
     }
 
     #[test]
@@ -354,6 +371,31 @@ mod tests {
         assert_eq!(Tuple::vector(1., -2., 1.), actual_ba);
     }
 
+    #[test]
+    fn cross_product_is_anticommutative() {
+        let vectors = [Tuple::vector(1., 2., 3.), Tuple::vector(-0.5, 4., 0.25), Tuple::vector(0., 0., -7.)];
+        for a in vectors.iter() {
+            for b in vectors.iter() {
+                assert_eq!(a.cross(b), -b.cross(a));
+                assert_eq!(a.cross(b).dot(a), 0.);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn cross_product_of_points_panics_in_debug_builds() {
+        Tuple::point(1., 2., 3.).cross(&Tuple::vector(2., 3., 4.));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn reflecting_point_panics_in_debug_builds() {
+        Tuple::point(1., -1., 0.).reflect(Tuple::vector(0., 1., 0.));
+    }
+
     #[test]
     fn reflecting_vector_approaching_45_degrees() {
         let v = Tuple::vector(1., -1., 0.);
@@ -366,10 +408,20 @@ mod tests {
     #[test]
     fn reflecting_vector_off_slanted_surface() {
         let v = Tuple::vector(0., -1., 0.);
-        let pv = 2.0f64.sqrt() / 2.0;
+        let pv = Scalar::sqrt(2.0) / 2.0;
         let n = Tuple::vector(pv, pv, 0.);
         let r = v.reflect(n);
 
         assert_eq!(r, Tuple::vector(1., 0., 0.));
     }
+
+    #[test]
+    fn comparing_tuples_with_custom_tolerance() {
+        let a = Tuple::point(0.5, -2., 0.25);
+        let b = Tuple::point(0.500001, -2., 0.25);
+
+        assert!(a.approx_eq(&b, 1e-5));
+        assert!(!a.approx_eq(&b, 1e-7));
+        assert!(!a.approx_eq(&Tuple::vector(0.5, -2., 0.25), 1.));
+    }
 }
\ No newline at end of file
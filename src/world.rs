@@ -1,27 +1,174 @@
 use super::sphere::Sphere;
-use super::shape::BoxShape;
+use super::shape::{BoxShape, Shape};
+use super::group::Group;
 use super::color::{Color, WHITE, BLACK};
-use super::tuple::Tuple;
+use super::tuple::{Tuple, VECTOR_Y_UP};
 use super::matrix::Matrix;
 use super::ray::Ray;
-use super::material::{Material, DEFAULT_AMBIENT, DEFAULT_SHININESS};
-use super::intersection::Intersections;
+use super::material::{Material, DEFAULT_AMBIENT, DEFAULT_SHININESS, DEFAULT_REFLECTIVE};
+use super::intersection::{IntersectionRef, Intersections, ObjectKey, hit_ref, sort_refs};
 use super::precomputed_data::PrecomputedData;
 
-use super::light::PointLight;
+use super::light::{BoxLight, Light, PointLight};
+use super::sampling::Rng;
+use super::{Scalar, EPSILON};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+// How many times a ray may bounce between reflective surfaces before giving up
+pub const MAX_REFLECTION_DEPTH: usize = 5;
+
+// Quality knobs for a render; the defaults match color_at with MAX_REFLECTION_DEPTH
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderSettings {
+    pub max_recursion_depth: usize,
+    // How far secondary rays start off the surface, so a surface doesn't shadow or reflect itself
+    pub shadow_bias: Scalar,
+    // Rays per pixel along each axis, see Camera::render_antialiased
+    pub antialias_samples: usize,
+    // How many lights to shade each hit with, picked by importance; None shades with all of them
+    pub sampled_lights: Option<usize>
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings { max_recursion_depth: MAX_REFLECTION_DEPTH, shadow_bias: EPSILON, antialias_samples: 1, sampled_lights: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneWarning {
+    NoLight,
+    NonFiniteTransform { object: usize },
+    SingularTransform { object: usize },
+    LightInsideObject { object: usize }
+}
+
+impl fmt::Display for SceneWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneWarning::NoLight =>
+                write!(f, "the world has no lights, every hit will shade black"),
+            SceneWarning::NonFiniteTransform { object } =>
+                write!(f, "object {} has a transform containing NaN or infinite values", object),
+            SceneWarning::SingularTransform { object } =>
+                write!(f, "object {} has a non-invertible transform (e.g. a zero scale)", object),
+            SceneWarning::LightInsideObject { object } =>
+                write!(f, "a light is inside object {} and cannot illuminate anything outside it", object)
+        }
+    }
+}
+
+// Per thread, so tests running in parallel don't see each other's shadow rays
+#[cfg(test)]
+thread_local! {
+    static SHADOW_RAYS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+fn shadow_rays_on_this_thread() -> usize {
+    SHADOW_RAYS.with(|n| n.get())
+}
+
+// Memoized is_shadowed results keyed by quantized point
+struct ShadowCache {
+    cell_size: Scalar,
+    cells: RwLock<ShadowCells>
+}
+
+// Results recorded while the world was at the given generation
+struct ShadowCells {
+    generation: usize,
+    // Keyed by light index as well, a point can be shadowed from one light but not another
+    shadowed: HashMap<(usize, i64, i64, i64), bool>
+}
 
 pub struct World {
-    pub light: Option<PointLight>,
-    pub objects: Vec<BoxShape>
+    lights: Vec<BoxLight>,
+    objects: Vec<BoxShape>,
+    // Frame time handed to animated patterns
+    pub time: Scalar,
+    // Root seed for every stochastic sample taken while rendering this world
+    pub seed: u64,
+    // Bumped whenever shapes or lights may have changed, dropping stale shadow cache entries
+    generation: usize,
+    shadow_cache: Option<ShadowCache>
+}
+
+// An empty world, to be filled with add_shape and add_light
+impl Default for World {
+    fn default() -> Self {
+        World::with_lights(vec![], vec![])
+    }
 }
 
 impl World {
+    // Single light constructor, kept for scenes with at most one lamp
     pub fn new(light: Option<PointLight>, objects: Vec<BoxShape>) -> Self {
-        World { light, objects }
+        World::with_lights(light.into_iter().map(|l| Box::new(l) as BoxLight).collect(), objects)
+    }
+
+    pub fn with_lights(lights: Vec<BoxLight>, objects: Vec<BoxShape>) -> Self {
+        World { lights, objects, time: 0., seed: 0, generation: 0, shadow_cache: None }
+    }
+
+    pub fn objects(&self) -> &[BoxShape] {
+        &self.objects
+    }
+
+    pub fn lights(&self) -> &[BoxLight] {
+        &self.lights
+    }
+
+    // The mutable accessors assume the caller changes something, so cached shadows are dropped
+    pub fn objects_mut(&mut self) -> &mut Vec<BoxShape> {
+        self.generation += 1;
+        &mut self.objects
+    }
+
+    pub fn lights_mut(&mut self) -> &mut Vec<BoxLight> {
+        self.generation += 1;
+        &mut self.lights
+    }
+
+    pub fn add_shape(&mut self, shape: BoxShape) {
+        self.objects_mut().push(shape);
+    }
+
+    pub fn add_light(&mut self, light: impl Light + 'static) {
+        self.lights_mut().push(Box::new(light));
+    }
+
+    // Removes every shape and light, keeping settings such as time and seed
+    pub fn clear(&mut self) {
+        self.objects_mut().clear();
+        self.lights_mut().clear();
+    }
+
+    // Gathers the bounded objects into one group and splits it into a bounding volume hierarchy, so
+    // rays skip whole clusters instead of testing every object. Unbounded shapes such as planes stay
+    // at the top, since they would make the root's bounds infinite and nothing could be split.
+    pub fn divide(&mut self, threshold: usize) {
+        let (bounded, mut unbounded): (Vec<BoxShape>, Vec<BoxShape>) = std::mem::take(self.objects_mut())
+            .into_iter()
+            .partition(|o| o.parent_space_bounds().is_finite());
+        if !bounded.is_empty() {
+            let mut root = Group::new(None, bounded);
+            root.divide(threshold);
+            unbounded.push(Box::new(root));
+        }
+        self.objects = unbounded;
+    }
+
+    // Points closer than cell_size share one shadow test; only worthwhile for static scenes
+    pub fn enable_shadow_cache(&mut self, cell_size: Scalar) {
+        let cells = ShadowCells { generation: self.generation, shadowed: HashMap::new() };
+        self.shadow_cache = Some(ShadowCache { cell_size, cells: RwLock::new(cells) });
     }
 
     fn default_objects() -> Vec<BoxShape> {
-        let m = Material::new(Color::new(0.8, 1., 0.6), DEFAULT_AMBIENT, 0.7, 0.2, DEFAULT_SHININESS, None);
+        let m = Material::new(Color::new(0.8, 1., 0.6), DEFAULT_AMBIENT, 0.7, 0.2, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
         let s1 = Sphere::new_boxed(Some(m), None);
         let tr = Matrix::scaling(0.5, 0.5, 0.5);
         let s2 = Sphere::new_boxed(None, Some(tr));
@@ -33,52 +180,254 @@ impl World {
         World::new(light, World::default_objects())
     }
 
-    pub fn color_at(&self, ray: Ray) -> Color {
-        let xs = self.intersect(ray);
-        match xs.hit() {
+    pub fn color_at(&self, ray: Ray, remaining: usize) -> Color {
+        self.trace(ray, &RenderSettings::default(), remaining)
+    }
+
+    pub fn color_at_with(&self, ray: Ray, settings: &RenderSettings) -> Color {
+        self.trace(ray, settings, settings.max_recursion_depth)
+    }
+
+    fn trace(&self, ray: Ray, settings: &RenderSettings, remaining: usize) -> Color {
+        let xs = self.intersect_ref(ray);
+        match hit_ref(&xs) {
             None => BLACK,
             Some(i) => {
-                let comps = i.prepare_computations(ray);
-                self.shade_hit(comps)
+                let mut comps = i.into_owned().prepare_computations_with(ray, &xs);
+                comps.over_point = comps.point + comps.normalv * settings.shadow_bias;
+                comps.under_point = comps.point - comps.normalv * settings.shadow_bias;
+                self.shade_hit(comps, settings, remaining)
             }
         }
     }
 
-    fn intersect(&self, ray: Ray) -> Intersections {
-        let mut xs = Intersections::new(vec![]);
-        for o in self.objects.iter() {
-            xs.extend(o.intersect(ray));
-        }
+    // Every intersection of the ray with the world, sorted by t
+    pub fn intersect(&self, ray: Ray) -> Intersections {
+        Intersections::from_refs(self.intersect_ref(ray))
+    }
+
+    fn intersect_ref(&self, ray: Ray) -> Vec<IntersectionRef<'_>> {
+        let mut xs: Vec<IntersectionRef> = self.objects.iter().flat_map(|o| o.intersect_ref(ray)).collect();
+        sort_refs(&mut xs);
         xs
     }
 
-    fn shade_hit(&self, comps: PrecomputedData) -> Color {
-        comps.object.material().lighting(
+    pub fn validate(&self) -> Result<(), Vec<SceneWarning>> {
+        let mut warnings = vec![];
+        if self.lights.is_empty() {
+            warnings.push(SceneWarning::NoLight);
+        }
+        for (object, shape) in self.objects.iter().enumerate() {
+            let transform = shape.transformation();
+            let finite = (0..4).all(|row| (0..4).all(|col| transform[row][col].is_finite()));
+            if !finite {
+                warnings.push(SceneWarning::NonFiniteTransform { object });
+            } else if transform.inverse().is_none() {
+                warnings.push(SceneWarning::SingularTransform { object });
+            } else {
+                // A point is enclosed by a closed shape when a ray through it hits on both sides
+                let enclosed = self.lights.iter().filter_map(|light| light.position()).any(|position| {
+                    let xs = shape.intersect_ref(Ray::new(position, VECTOR_Y_UP));
+                    xs.iter().any(|i| i.t < 0.) && xs.iter().any(|i| i.t > 0.)
+                });
+                if enclosed {
+                    warnings.push(SceneWarning::LightInsideObject { object });
+                }
+            }
+        }
+        if warnings.is_empty() { Ok(()) } else { Err(warnings) }
+    }
+
+    fn shade_hit(&self, comps: PrecomputedData, settings: &RenderSettings, remaining: usize) -> Color {
+        // Each light contributes independently; without lights the surface stays black
+        let material = comps.object.material();
+        let shade = |index: usize| material.surface_lighting(
             &*(comps.object),
-            &self.light.unwrap(),
+            &*self.lights[index],
             comps.point,
             comps.eyev,
             comps.normalv,
-            self.is_shadowed(comps.over_point))
+            self.light_visibility(comps.over_point, index),
+            self.time) + self.subsurface(&comps, index);
+        let surface = match settings.sampled_lights.and_then(|count| self.sample_lights(comps.point, count)) {
+            None => (0..self.lights.len()).map(shade).fold(BLACK, |acc, c| acc + c),
+            Some(picks) => picks.into_iter().map(|(index, weight)| shade(index) * weight).fold(BLACK, |acc, c| acc + c)
+        };
+        let reflected = self.reflected(&comps, settings, remaining);
+        let refracted = self.refracted(&comps, settings, remaining);
+        if material.reflective > 0. && material.transparency > 0. {
+            let reflectance = comps.schlick();
+            surface + reflected * reflectance + refracted * (1. - reflectance)
+        } else {
+            surface + reflected + refracted
+        }
+    }
+
+    // Picks count lights at random, each with a probability proportional to its estimated
+    // contribution (intensity over squared distance), along with the weight that keeps the sum
+    // of their shading unbiased. None when every light should be shaded instead.
+    fn sample_lights(&self, point: Tuple, count: usize) -> Option<Vec<(usize, Scalar)>> {
+        if count == 0 || count >= self.lights.len() {
+            return None;
+        }
+        let estimates: Vec<Scalar> = self.lights.iter()
+            .map(|light| {
+                let intensity = light.intensity_at(point);
+                let distance = light.distance_from(point);
+                let falloff = if distance.is_finite() { (distance * distance).max(EPSILON) } else { 1. };
+                (intensity.r + intensity.g + intensity.b) / falloff
+            })
+            .collect();
+        let total: Scalar = estimates.iter().sum();
+        let last_lit = estimates.iter().rposition(|&e| e > 0.)?;
+        // A stream apart from the one light_visibility jitters area lights with
+        let mut rng = Rng::new(self.point_seed(point).wrapping_add(1));
+        let picks = (0..count)
+            .map(|_| {
+                let mut target = rng.next_scalar() * total;
+                let index = estimates.iter()
+                    .position(|&e| { target -= e; target < 0. })
+                    .unwrap_or(last_lit);
+                (index, total / (estimates[index] * count as Scalar))
+            })
+            .collect();
+        Some(picks)
+    }
+
+    // Seeded from the point, so the jitter does not depend on the order pixels are shaded in
+    fn point_seed(&self, point: Tuple) -> u64 {
+        [point.x, point.y, point.z].iter()
+            .fold(self.seed, |seed, c| Rng::new(seed ^ (c / EPSILON).round() as i64 as u64).next_u64())
+    }
+
+    // Light scattered through the object is only blocked by other objects, not by the object itself
+    fn subsurface(&self, comps: &PrecomputedData, light: usize) -> Color {
+        let material = comps.object.material();
+        if material.subsurface.1 <= 0. {
+            return BLACK;
+        }
+        let visibility = self.visibility(comps.over_point, light, Some(comps.key()));
+        material.subsurface_at(&*self.lights[light], comps.point, comps.normalv) * visibility
     }
 
-    fn is_shadowed(&self, point: Tuple) -> bool {
-        let v = self.light.unwrap().position - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
-        let r = Ray::new(point, direction);
-        let intersections = self.intersect(r);
-        let h = intersections.hit();
-        h != None && h.unwrap().t < distance
+    pub fn reflected_color(&self, comps: &PrecomputedData, remaining: usize) -> Color {
+        self.reflected(comps, &RenderSettings::default(), remaining)
+    }
+
+    fn reflected(&self, comps: &PrecomputedData, settings: &RenderSettings, remaining: usize) -> Color {
+        let reflective = comps.object.material().reflective;
+        if remaining == 0 || reflective == 0. {
+            return BLACK;
+        }
+        let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
+        self.trace(reflect_ray, settings, remaining - 1) * reflective
+    }
+
+    pub fn refracted_color(&self, comps: &PrecomputedData, remaining: usize) -> Color {
+        self.refracted(comps, &RenderSettings::default(), remaining)
+    }
+
+    fn refracted(&self, comps: &PrecomputedData, settings: &RenderSettings, remaining: usize) -> Color {
+        let transparency = comps.object.material().transparency;
+        if remaining == 0 || transparency == 0. {
+            return BLACK;
+        }
+        // Snell's law; no refracted ray exists past the critical angle (total internal reflection)
+        let n_ratio = comps.n1 / comps.n2;
+        let cos_i = comps.eyev.dot(&comps.normalv);
+        let sin2_t = n_ratio * n_ratio * (1. - cos_i * cos_i);
+        if sin2_t > 1. {
+            return BLACK;
+        }
+        let cos_t = (1. - sin2_t).sqrt();
+        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        let refract_ray = Ray::new(comps.under_point, direction);
+        self.trace(refract_ray, settings, remaining - 1) * transparency
+    }
+
+    // Unshadowed fraction of the light at the point. Area lights average shadow rays towards
+    // samples across their surface, so points in a penumbra get values between 0 and 1.
+    pub fn light_visibility(&self, point: Tuple, light: usize) -> Scalar {
+        self.visibility(point, light, None)
+    }
+
+    // Objects with the ignored key don't cast shadows here
+    fn visibility(&self, point: Tuple, light: usize, ignored: Option<ObjectKey>) -> Scalar {
+        let samples = self.lights[light].surface_samples(&mut Rng::new(self.point_seed(point)));
+        if samples.is_empty() {
+            let shadowed = match ignored {
+                None => self.is_shadowed(point, light),
+                Some(_) => self.cast_shadow_ray(point, light, ignored)
+            };
+            return if shadowed { 0. } else { 1. };
+        }
+        let lit = samples.iter().filter(|&&sample| !self.any_hit_between_ignoring(point, sample, ignored)).count();
+        lit as Scalar / samples.len() as Scalar
+    }
+
+    fn is_shadowed(&self, point: Tuple, light: usize) -> bool {
+        let cache = match &self.shadow_cache {
+            None => return self.cast_shadow_ray(point, light, None),
+            Some(cache) => cache
+        };
+        let quantize = |v: Scalar| (v / cache.cell_size).round() as i64;
+        let key = (light, quantize(point.x), quantize(point.y), quantize(point.z));
+        {
+            let cells = cache.cells.read().unwrap();
+            if cells.generation == self.generation {
+                if let Some(&shadowed) = cells.shadowed.get(&key) {
+                    return shadowed;
+                }
+            }
+        }
+        // Cast outside the lock, so other threads keep reading while this ray is traced
+        let shadowed = self.cast_shadow_ray(point, light, None);
+        let mut cells = cache.cells.write().unwrap();
+        if cells.generation != self.generation {
+            cells.generation = self.generation;
+            cells.shadowed.clear();
+        }
+        cells.shadowed.insert(key, shadowed);
+        shadowed
+    }
+
+    fn cast_shadow_ray(&self, point: Tuple, light: usize, ignored: Option<ObjectKey>) -> bool {
+        #[cfg(test)]
+        SHADOW_RAYS.with(|n| n.set(n.get() + 1));
+        let distance = self.lights[light].distance_from(point);
+        let direction = self.lights[light].direction_to(point);
+        self.any_hit_along(Ray::new(point, direction), distance, ignored)
+    }
+
+    // Whether a shadow casting object lies strictly between the two points
+    pub fn any_hit_between(&self, from: Tuple, to: Tuple) -> bool {
+        self.any_hit_between_ignoring(from, to, None)
+    }
+
+    fn any_hit_between_ignoring(&self, from: Tuple, to: Tuple, ignored: Option<ObjectKey>) -> bool {
+        let v = to - from;
+        self.any_hit_along(Ray::new(from, v.normalize()), v.magnitude(), ignored)
+    }
+
+    // Stops at the first blocking intersection, so neither sorting nor the remaining objects are needed
+    fn any_hit_along(&self, ray: Ray, distance: Scalar, ignored: Option<ObjectKey>) -> bool {
+        self.objects.iter().any(|o| o.intersect_ref(ray).iter()
+            .any(|i| i.t > 0. && i.t < distance && i.object.material().casts_shadow && Some(i.key()) != ignored))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::light::{AreaLight, DirectionalLight, Light};
     use crate::tuple::ORIGO;
     use crate::material::{DEFAULT_DIFFUSE, DEFAULT_SPECULAR};
     use crate::intersection::Intersection;
+    use crate::Scalar;
+    use crate::consts::PI;
+    use crate::plane::Plane;
+    use crate::EPSILON;
+    use crate::shape::{Shape, ShapeId, Transforms};
 
     #[test]
     fn empty_world()
@@ -86,7 +435,27 @@ mod tests {
         let w = World::new(None, vec![]);
 
         assert_eq!(w.objects.len(), 0);
-        assert_eq!(w.light, None);
+        assert!(w.lights.is_empty());
+    }
+
+    #[test]
+    fn building_world_incrementally() {
+        let mut w = World::default();
+        assert_eq!(w.objects.len(), 0);
+        assert!(w.lights.is_empty());
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        assert_eq!(w.color_at(r, MAX_REFLECTION_DEPTH), BLACK);
+
+        w.add_shape(Sphere::default_boxed());
+        w.add_shape(Sphere::new_boxed(None, Some(Matrix::translation(0., 0., 3.))));
+        w.add_light(PointLight::new(Tuple::point(-10., 10., -10.), WHITE));
+        let ts: Vec<Scalar> = w.intersect(r).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4., 6., 7., 9.]);
+        assert_ne!(w.color_at(r, MAX_REFLECTION_DEPTH), BLACK);
+
+        w.clear();
+        assert!(w.objects.is_empty() && w.lights.is_empty());
+        assert!(w.intersect(r).is_empty());
     }
 
     #[test]
@@ -94,7 +463,7 @@ mod tests {
     {
         let light = PointLight::new(Tuple::point(-10., 10., -10.), WHITE);
         let w = World::default_world();
-        assert_eq!(w.light.unwrap(), light);
+        assert_eq!(w.lights, vec![light.box_clone()]);
 
         let m = w.objects[0].material();
         assert_eq!(m.color, Color::new(0.8, 1., 0.6));
@@ -109,7 +478,7 @@ mod tests {
     fn intersect_world_with_ray() {
         let w = World::default_world();
         let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
-        let xs = w.intersect(r);
+        let xs = w.intersect_ref(r);
 
         assert_eq!(xs.len(), 4);
         assert_eq!(xs[0].t, 4.);
@@ -118,6 +487,27 @@ mod tests {
         assert_eq!(xs[3].t, 6.);
     }
 
+    #[test]
+    fn public_intersect_returns_sorted_intersections() {
+        let w = World::default_world();
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = w.intersect(r);
+        let ts: Vec<Scalar> = xs.iter().map(|i| i.t).collect();
+
+        assert_eq!(ts, vec![4., 4.5, 5.5, 6.]);
+        assert_eq!(xs.hit().unwrap().t, 4.);
+        assert_eq!(&xs[1].object, &w.objects[1]);
+    }
+
+    #[test]
+    fn intersecting_empty_world() {
+        let w = World::new(None, vec![]);
+        let xs = w.intersect(Ray::new(ORIGO, Tuple::vector(0., 0., 1.)));
+
+        assert!(xs.is_empty());
+        assert!(xs.hit().is_none());
+    }
+
     #[test]
     fn shading_intersection() {
         let w = World::default_world();
@@ -125,7 +515,7 @@ mod tests {
         let shape = &w.objects[0];
         let i = Intersection::new(4., shape.clone());
         let comps = i.prepare_computations(r);
-        let c = w.shade_hit(comps);
+        let c = w.shade_hit(comps, &RenderSettings::default(), MAX_REFLECTION_DEPTH);
 
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
@@ -138,7 +528,7 @@ mod tests {
         let shape = &w.objects[1];
         let i = Intersection::new(0.5, shape.clone());
         let comps = i.prepare_computations(r);
-        let c = w.shade_hit(comps);
+        let c = w.shade_hit(comps, &RenderSettings::default(), MAX_REFLECTION_DEPTH);
 
         assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498));
     }
@@ -147,7 +537,7 @@ mod tests {
     fn color_when_ray_misses() {
         let w = World::default_world();
         let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 1., 0.));
-        let c = w.color_at(r);
+        let c = w.color_at(r, MAX_REFLECTION_DEPTH);
 
         assert_eq!(c, BLACK);
     }
@@ -156,7 +546,7 @@ mod tests {
     fn color_when_ray_hits() {
         let w = World::default_world();
         let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
-        let c = w.color_at(r);
+        let c = w.color_at(r, MAX_REFLECTION_DEPTH);
 
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
@@ -167,26 +557,105 @@ mod tests {
         // to 1.0 for both spheres. But due to the (mostly) immutable design I've opted for, this is not really
         // possible. Rather most of the setup code needs to be duplicated here. This is embarrasing enough for me
         // to come back later and fix it.
-        let m1 = Material::new(Color::new(0.8, 1., 0.6), 1., 0.7, 0.2, DEFAULT_SHININESS, None);
+        let m1 = Material::new(Color::new(0.8, 1., 0.6), 1., 0.7, 0.2, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
         let s1 = Sphere::new_boxed(Some(m1), None);
         let tr = Matrix::scaling(0.5, 0.5, 0.5);
         let color = WHITE;
-        let m2 = Material::new(color, 1., DEFAULT_DIFFUSE, DEFAULT_SPECULAR, DEFAULT_SHININESS, None);
+        let m2 = Material::new(color, 1., DEFAULT_DIFFUSE, DEFAULT_SPECULAR, DEFAULT_SHININESS, DEFAULT_REFLECTIVE, None);
         let s2 = Sphere::new_boxed(Some(m2), Some(tr));
         let light = Some(PointLight::new(Tuple::point(-10., 10., -10.), WHITE));
         let w = World::new(light, vec![s1, s2]);
         let r = Ray::new(Tuple::point(0., 0., 0.75), Tuple::vector(0., 0., -1.));
-        let c = w.color_at(r);
+        let c = w.color_at(r, MAX_REFLECTION_DEPTH);
 
         assert_eq!(c, color);
     }
 
+    #[test]
+    fn any_hit_between_point_and_light() {
+        let w = World::default_world();
+        let light = Tuple::point(-10., 10., -10.);
+
+        assert!(!w.any_hit_between(Tuple::point(0., 10., 0.), light));
+        assert!(w.any_hit_between(Tuple::point(10., -10., 10.), light));
+        assert!(!w.any_hit_between(Tuple::point(-20., 20., -20.), light));
+        assert!(!w.any_hit_between(Tuple::point(-2., 2., -2.), light));
+    }
+
+    #[test]
+    fn hit_exactly_at_target_does_not_count() {
+        let w = World::new(None, vec![Sphere::default_boxed()]);
+
+        assert!(!w.any_hit_between(Tuple::point(0., 0., -5.), Tuple::point(0., 0., -1.)));
+        assert!(w.any_hit_between(Tuple::point(0., 0., -5.), Tuple::point(0., 0., -0.99)));
+    }
+
+    // Fails the test if anything intersects it
+    #[derive(Debug, Clone, PartialEq)]
+    struct Tripwire(Material, ShapeId, Transforms);
+
+    impl Shape for Tripwire {
+        fn box_clone(&self) -> BoxShape {
+            Box::new(self.clone())
+        }
+
+        fn box_eq(&self, other: &dyn std::any::Any) -> bool {
+            other.downcast_ref::<Self>().is_some_and(|a| self == a)
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn inner_intersect_ref(&self, _object_ray: Ray) -> Vec<IntersectionRef<'_>> {
+            panic!("shadow test kept intersecting after a blocker was found");
+        }
+
+        fn inner_normal_at(&self, _object_point: Tuple) -> Tuple {
+            VECTOR_Y_UP
+        }
+
+        fn material(&self) -> &Material {
+            &self.0
+        }
+
+        fn material_mut(&mut self) -> &mut Material {
+            &mut self.0
+        }
+
+        fn transforms(&self) -> &Transforms {
+            &self.2
+        }
+
+        fn transforms_mut(&mut self) -> &mut Transforms {
+            &mut self.2
+        }
+
+        fn id(&self) -> usize {
+            self.1.get()
+        }
+
+        fn renew_ids(&mut self) {
+            self.1 = ShapeId::next();
+        }
+    }
+
+    #[test]
+    fn shadow_test_stops_at_first_blocker() {
+        let blocker = Sphere::default_boxed();
+        let w = World::new(Some(PointLight::new(Tuple::point(0., 10., 0.), WHITE)),
+                           vec![blocker, Box::new(Tripwire(Material::default(), ShapeId::next(), Transforms::default()))]);
+
+        assert!(w.any_hit_between(Tuple::point(0., -5., 0.), Tuple::point(0., 10., 0.)));
+        assert!(w.is_shadowed(Tuple::point(0., -5., 0.), 0));
+    }
+
     #[test]
     fn no_shadow_when_nothing_collinear_with_point_and_light() {
         let w = World::default_world();
         let p = Tuple::point(0., 10., 0.);
 
-        assert!(!w.is_shadowed(p));
+        assert!(!w.is_shadowed(p, 0));
     }
 
     #[test]
@@ -194,7 +663,7 @@ mod tests {
         let w = World::default_world();
         let p = Tuple::point(10., -10., 10.);
 
-        assert!(w.is_shadowed(p));
+        assert!(w.is_shadowed(p, 0));
     }
 
     #[test]
@@ -202,7 +671,7 @@ mod tests {
         let w = World::default_world();
         let p = Tuple::point(-20., 20., -20.);
 
-        assert!(!w.is_shadowed(p));
+        assert!(!w.is_shadowed(p, 0));
     }
 
     #[test]
@@ -210,7 +679,7 @@ mod tests {
         let w = World::default_world();
         let p = Tuple::point(-2., 2., -2.);
 
-        assert!(!w.is_shadowed(p));
+        assert!(!w.is_shadowed(p, 0));
     }
 
     #[test]
@@ -225,8 +694,432 @@ mod tests {
         let r = Ray::new(Tuple::point(0., 0., 5.), Tuple::vector(0., 0., 1.));
         let i = Intersection::new(4., s2);
         let comps = i.prepare_computations(r);
-        let c = w.shade_hit(comps);
+        let c = w.shade_hit(comps, &RenderSettings::default(), MAX_REFLECTION_DEPTH);
 
         assert_eq!(c, Color::new(0.1, 0.1, 0.1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn subsurface_lights_world_surface_past_terminator() {
+        let light = PointLight::new(Tuple::point(10., 0., 1.), WHITE);
+        let plain = Material { ambient: 0., ..Default::default() };
+        let waxy = Material { subsurface: (Color::new(1., 0.5, 0.5), 0.3), ..plain.clone() };
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let render = |objects: Vec<BoxShape>| World::new(Some(light), objects).color_at(r, MAX_REFLECTION_DEPTH);
+        let blocker = Sphere::new_boxed(None, Some(Matrix::translation(5., 0., 0.)));
+
+        assert_eq!(render(vec![Sphere::new_boxed(Some(plain), None)]), BLACK);
+        let c = render(vec![Sphere::new_boxed(Some(waxy.clone()), None)]);
+        assert!(c.r > 0. && c.g > 0. && c.g < c.r);
+        assert_eq!(render(vec![Sphere::new_boxed(Some(waxy), None), blocker]), BLACK);
+    }
+
+    #[test]
+    fn importance_sampled_lights_converge_to_full_sum() {
+        let mut w = World::new(None, vec![Plane::default_boxed()]);
+        for i in 0..20 {
+            let a = i as Scalar * 0.7;
+            let brightness = 0.2 + 0.04 * i as Scalar;
+            w.add_light(PointLight::new(Tuple::point(a.cos() * (2. + i as Scalar), 3. + (i % 4) as Scalar, a.sin() * 4.), Color::new(brightness, brightness, brightness)));
+        }
+        let r = Ray::new(Tuple::point(0., 2., -3.), Tuple::vector(0., -2., 3.).normalize());
+        let full = w.color_at_with(r, &RenderSettings::default());
+        let sampled = RenderSettings { sampled_lights: Some(2), ..Default::default() };
+        const RUNS: u64 = 4000;
+        let mut sum = BLACK;
+        for seed in 0..RUNS {
+            w.seed = seed;
+            sum = sum + w.color_at_with(r, &sampled);
+        }
+        let estimate = sum / RUNS as Scalar;
+
+        assert_ne!(w.color_at_with(r, &sampled), full);
+        assert!((estimate.r - full.r).abs() < full.r * 0.03);
+        let all = RenderSettings { sampled_lights: Some(20), ..Default::default() };
+        assert_eq!(w.color_at_with(r, &all), full);
+    }
+
+    #[test]
+    fn default_world_is_valid() {
+        assert_eq!(World::default_world().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_singular_transform() {
+        let light = Some(PointLight::new(Tuple::point(-10., 10., -10.), WHITE));
+        let flat = Sphere::new_boxed(None, Some(Matrix::scaling(0., 1., 1.)));
+        let w = World::new(light, vec![Sphere::default_boxed(), flat]);
+
+        assert_eq!(w.validate(), Err(vec![SceneWarning::SingularTransform { object: 1 }]));
+    }
+
+    #[test]
+    fn validate_reports_nan_transform_and_missing_light() {
+        let broken = Sphere::new_boxed(None, Some(Matrix::translation(Scalar::NAN, 0., 0.)));
+        let w = World::new(None, vec![broken]);
+        let warnings = w.validate().unwrap_err();
+
+        assert_eq!(warnings, vec![SceneWarning::NoLight, SceneWarning::NonFiniteTransform { object: 0 }]);
+        assert!(warnings[1].to_string().contains("object 0"));
+    }
+
+    #[test]
+    fn validate_reports_light_inside_object() {
+        let light = Some(PointLight::new(ORIGO, WHITE));
+        let w = World::new(light, World::default_objects());
+
+        assert_eq!(w.validate(), Err(vec![
+            SceneWarning::LightInsideObject { object: 0 },
+            SceneWarning::LightInsideObject { object: 1 }]));
+    }
+
+    fn reflective_floor() -> BoxShape {
+        let m = Material { reflective: 0.5, ..Default::default() };
+        Plane::new_boxed(Some(m), Some(Matrix::translation(0., -1., 0.)))
+    }
+
+    #[test]
+    fn reflected_color_for_nonreflective_material() {
+        let w = World::default_world();
+        let r = Ray::new(ORIGO, Tuple::vector(0., 0., 1.));
+        let i = Intersection::new(1., w.objects[1].clone());
+        let comps = i.prepare_computations(r);
+
+        assert_eq!(w.reflected_color(&comps, MAX_REFLECTION_DEPTH), BLACK);
+    }
+
+    #[test]
+    fn reflective_plane_under_sphere() {
+        let mut w = World::default_world();
+        let floor = reflective_floor();
+        w.add_shape(floor.clone());
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -pv, pv));
+        let i = Intersection::new(Scalar::sqrt(2.0), floor);
+
+        let comps = i.prepare_computations(r);
+        assert_eq!(w.reflected_color(&comps, MAX_REFLECTION_DEPTH), Color::new(0.19033, 0.23792, 0.14275));
+
+        let comps = i.prepare_computations(r);
+        assert_eq!(w.shade_hit(comps, &RenderSettings::default(), MAX_REFLECTION_DEPTH), Color::new(0.87676, 0.92434, 0.82917));
+    }
+
+    #[test]
+    fn reflected_color_at_maximum_recursive_depth() {
+        let mut w = World::default_world();
+        let floor = reflective_floor();
+        w.add_shape(floor.clone());
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -pv, pv));
+        let comps = Intersection::new(Scalar::sqrt(2.0), floor).prepare_computations(r);
+
+        assert_eq!(w.reflected_color(&comps, 0), BLACK);
+    }
+
+    #[test]
+    fn mutually_reflective_surfaces_terminate() {
+        let m = Material { reflective: 1., ..Default::default() };
+        let lower = Plane::new_boxed(Some(m.clone()), Some(Matrix::translation(0., -1., 0.)));
+        let upper = Plane::new_boxed(Some(m), Some(Matrix::rotation_x(PI) * Matrix::translation(0., -1., 0.)));
+        let w = World::new(Some(PointLight::new(ORIGO, WHITE)), vec![lower, upper]);
+        let r = Ray::new(ORIGO, Tuple::vector(0., 1., 0.));
+
+        let c = w.color_at(r, MAX_REFLECTION_DEPTH);
+        assert!(c.r.is_finite() && c.r > 0.);
+    }
+
+    #[test]
+    fn refracted_color_with_opaque_surface() {
+        let w = World::default_world();
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = w.intersect_ref(r);
+        let comps = xs[0].into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!(w.refracted_color(&comps, MAX_REFLECTION_DEPTH), BLACK);
+    }
+
+    fn glassy_default_world() -> World {
+        let mut w = World::default_world();
+        let glass = Material { transparency: 1., refractive_index: 1.5, ..w.objects[0].material().clone() };
+        w.objects_mut()[0] = Sphere::new_boxed(Some(glass), None);
+        w
+    }
+
+    #[test]
+    fn refracted_color_at_maximum_recursive_depth() {
+        let w = glassy_default_world();
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = w.intersect_ref(r);
+        let comps = xs[0].into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!(w.refracted_color(&comps, 0), BLACK);
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let w = glassy_default_world();
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0., 0., pv), Tuple::vector(0., 1., 0.));
+        let xs = w.intersect_ref(r);
+        // Inside the sphere, so the hit to look at is the exit (the second of the outer sphere's hits)
+        let exit = xs.iter().filter(|i| i.object.box_eq(w.objects[0].as_any())).nth(1).unwrap();
+        let comps = exit.into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!((comps.n1, comps.n2), (1.5, 1.));
+        assert_eq!(w.refracted_color(&comps, MAX_REFLECTION_DEPTH), BLACK);
+    }
+
+    #[test]
+    fn shade_hit_with_transparent_material() {
+        let mut w = World::default_world();
+        let floor_material = Material { transparency: 0.5, refractive_index: 1.5, ..Default::default() };
+        let floor = Plane::new_boxed(Some(floor_material), Some(Matrix::translation(0., -1., 0.)));
+        let ball_material = Material { color: Color::new(1., 0., 0.), ambient: 0.5, ..Default::default() };
+        let ball = Sphere::new_boxed(Some(ball_material), Some(Matrix::translation(0., -3.5, -0.5)));
+        w.add_shape(floor.clone());
+        w.add_shape(ball);
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -pv, pv));
+        let xs = vec![IntersectionRef::new(Scalar::sqrt(2.0), &*floor)];
+        let comps = xs[0].into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!(w.shade_hit(comps, &RenderSettings::default(), MAX_REFLECTION_DEPTH), Color::new(0.93642, 0.68642, 0.68642));
+    }
+
+    fn shadowed_floor_world() -> World {
+        let mut w = World::default_world();
+        w.add_shape(Plane::new_boxed(None, Some(Matrix::translation(0., -1., 0.))));
+        w
+    }
+
+    #[test]
+    fn transparent_sphere_without_shadow_leaves_floor_lit() {
+        let floor_point = Tuple::point(0., -0.99, 0.);
+        let mut w = World::new(Some(PointLight::new(Tuple::point(0., 10., 0.), WHITE)), vec![
+            Plane::new_boxed(None, Some(Matrix::translation(0., -1., 0.))),
+            Sphere::new_boxed(Some(Material { transparency: 1., refractive_index: 1.5, ..Default::default() }), None)]);
+        assert!(w.is_shadowed(floor_point, 0));
+
+        w.objects_mut()[1] = Sphere::new_boxed(Some(Material { transparency: 1., refractive_index: 1.5, casts_shadow: false, ..Default::default() }), None);
+        assert!(!w.is_shadowed(floor_point, 0));
+        // Looking at the floor just under the sphere, it shades as if the sphere weren't there
+        let r = Ray::new(Tuple::point(0., -0.5, -5.), Tuple::vector(0., -0.5, 4.5).normalize());
+        let floor_only = World::new(Some(PointLight::new(Tuple::point(0., 10., 0.), WHITE)), vec![w.objects[0].clone()]);
+        assert_eq!(w.color_at(r, MAX_REFLECTION_DEPTH), floor_only.color_at(r, MAX_REFLECTION_DEPTH));
+    }
+
+    #[test]
+    fn shadow_cache_reuses_results_across_camera_positions() {
+        use crate::camera::Camera;
+        use crate::consts::FRAC_PI_3;
+
+        let from = Tuple::point(0., 1.5, -5.);
+        let first = Camera::new(21, 21, FRAC_PI_3, Some(Matrix::view_transform(from, ORIGO, VECTOR_Y_UP)));
+        // Rolling the square view by 90 degrees revisits the same hit points in a different pixel order
+        let second = Camera::new(21, 21, FRAC_PI_3, Some(Matrix::view_transform(from, ORIGO, Tuple::vector(1., 0., 0.))));
+
+        // Rendered on this thread, so the shadow rays can be counted
+        let uncached_world = shadowed_floor_world();
+        let start = shadow_rays_on_this_thread();
+        let uncached = second.render_with_progress(&uncached_world, &RenderSettings::default(), |_, _| {});
+        let uncached_rays = shadow_rays_on_this_thread() - start;
+
+        let mut cached_world = shadowed_floor_world();
+        cached_world.enable_shadow_cache(EPSILON);
+        first.render_with_progress(&cached_world, &RenderSettings::default(), |_, _| {});
+        let start = shadow_rays_on_this_thread();
+        let cached = second.render_with_progress(&cached_world, &RenderSettings::default(), |_, _| {});
+
+        assert_eq!(cached.diff(&uncached).unwrap().1, 0.);
+        assert!(shadow_rays_on_this_thread() - start < uncached_rays);
+    }
+
+    #[test]
+    fn shadow_cache_is_invalidated_when_light_moves() {
+        let mut w = World::default_world();
+        w.enable_shadow_cache(EPSILON);
+        let p = Tuple::point(10., -10., 10.);
+        let start = shadow_rays_on_this_thread();
+        assert!(w.is_shadowed(p, 0));
+        assert!(w.is_shadowed(p, 0));
+
+        *w.lights_mut() = vec![PointLight::new_boxed(Tuple::point(20., -20., 20.), WHITE)];
+        assert!(!w.is_shadowed(p, 0));
+        assert_eq!(shadow_rays_on_this_thread() - start, 2);
+    }
+
+    fn two_lamp_world(lights: Vec<BoxLight>) -> World {
+        let ball = Sphere::new_boxed(None, Some(Matrix::translation(0., 1., 0.) * Matrix::scaling(0.5, 0.5, 0.5)));
+        World::with_lights(lights, vec![Plane::default_boxed(), ball])
+    }
+
+    #[test]
+    fn each_light_casts_its_own_shadow() {
+        let left = PointLight::new_boxed(Tuple::point(-10., 10., 0.), WHITE);
+        let right = PointLight::new_boxed(Tuple::point(10., 10., 0.), WHITE);
+        let w = two_lamp_world(vec![left.clone(), right.clone()]);
+        let right_of_ball = Tuple::point(10. / 9., 0.01, 0.);
+        let left_of_ball = Tuple::point(-10. / 9., 0.01, 0.);
+
+        assert!(w.is_shadowed(right_of_ball, 0) && !w.is_shadowed(right_of_ball, 1));
+        assert!(!w.is_shadowed(left_of_ball, 0) && w.is_shadowed(left_of_ball, 1));
+
+        // Each lamp's contribution, shadowed or not, simply adds up
+        let r = Ray::new(Tuple::point(10. / 9., 1., -5.), Tuple::vector(0., -1., 5.).normalize());
+        let both = w.color_at(r, MAX_REFLECTION_DEPTH);
+        let only_left = two_lamp_world(vec![left]).color_at(r, MAX_REFLECTION_DEPTH);
+        let only_right = two_lamp_world(vec![right]).color_at(r, MAX_REFLECTION_DEPTH);
+
+        assert_eq!(only_left, Color::new(0.1, 0.1, 0.1));
+        assert_eq!(both, only_left + only_right);
+    }
+
+    #[test]
+    fn directional_light_is_blocked_by_objects_at_any_distance() {
+        let sun = DirectionalLight::new_boxed(Tuple::vector(0., -1., 0.), WHITE);
+        let w = two_lamp_world(vec![sun]);
+
+        assert!(w.is_shadowed(Tuple::point(0., 0.01, 0.), 0));
+        assert!(!w.is_shadowed(Tuple::point(0.6, 0.01, 0.), 0));
+        assert!(w.validate().is_ok());
+    }
+
+    #[test]
+    fn overlapping_lights_brighten_additively() {
+        let light = PointLight::new_boxed(Tuple::point(-10., 10., -10.), WHITE);
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let single = World::with_lights(vec![light.clone()], World::default_objects()).color_at(r, MAX_REFLECTION_DEPTH);
+        let double = World::with_lights(vec![light.clone(), light], World::default_objects()).color_at(r, MAX_REFLECTION_DEPTH);
+
+        assert_eq!(single, Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(double, single * 2.);
+    }
+
+    #[test]
+    fn world_without_lights_shades_black() {
+        let w = World::with_lights(vec![], World::default_objects());
+        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        assert_eq!(w.color_at(r, MAX_REFLECTION_DEPTH), BLACK);
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_transparent_material() {
+        let mut w = World::default_world();
+        let floor_material = Material { reflective: 0.5, transparency: 0.5, refractive_index: 1.5, ..Default::default() };
+        let floor = Plane::new_boxed(Some(floor_material), Some(Matrix::translation(0., -1., 0.)));
+        let ball_material = Material { color: Color::new(1., 0., 0.), ambient: 0.5, ..Default::default() };
+        let ball = Sphere::new_boxed(Some(ball_material), Some(Matrix::translation(0., -3.5, -0.5)));
+        w.add_shape(floor.clone());
+        w.add_shape(ball);
+        let pv = Scalar::sqrt(2.0) / 2.0;
+        let r = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -pv, pv));
+        let xs = vec![IntersectionRef::new(Scalar::sqrt(2.0), &*floor)];
+        let comps = xs[0].into_owned().prepare_computations_with(r, &xs);
+
+        assert_eq!(w.shade_hit(comps, &RenderSettings::default(), MAX_REFLECTION_DEPTH), Color::new(0.93391, 0.69643, 0.69243));
+    }
+
+    #[test]
+    fn refraction_is_consistent_across_cap_rims_and_edges() {
+        use crate::cube::Cube;
+        use crate::cylinder::Cylinder;
+
+        let glass = Material { ambient: 0., diffuse: 0., specular: 0., transparency: 1., refractive_index: 1.5, ..Default::default() };
+        let cylinder: BoxShape = Box::new(Cylinder::new(Some(glass.clone()), None).truncated(-1., 1., true));
+        let cube = Cube::new_boxed(Some(glass), None);
+        let backdrop_material = Material { ambient: 1., diffuse: 0., specular: 0., ..Default::default() };
+        let backdrop = Plane::new_boxed(Some(backdrop_material), Some(Matrix::translation(0., -10., 0.)));
+        let light = Some(PointLight::new(Tuple::point(0., 10., -10.), WHITE));
+        let direction = Tuple::vector(0., -1., 1.).normalize();
+
+        // Rays sweep across the top rim (or edge) where side and cap hits share the same t
+        for shape in [cylinder, cube].iter() {
+            let w = World::new(light, vec![shape.clone(), backdrop.clone()]);
+            let rim_z = if shape.as_any().is::<Cube>() { -1. } else { -Scalar::sqrt(0.91) };
+            for i in -20..=20 {
+                let d = i as Scalar * EPSILON / 8.;
+                let r = Ray::new(Tuple::point(0.3, 2. + d, rim_z - 1.), direction);
+                let xs = w.intersect_ref(r);
+                let hits: Vec<_> = xs.iter().filter(|h| h.object.box_eq(shape.as_any())).collect();
+                assert_eq!(hits.len(), 2);
+
+                let exit = hits[1].into_owned().prepare_computations_with(r, &xs);
+                assert_eq!((exit.n1, exit.n2), (1.5, 1.));
+                assert_ne!(w.color_at(r, MAX_REFLECTION_DEPTH), BLACK);
+            }
+        }
+    }
+
+    #[test]
+    fn dividing_world_keeps_intersections() {
+        let mut objects = vec![Plane::new_boxed(None, Some(Matrix::translation(0., -3., 0.)))];
+        for i in 0..125 {
+            let (x, y, z) = ((i % 5) as Scalar, (i / 5 % 5) as Scalar, (i / 25) as Scalar);
+            objects.push(Sphere::new_boxed(None, Some(Matrix::translation(x - 2., y - 2., z - 2.) * Matrix::scaling(0.3, 0.3, 0.3))));
+        }
+        let flat = World::new(None, objects.clone());
+        let mut divided = World::new(None, objects);
+        divided.divide(4);
+
+        assert_eq!(divided.objects.len(), 2);
+        let mut sphere_hits = 0;
+        for i in 0..40 {
+            let a = i as Scalar * 0.37;
+            let r = Ray::new(Tuple::point(a.sin() * 8., 3., a.cos() * 8.), Tuple::vector(-a.sin(), -0.4 + 0.02 * i as Scalar, -a.cos()).normalize());
+            let hits = |w: &World| w.intersect_ref(r).iter().map(|i| (i.t, i.object.transformation())).collect::<Vec<_>>();
+            assert_eq!(hits(&flat), hits(&divided));
+            sphere_hits += hits(&flat).len().saturating_sub(1);
+        }
+        assert!(sphere_hits > 40);
+    }
+
+    #[test]
+    fn dividing_world_keeps_planes_at_top_and_nests_the_rest() {
+        let plane = Plane::new_boxed(None, Some(Matrix::translation(0., -3., 0.)));
+        let mut objects = vec![plane.clone()];
+        for i in 0..125 {
+            let (x, y, z) = ((i % 5) as Scalar, (i / 5 % 5) as Scalar, (i / 25) as Scalar);
+            objects.push(Sphere::new_boxed(None, Some(Matrix::translation(x - 2., y - 2., z - 2.) * Matrix::scaling(0.3, 0.3, 0.3))));
+        }
+        let mut w = World::new(None, objects);
+        w.divide(4);
+
+        assert_eq!(w.objects.len(), 2);
+        assert_eq!(&w.objects[0], &plane);
+        let root = w.objects[1].as_any().downcast_ref::<Group>().unwrap();
+        assert!(root.children().len() < 125);
+        let subgroup = root.children().iter().find_map(|c| c.as_any().downcast_ref::<Group>()).unwrap();
+        assert!(subgroup.children().iter().any(|c| c.as_any().is::<Group>()));
+    }
+
+    #[test]
+    fn area_light_visibility_is_fractional_in_penumbra() {
+        let mut w = World::default_world();
+        *w.lights_mut() = vec![AreaLight::new_boxed(Tuple::point(-0.5, -0.5, -5.), Tuple::vector(1., 0., 0.), 2, Tuple::vector(0., 1., 0.), 2, WHITE)];
+        let cases = [
+            (Tuple::point(0., 0., 2.), 0.),
+            (Tuple::point(1., -1., 2.), 0.25),
+            (Tuple::point(1.5, 0., 2.), 0.5),
+            (Tuple::point(1.25, 1.25, 3.), 0.75),
+            (Tuple::point(0., 0., -2.), 1.)];
+
+        for (point, expected) in cases.iter() {
+            assert_eq!(w.light_visibility(*point, 0), *expected);
+        }
+    }
+
+    #[test]
+    fn area_light_softens_shadow_edge() {
+        let floor = Plane::new_boxed(None, None);
+        let blocker = Sphere::new_boxed(None, Some(Matrix::translation(0., 2., 0.)));
+        let light = AreaLight::new(Tuple::point(-1., 5., -1.), Tuple::vector(2., 0., 0.), 4, Tuple::vector(0., 0., 2.), 4, WHITE).jittered();
+        let w = World::with_lights(vec![Box::new(light)], vec![floor, blocker]);
+        let visibility: Vec<Scalar> = (0..30).map(|i| w.light_visibility(Tuple::point(i as Scalar * 0.1, EPSILON, 0.), 0)).collect();
+
+        assert_eq!(visibility[0], 0.);
+        assert_eq!(visibility[29], 1.);
+        assert!(visibility.iter().any(|&v| v > 0. && v < 1.));
+        assert!(visibility.windows(2).all(|pair| pair[0] <= pair[1] + 0.25));
+    }
+}